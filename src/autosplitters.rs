@@ -1 +1,1132 @@
+pub mod cartridge;
+pub mod declarative;
+pub mod expression;
+pub mod recording;
 pub mod supermetroid;
+pub mod wasm;
+
+use crate::usb2snes::{SyncClient, MAX_OPERAND_SIZE};
+use cartridge::CartridgeHeader;
+use declarative::{DeclarativeAutoSplitter, Definition};
+use livesplit_core::TimeSpan;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
+use wasm::{WasmAutoSplitter, WasmModule};
+
+// usb2snes maps WRAM here
+const WRAM_START: u32 = 0xF50000;
+const WRAM_SIZE: usize = 0x20000;
+
+/// The games we know how to autosplit
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum Game {
+    #[default]
+    SuperMetroid,
+}
+
+impl Game {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Game::SuperMetroid => "Super Metroid",
+        }
+    }
+
+    /// A fresh autosplitter for this game, with no memory history
+    pub fn autosplitter(&self) -> Box<dyn AutoSplitter> {
+        match self {
+            Game::SuperMetroid => Box::new(supermetroid::SNESState::new()),
+        }
+    }
+
+    pub fn default_settings(&self) -> Settings {
+        self.autosplitter().default_settings()
+    }
+
+    /// Whether the cartridge header belongs to this game. Matching is done
+    /// on the title so that ROM hacks, which usually keep it, are recognized.
+    pub fn matches(&self, header: &CartridgeHeader) -> bool {
+        match self {
+            Game::SuperMetroid => header.title.to_uppercase().starts_with("SUPER METROID"),
+        }
+    }
+
+    pub fn detect(header: &CartridgeHeader) -> Option<Game> {
+        <Game as clap::ValueEnum>::value_variants()
+            .iter()
+            .find(|game| game.matches(header))
+            .copied()
+    }
+}
+
+/// The console's video standard, which decides how many frames the game
+/// counts per second of in-game time
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+impl Region {
+    pub fn frames_per_second(&self) -> u32 {
+        match self {
+            Region::Ntsc => 60,
+            Region::Pal => 50,
+        }
+    }
+}
+
+/// What the autosplitter runs: one of the built in games, a definition
+/// loaded from a file, or a WebAssembly module.
+#[derive(Clone)]
+pub enum AutoSplitterChoice {
+    Game(Game),
+    Definition(Arc<Definition>),
+    Wasm(Arc<WasmModule>),
+}
+
+impl AutoSplitterChoice {
+    pub fn name(&self) -> &str {
+        match self {
+            AutoSplitterChoice::Game(game) => game.name(),
+            AutoSplitterChoice::Definition(definition) => definition.name(),
+            AutoSplitterChoice::Wasm(module) => module.name(),
+        }
+    }
+
+    /// A fresh autosplitter, with no memory history
+    pub fn autosplitter(&self) -> Box<dyn AutoSplitter> {
+        match self {
+            AutoSplitterChoice::Game(game) => game.autosplitter(),
+            AutoSplitterChoice::Definition(definition) => {
+                Box::new(DeclarativeAutoSplitter::new(definition.clone()))
+            }
+            AutoSplitterChoice::Wasm(module) => Box::new(WasmAutoSplitter::new(module.clone())),
+        }
+    }
+
+    pub fn default_settings(&self) -> Settings {
+        match self {
+            AutoSplitterChoice::Game(game) => game.default_settings(),
+            AutoSplitterChoice::Definition(definition) => definition.default_settings(),
+            AutoSplitterChoice::Wasm(module) => module.default_settings(),
+        }
+    }
+}
+
+/// Decide whether to autosplit the cartridge that's in the console.
+/// We only split when the cartridge matches the game the settings were made
+/// for, otherwise we'd be firing splits off of unrelated memory.
+pub fn select_game(
+    header: &CartridgeHeader,
+    configured: &AutoSplitterChoice,
+) -> Option<AutoSplitterChoice> {
+    match (configured, Game::detect(header)) {
+        (AutoSplitterChoice::Definition(definition), _) => {
+            if definition.matches(&header.title) {
+                Some(configured.clone())
+            } else {
+                println!(
+                    "Cartridge {} does not match the definition for {}. Not splitting.",
+                    header,
+                    definition.name()
+                );
+                None
+            }
+        }
+        // Modules don't say which game they're for, and are expected to
+        // check for themselves
+        (AutoSplitterChoice::Wasm(_), _) => Some(configured.clone()),
+        (AutoSplitterChoice::Game(configured), Some(game)) if game == *configured => {
+            Some(AutoSplitterChoice::Game(game))
+        }
+        (AutoSplitterChoice::Game(configured), Some(game)) => {
+            println!(
+                "Cartridge {} is {}, but the autosplitter is configured for {}. Not splitting.",
+                header,
+                game.name(),
+                configured.name()
+            );
+            None
+        }
+        (AutoSplitterChoice::Game(_), None) => {
+            println!(
+                "Cartridge {} is not a game we can autosplit. Not splitting.",
+                header
+            );
+            None
+        }
+    }
+}
+
+/// The name of a condition that caused a split. For conditions enabled by
+/// a setting, this is the setting's key.
+pub type SplitReason = Arc<str>;
+
+/// The conditions behind one split. Several can fire for the same thing,
+/// like a Missile pack that's also the first Missile pickup.
+pub type SplitEvent = Vec<SplitReason>;
+
+/// Everything the polling thread needs to know about a game in order to
+/// autosplit it. Implementations own their memory watchers; the settings
+/// tree is shared with the UI and passed in on each poll.
+pub trait AutoSplitter {
+    /// The `(address, size)` pairs to read from the SNES on each poll
+    fn read_plan(&self) -> Vec<(u32, usize)>;
+    /// Feed the bytes returned for `read_plan` into the memory watchers
+    fn update(&mut self, snes_data: &[Vec<u8>]);
+    /// Whether the run should start. Settings may turn start conditions
+    /// off, so categories that start differently can pick theirs.
+    fn start(&self, settings: &Settings) -> bool;
+    /// Whether the run should reset, with the same settings as `start`
+    fn reset(&self, settings: &Settings) -> bool;
+    /// The conditions that want to split, if any. Called once per poll,
+    /// after `update` and before `start` and `reset`. Everything returned
+    /// is applied, grouped into splits by [`Settings::group_events`].
+    fn split(&mut self, settings: &Settings) -> Vec<SplitReason>;
+    /// The reasons for earlier splits whose progress has since been lost,
+    /// such as by dying and reloading a save, oldest first. A reason is
+    /// reported once for each time `split` returned it. Called once per
+    /// poll after `split`; autosplitters that can't tell report nothing.
+    fn reverted(&mut self, _settings: &Settings) -> Vec<SplitReason> {
+        vec![]
+    }
+    /// The in-game time, for games that keep one. The timer's game time
+    /// follows it on every poll while a run is in progress.
+    fn gametime_to_seconds(&self) -> Option<TimeSpan>;
+    /// What the polling thread knows about the console's region. A region
+    /// chosen by the `user` wins over anything the autosplitter detects on
+    /// its own, while the one from the cartridge `header` is only a hint.
+    fn set_region(&mut self, _user: Option<Region>, _header: Option<Region>) {}
+    /// The settings tree this autosplitter understands, with default values
+    fn default_settings(&self) -> Settings;
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Width {
+    Byte,
+    Word,
+    /// 24 bits, the size of a SNES pointer
+    Long,
+    Dword,
+}
+
+impl Width {
+    fn size(&self) -> usize {
+        match self {
+            Width::Byte => 1,
+            Width::Word => 2,
+            Width::Long => 3,
+            Width::Dword => 4,
+        }
+    }
+}
+
+/// How the bytes under a watcher are turned into a value
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Unsigned,
+    /// Two's complement
+    Signed,
+    /// One decimal digit per nibble, as some games keep their timers
+    Bcd,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryWatcher {
+    address: u32,
+    current: i64,
+    old: i64,
+    width: Width,
+    encoding: Encoding,
+    // Only the bits under the mask, shifted down to bit 0
+    mask: Option<u32>,
+    history: History,
+}
+
+impl MemoryWatcher {
+    pub fn new(address: u32, width: Width) -> MemoryWatcher {
+        MemoryWatcher {
+            address,
+            current: 0,
+            old: 0,
+            width,
+            encoding: Encoding::Unsigned,
+            mask: None,
+            history: History::default(),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> MemoryWatcher {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Watch a bitfield. The value is the masked bits shifted down, and a
+    /// signed bitfield's sign bit is the mask's highest bit.
+    pub fn with_mask(mut self, mask: u32) -> MemoryWatcher {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Keep the last `polls` values, on top of `old` and `current`
+    pub fn with_history(mut self, polls: usize) -> MemoryWatcher {
+        self.history = History::new(polls);
+        self
+    }
+
+    fn decode(&self, memory: &[u8]) -> i64 {
+        let address = self.address as usize;
+        let size = self.width.size();
+        let mut raw = memory[address..address + size]
+            .iter()
+            .rev()
+            .fold(0u32, |raw, &byte| raw << 8 | byte as u32);
+        let mut bits = size as u32 * 8;
+        if let Some(mask) = self.mask {
+            raw = (raw & mask).checked_shr(mask.trailing_zeros()).unwrap_or(0);
+            bits = (32 - mask.leading_zeros()).saturating_sub(mask.trailing_zeros());
+        }
+        match self.encoding {
+            Encoding::Unsigned => raw as i64,
+            Encoding::Signed if bits == 0 => 0,
+            Encoding::Signed => {
+                let unused = 64 - bits;
+                ((raw as i64) << unused) >> unused
+            }
+            Encoding::Bcd => (0..bits.div_ceil(4)).rev().fold(0, |value, digit| {
+                value * 10 + (raw >> (digit * 4) & 0xF) as i64
+            }),
+        }
+    }
+
+    fn update_value(&mut self, memory: &[u8]) {
+        self.old = self.current;
+        self.current = self.decode(memory);
+        self.history.push(self.current);
+    }
+
+    /// Whether the value changed in any of the last `polls` polls, as far
+    /// back as the history goes. The last poll is covered even without
+    /// history.
+    pub fn changed_within(&self, polls: usize) -> bool {
+        if polls == 0 {
+            return false;
+        }
+        if self.old != self.current {
+            return true;
+        }
+        let values = &self.history.values;
+        let first = values.len().saturating_sub(polls + 1);
+        (first + 1..values.len()).any(|i| values[i - 1].0 != values[i].0)
+    }
+
+    /// Whether the value was `value` in any of the last `polls` polls
+    /// before the current one, as far back as the history goes. The last
+    /// poll is covered even without history.
+    pub fn was_within(&self, value: i64, polls: usize) -> bool {
+        if polls == 0 {
+            return false;
+        }
+        if self.old == value {
+            return true;
+        }
+        let values = &self.history.values;
+        let last = values.len().saturating_sub(1);
+        values
+            .range(last.saturating_sub(polls)..last)
+            .any(|&(v, _)| v == value)
+    }
+
+    /// When the value last changed, if that's still in the history
+    pub fn last_changed(&self) -> Option<Instant> {
+        let values = &self.history.values;
+        (1..values.len())
+            .rev()
+            .find(|&i| values[i - 1].0 != values[i].0)
+            .map(|i| values[i].1)
+    }
+}
+
+/// The most recent values of a watcher and when they were read, oldest
+/// first
+#[derive(Debug, Clone, Default)]
+struct History {
+    capacity: usize,
+    values: VecDeque<(i64, Instant)>,
+}
+
+impl History {
+    fn new(polls: usize) -> History {
+        History {
+            // The current value, plus the polls before it
+            capacity: polls + 1,
+            values: VecDeque::with_capacity(polls + 1),
+        }
+    }
+
+    fn push(&mut self, value: i64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back((value, Instant::now()));
+    }
+}
+
+// Reading a few bytes nobody watches is cheaper than another range in
+// the request, so watchers this close together share one
+const MAX_READ_GAP: u32 = 32;
+
+/// The fewest WRAM ranges that cover every watcher, as `(address, size)`
+/// pairs for usb2snes. Ranges are kept short enough to be read together
+/// with others in one request.
+pub fn plan_reads<'a>(watchers: impl IntoIterator<Item = &'a MemoryWatcher>) -> Vec<(u32, usize)> {
+    plan_ranges(
+        watchers
+            .into_iter()
+            .map(|w| (WRAM_START + w.address, w.width.size())),
+    )
+}
+
+/// Merge usb2snes `(address, size)` ranges the way [`plan_reads`] does.
+/// A range already too long to share a request is kept whole.
+pub fn plan_ranges(ranges: impl IntoIterator<Item = (u32, usize)>) -> Vec<(u32, usize)> {
+    let mut spans: Vec<(u32, u32)> = ranges
+        .into_iter()
+        .map(|(address, size)| (address, address + size as u32))
+        .collect();
+    spans.sort_unstable();
+    let mut plan: Vec<(u32, usize)> = vec![];
+    for (start, end) in spans {
+        if let Some((last_start, last_size)) = plan.last_mut() {
+            let last_end = *last_start + *last_size as u32;
+            let merged = (end.max(last_end) - *last_start) as usize;
+            if start <= last_end + MAX_READ_GAP && merged <= MAX_OPERAND_SIZE {
+                *last_size = merged;
+                continue;
+            }
+        }
+        plan.push((start, (end - start) as usize));
+    }
+    plan
+}
+
+/// Copy the bytes read for a plan from [`plan_reads`] into a WRAM image
+pub fn store_reads(plan: &[(u32, usize)], snes_data: &[Vec<u8>], memory: &mut [u8]) {
+    for (&(address, size), bytes) in plan.iter().zip(snes_data) {
+        let offset = (address - WRAM_START) as usize;
+        memory[offset..offset + size].copy_from_slice(&bytes[..size]);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SNESSummary {
+    pub latency_average: f32,
+    pub latency_stddev: f32,
+    pub start: bool,
+    pub reset: bool,
+    /// One entry per split to make, empty if we shouldn't split
+    pub split_events: Vec<SplitEvent>,
+    /// The reasons for earlier splits that should be undone
+    pub reverted: Vec<SplitReason>,
+}
+
+/// Whether every reason for a split is among the `reverted` reasons,
+/// taking them out if so. Going back through the splits from the latest,
+/// this matches the reverted reasons up with the splits they made, and
+/// stops at the first split that still stands.
+pub fn take_reverted(reverted: &mut Vec<SplitReason>, reasons: &[SplitReason]) -> bool {
+    let mut remaining = reverted.clone();
+    for reason in reasons {
+        match remaining.iter().rposition(|r| r == reason) {
+            Some(i) => {
+                remaining.remove(i);
+            }
+            None => return false,
+        }
+    }
+    *reverted = remaining;
+    true
+}
+
+const NUM_LATENCY_SAMPLES: usize = 10;
+
+#[derive(Clone, Default)]
+pub struct LatencySamples {
+    samples: VecDeque<u128>,
+}
+
+impl LatencySamples {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new sample and return the (average, stddev) of the window.
+    /// One sample has no spread, so its stddev is 0.
+    fn record(&mut self, elapsed: u128) -> (f32, f32) {
+        if self.samples.len() == NUM_LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(elapsed);
+        let average_latency: f32 =
+            self.samples.iter().sum::<u128>() as f32 / self.samples.len() as f32;
+        let mut s = 0;
+        for x in self.samples.iter() {
+            let y = *x as i128;
+            let avg = average_latency as i128;
+            let diff = y - avg;
+            s += diff * diff;
+        }
+        let stddev = if self.samples.len() < 2 {
+            0.0
+        } else {
+            (s as f32 / (self.samples.len() as f32 - 1.0)).sqrt()
+        };
+        (average_latency, stddev)
+    }
+}
+
+/// Somewhere to read SNES memory from: a usb2snes connection, or a
+/// recording of one
+pub trait MemorySource {
+    fn get_addresses(&mut self, pairs: &[(u32, usize)]) -> Result<Vec<Vec<u8>>, Box<dyn Error>>;
+    /// Reset the game, if there is a console to reset
+    fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+impl MemorySource for SyncClient {
+    fn get_addresses(&mut self, pairs: &[(u32, usize)]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        Ok(SyncClient::get_addresses(self, pairs)?)
+    }
+
+    fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(SyncClient::reset(self)?)
+    }
+}
+
+/// Read the SNES memory the autosplitter asks for and evaluate its
+/// start, reset, and split conditions against the new values.
+pub fn fetch_all(
+    autosplitter: &mut dyn AutoSplitter,
+    client: &mut dyn MemorySource,
+    settings: &Settings,
+    latency: &mut LatencySamples,
+) -> Result<SNESSummary, Box<dyn Error>> {
+    let start_time = Instant::now();
+    let read_plan = autosplitter.read_plan();
+    // usb2snes never answers a request for nothing
+    let snes_data = if read_plan.is_empty() {
+        vec![]
+    } else {
+        client.get_addresses(&read_plan)?
+    };
+    autosplitter.update(&snes_data);
+    // Split first: WebAssembly autosplitters only run their update there,
+    // which is also where they decide to start or reset.
+    let split_events = settings.group_events(autosplitter.split(settings));
+    let reverted = autosplitter.reverted(settings);
+    let start = autosplitter.start(settings);
+    let reset = autosplitter.reset(settings);
+    let (latency_average, latency_stddev) = latency.record(start_time.elapsed().as_millis());
+    Ok(SNESSummary {
+        latency_average,
+        latency_stddev,
+        start,
+        reset,
+        split_events,
+        reverted,
+    })
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Settings {
+    data: HashMap<std::sync::Arc<str>, (bool, Option<String>)>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    descriptions: HashMap<std::sync::Arc<str>, String>,
+    /// In ordered mode, a segment with bindings only splits on the
+    /// conditions (setting keys) bound to it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ordered: bool,
+    /// The setting keys bound to each segment, by segment index
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    segment_bindings: Vec<Vec<String>>,
+    #[serde(skip)]
+    modified_after_creation: bool,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings {
+            data: HashMap::new(),
+            descriptions: HashMap::new(),
+            ordered: false,
+            segment_bindings: vec![],
+            modified_after_creation: false,
+        }
+    }
+
+    fn insert(&mut self, name: &str, value: bool) {
+        self.modified_after_creation = true;
+        self.data.insert(name.into(), (value, None));
+    }
+
+    fn insert_with_parent(&mut self, name: &str, value: bool, parent: &str) {
+        self.modified_after_creation = true;
+        self.data
+            .insert(name.into(), (value, Some(parent.to_owned())));
+    }
+
+    fn describe(&mut self, name: &str, description: &str) {
+        self.descriptions
+            .insert(name.into(), description.to_owned());
+    }
+
+    fn contains(&self, var: &str) -> bool {
+        self.data.contains_key(var)
+    }
+
+    fn get(&self, var: &str) -> bool {
+        match self.data.get(var) {
+            None => false,
+            Some((b, None)) => *b,
+            Some((b, Some(ref p))) => *b && self.get(p),
+        }
+    }
+
+    fn set(&mut self, var: &str, value: bool) {
+        let val = match self.data.get_mut(var) {
+            None => (value, None),
+            Some((_, x)) => (value, x.clone()),
+        };
+        self.data.insert(std::sync::Arc::from(var), val);
+    }
+
+    /// Add any settings from `defaults` that are missing, such as ones
+    /// added to the autosplitter after these settings were saved
+    pub fn add_missing(&mut self, defaults: &Settings) {
+        for (key, value) in defaults.data.iter() {
+            if !self.data.contains_key(key) {
+                self.data.insert(key.clone(), value.clone());
+            }
+        }
+        for (key, description) in defaults.descriptions.iter() {
+            if !self.descriptions.contains_key(key) {
+                self.descriptions.insert(key.clone(), description.clone());
+            }
+        }
+    }
+
+    /// Every key, sorted
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.data.keys().map(|k| k.to_string()).collect();
+        keys.sort();
+        keys
+    }
+
+    /// The keys which have no parent defined
+    pub fn roots(&self) -> Vec<String> {
+        let mut rs = vec![];
+        for (key, (_, parent)) in self.data.iter() {
+            if parent.is_none() {
+                rs.push(key.to_string());
+            }
+        }
+        rs
+    }
+
+    /// The immediate childern (if any)
+    pub fn children(&self, key: &str) -> Vec<String> {
+        let mut rs = vec![];
+        for (k, (_, parent)) in self.data.iter() {
+            if let Some(parent) = parent {
+                if key == parent {
+                    rs.push(k.to_string())
+                }
+            }
+        }
+        rs
+    }
+
+    pub fn lookup(&mut self, var: &str) -> bool {
+        match self.data.get(var) {
+            None => panic!(),
+            Some((b, _)) => *b,
+        }
+    }
+
+    pub fn lookup_mut(&mut self, var: &str) -> &mut bool {
+        // TODO: this is a conservative overapproximation. We don't actually
+        // know if the caller wrote to the &mut bool we gave them.
+        self.modified_after_creation = true;
+        match self.data.get_mut(var) {
+            None => panic!(),
+            Some((b, _)) => b,
+        }
+    }
+
+    /// Help text for a setting, if its autosplitter provided any
+    pub fn description(&self, key: &str) -> Option<&str> {
+        self.descriptions.get(key).map(|d| d.as_str())
+    }
+
+    pub fn has_been_modified(&self) -> bool {
+        self.modified_after_creation
+    }
+
+    pub fn ordered(&self) -> bool {
+        self.ordered
+    }
+
+    pub fn set_ordered(&mut self, ordered: bool) {
+        self.modified_after_creation = true;
+        self.ordered = ordered;
+    }
+
+    /// The setting keys bound to a segment
+    pub fn bindings(&self, segment: usize) -> &[String] {
+        self.segment_bindings
+            .get(segment)
+            .map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn bind(&mut self, segment: usize, key: &str) {
+        self.modified_after_creation = true;
+        if self.segment_bindings.len() <= segment {
+            self.segment_bindings.resize(segment + 1, vec![]);
+        }
+        if !self.segment_bindings[segment].iter().any(|k| k == key) {
+            self.segment_bindings[segment].push(key.to_owned());
+        }
+    }
+
+    pub fn unbind(&mut self, segment: usize, key: &str) {
+        self.modified_after_creation = true;
+        if let Some(keys) = self.segment_bindings.get_mut(segment) {
+            keys.retain(|k| k != key);
+        }
+    }
+
+    /// Whether `key` is `ancestor` or one of its descendants
+    fn is_within(&self, key: &str, ancestor: &str) -> bool {
+        if key == ancestor {
+            return true;
+        }
+        match self.data.get(key) {
+            Some((_, Some(parent))) => self.is_within(parent, ancestor),
+            _ => false,
+        }
+    }
+
+    /// The reasons that are allowed to split the current segment. Outside
+    /// of ordered mode, or for a segment with no bindings, that's all of
+    /// them. Binding a setting allows all of its children as well.
+    pub fn bound_reasons(
+        &self,
+        segment: Option<usize>,
+        reasons: Vec<SplitReason>,
+    ) -> Vec<SplitReason> {
+        let bindings = match segment {
+            Some(segment) if self.ordered => self.bindings(segment),
+            _ => return reasons,
+        };
+        if bindings.is_empty() {
+            return reasons;
+        }
+        reasons
+            .into_iter()
+            .filter(|reason| bindings.iter().any(|key| self.is_within(reason, key)))
+            .collect()
+    }
+
+    /// The top level setting `key` is under, if it's a setting
+    fn root(&self, key: &str) -> Option<&str> {
+        let (key, (_, parent)) = self.data.get_key_value(key)?;
+        match parent {
+            Some(parent) => self.root(parent),
+            None => Some(key),
+        }
+    }
+
+    /// Split the conditions that fired in one poll into separate splits.
+    /// Conditions under the same top level setting are one split, since
+    /// they're usually the same thing seen from different angles, while
+    /// anything under another top level setting happened separately.
+    /// Conditions that aren't settings at all each get a split of their
+    /// own.
+    pub fn group_events(&self, reasons: Vec<SplitReason>) -> Vec<SplitEvent> {
+        let mut events: Vec<(Option<&str>, SplitEvent)> = vec![];
+        for reason in reasons {
+            let root = self.root(&reason);
+            match events
+                .iter_mut()
+                .find(|(r, _)| root.is_some() && *r == root)
+            {
+                Some((_, event)) => event.push(reason),
+                None => events.push((root, vec![reason])),
+            }
+        }
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// Decide which segments the split events of one poll split, starting
+    /// with `segment` and moving on one segment per split. Each segment
+    /// takes the first remaining event that's bound to it, since there's
+    /// no telling which order things happened in within one poll. Returns
+    /// the reasons for each split in order, and the events left over.
+    pub fn assign_splits(
+        &self,
+        segment: Option<usize>,
+        mut events: Vec<SplitEvent>,
+    ) -> (Vec<Vec<SplitReason>>, Vec<SplitEvent>) {
+        let mut splits = vec![];
+        loop {
+            let index = segment.map(|s| s + splits.len());
+            let found = events.iter().enumerate().find_map(|(i, event)| {
+                let reasons = self.bound_reasons(index, event.clone());
+                (!reasons.is_empty()).then_some((i, reasons))
+            });
+            match found {
+                Some((i, reasons)) => {
+                    events.remove(i);
+                    splits.push(reasons);
+                }
+                None => return (splits, events),
+            }
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `watcher` reads from memory holding `bytes` at its address
+    fn decode(watcher: MemoryWatcher, bytes: &[u8]) -> i64 {
+        let mut memory = vec![0; 8];
+        memory[..bytes.len()].copy_from_slice(bytes);
+        watcher.decode(&memory)
+    }
+
+    #[test]
+    fn little_endian_widths() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+        assert_eq!(decode(MemoryWatcher::new(0, Width::Byte), &bytes), 0x01);
+        assert_eq!(decode(MemoryWatcher::new(0, Width::Word), &bytes), 0x0201);
+        assert_eq!(decode(MemoryWatcher::new(0, Width::Long), &bytes), 0x030201);
+        assert_eq!(
+            decode(MemoryWatcher::new(0, Width::Dword), &bytes),
+            0x04030201
+        );
+        assert_eq!(decode(MemoryWatcher::new(1, Width::Word), &bytes), 0x0302);
+    }
+
+    #[test]
+    fn signed_values_are_sign_extended() {
+        let signed = |width| MemoryWatcher::new(0, width).with_encoding(Encoding::Signed);
+        let ones = [0xFF; 4];
+        for width in [Width::Byte, Width::Word, Width::Long, Width::Dword] {
+            assert_eq!(decode(signed(width), &ones), -1, "{:?}", width);
+        }
+        assert_eq!(decode(signed(Width::Byte), &[0x80]), -128);
+        assert_eq!(decode(signed(Width::Byte), &[0x7F]), 127);
+        assert_eq!(decode(signed(Width::Word), &[0x00, 0x80]), -0x8000);
+        assert_eq!(decode(signed(Width::Word), &[0xFF, 0x7F]), 0x7FFF);
+        assert_eq!(decode(signed(Width::Long), &[0x00, 0x00, 0x80]), -0x800000);
+        assert_eq!(
+            decode(signed(Width::Long), &[0xFF, 0xFF, 0x7F, 0xFF]),
+            0x7FFFFF
+        );
+        assert_eq!(decode(signed(Width::Dword), &[0, 0, 0, 0x80]), -0x80000000);
+        // Unsigned reads of the same bytes stay positive
+        assert_eq!(
+            decode(MemoryWatcher::new(0, Width::Dword), &ones),
+            0xFFFFFFFF
+        );
+    }
+
+    #[test]
+    fn bcd_values() {
+        let bcd = |width| MemoryWatcher::new(0, width).with_encoding(Encoding::Bcd);
+        assert_eq!(decode(bcd(Width::Byte), &[0x59]), 59);
+        assert_eq!(decode(bcd(Width::Word), &[0x34, 0x12]), 1234);
+        assert_eq!(
+            decode(bcd(Width::Dword), &[0x78, 0x56, 0x34, 0x12]),
+            12345678
+        );
+        // Nibbles past 9 aren't valid BCD. They still count for their
+        // value in their place, rather than failing the poll.
+        assert_eq!(decode(bcd(Width::Byte), &[0x1A]), 20);
+        assert_eq!(decode(bcd(Width::Byte), &[0xFF]), 165);
+        assert_eq!(
+            decode(bcd(Width::Dword), &[0xFF; 4]),
+            (0..8).map(|digit| 15 * 10i64.pow(digit)).sum::<i64>()
+        );
+    }
+
+    #[test]
+    fn masks_apply_before_the_encoding() {
+        let masked = |mask, encoding| {
+            MemoryWatcher::new(0, Width::Word)
+                .with_mask(mask)
+                .with_encoding(encoding)
+        };
+        assert_eq!(decode(masked(0x0C, Encoding::Unsigned), &[0b1110]), 0b11);
+        assert_eq!(decode(masked(0x0C, Encoding::Unsigned), &[0b0100]), 0b01);
+        // The mask's highest bit is the sign bit
+        assert_eq!(decode(masked(0x0C, Encoding::Signed), &[0b1000]), -2);
+        assert_eq!(decode(masked(0x0C, Encoding::Signed), &[0b0100]), 1);
+        assert_eq!(decode(masked(0x0FF0, Encoding::Bcd), &[0x59, 0x02]), 25);
+        // Masks can span bytes
+        assert_eq!(
+            decode(masked(0x0FF0, Encoding::Unsigned), &[0x59, 0x02]),
+            0x25
+        );
+        assert_eq!(decode(masked(0, Encoding::Signed), &[0xFF, 0xFF]), 0);
+    }
+
+    /// A watcher on WRAM byte 0 that has read each of `values`
+    fn watcher_with(history: usize, values: &[u8]) -> MemoryWatcher {
+        let mut watcher = MemoryWatcher::new(0, Width::Byte).with_history(history);
+        for &value in values {
+            watcher.update_value(&[value]);
+        }
+        watcher
+    }
+
+    #[test]
+    fn changed_within_looks_back_as_far_as_asked() {
+        let watcher = watcher_with(10, &[1, 2, 2, 2, 2]);
+        assert!(!watcher.changed_within(0));
+        assert!(!watcher.changed_within(3));
+        assert!(watcher.changed_within(4));
+        assert!(watcher.changed_within(100));
+        // The last poll counts even without history
+        let watcher = watcher_with(0, &[1, 2]);
+        assert!(watcher.changed_within(1));
+        assert!(!watcher_with(0, &[1, 2, 2]).changed_within(10));
+    }
+
+    #[test]
+    fn was_within_skips_the_current_value() {
+        let watcher = watcher_with(10, &[1, 2, 3, 4]);
+        assert!(watcher.was_within(3, 1));
+        assert!(!watcher.was_within(4, 10));
+        assert!(!watcher.was_within(1, 2));
+        assert!(watcher.was_within(1, 3));
+        assert!(!watcher.was_within(3, 0));
+        // The last poll counts even without history
+        let watcher = watcher_with(0, &[1, 2, 3]);
+        assert!(watcher.was_within(2, 1));
+        assert!(!watcher.was_within(1, 10));
+    }
+
+    #[test]
+    fn history_forgets_past_its_capacity() {
+        let watcher = watcher_with(3, &[1, 2, 2, 2]);
+        assert!(watcher.changed_within(3));
+        assert!(watcher.last_changed().is_some());
+        // The change from 1 to 2 has wrapped out of the history
+        let watcher = watcher_with(3, &[1, 2, 2, 2, 2]);
+        assert_eq!(watcher.history.values.len(), 4);
+        assert!(!watcher.changed_within(10));
+        assert!(watcher.last_changed().is_none());
+        // Only the newest values are kept, oldest first
+        let watcher = watcher_with(2, &[1, 2, 3, 4, 5]);
+        let kept: Vec<i64> = watcher.history.values.iter().map(|&(v, _)| v).collect();
+        assert_eq!(kept, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn last_changed_is_the_most_recent_change() {
+        let watcher = watcher_with(10, &[1, 2, 2, 3, 3]);
+        let (_, when) = watcher.history.values[3];
+        assert_eq!(watcher.last_changed(), Some(when));
+        assert!(watcher_with(10, &[1, 1, 1]).last_changed().is_none());
+        assert!(watcher_with(0, &[1, 2]).last_changed().is_none());
+    }
+
+    #[test]
+    fn latency_spread_needs_two_samples() {
+        let mut latency = LatencySamples::new();
+        assert_eq!(latency.record(20), (20.0, 0.0));
+        assert_eq!(latency.record(40), (30.0, 200f32.sqrt()));
+        // Only the newest samples count
+        for _ in 0..NUM_LATENCY_SAMPLES {
+            latency.record(10);
+        }
+        assert_eq!(latency.record(10), (10.0, 0.0));
+    }
+
+    fn reasons(names: &[&str]) -> Vec<SplitReason> {
+        names.iter().map(|&name| SplitReason::from(name)).collect()
+    }
+
+    fn settings() -> Settings {
+        let mut settings = Settings::new();
+        settings.insert("bosses", true);
+        settings.insert_with_parent("kraid", true, "bosses");
+        settings.insert_with_parent("ridley", true, "bosses");
+        settings.insert("ammo", true);
+        settings.insert_with_parent("missiles", true, "ammo");
+        settings.insert_with_parent("firstMissile", true, "missiles");
+        settings.insert_with_parent("allMissiles", true, "missiles");
+        settings.insert("miniBosses", true);
+        settings.insert_with_parent("sporeSpawn", true, "miniBosses");
+        settings
+    }
+
+    #[test]
+    fn events_are_grouped_by_top_level_setting() {
+        let events = settings().group_events(reasons(&[
+            "firstMissile",
+            "ridley",
+            "allMissiles",
+            "roomID == 5",
+            "roomID == 6",
+        ]));
+        assert_eq!(
+            events,
+            vec![
+                reasons(&["firstMissile", "allMissiles"]),
+                reasons(&["ridley"]),
+                reasons(&["roomID == 5"]),
+                reasons(&["roomID == 6"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn bindings_only_apply_in_ordered_mode() {
+        let mut settings = settings();
+        settings.bind(1, "missiles");
+        let all = reasons(&["kraid", "firstMissile"]);
+        assert_eq!(settings.bound_reasons(Some(1), all.clone()), all);
+        settings.set_ordered(true);
+        // Binding a setting binds its children
+        assert_eq!(
+            settings.bound_reasons(Some(1), all.clone()),
+            reasons(&["firstMissile"])
+        );
+        // Segments without bindings split on anything
+        assert_eq!(settings.bound_reasons(Some(0), all.clone()), all);
+        assert_eq!(settings.bound_reasons(Some(2), all.clone()), all);
+        assert_eq!(settings.bound_reasons(None, all.clone()), all);
+        settings.unbind(1, "missiles");
+        assert_eq!(settings.bound_reasons(Some(1), all.clone()), all);
+    }
+
+    #[test]
+    fn ordered_splits_take_the_event_bound_to_each_segment() {
+        let mut settings = settings();
+        settings.set_ordered(true);
+        settings.bind(0, "bosses");
+        settings.bind(1, "ammo");
+        settings.bind(2, "miniBosses");
+        let events = vec![
+            reasons(&["firstMissile"]),
+            reasons(&["ridley"]),
+            reasons(&["roomID == 5"]),
+        ];
+        let (splits, unbound) = settings.assign_splits(Some(0), events);
+        assert_eq!(
+            splits,
+            vec![reasons(&["ridley"]), reasons(&["firstMissile"])]
+        );
+        assert_eq!(unbound, vec![reasons(&["roomID == 5"])]);
+    }
+
+    #[test]
+    fn unordered_splits_take_every_event() {
+        let events = vec![reasons(&["firstMissile"]), reasons(&["ridley"])];
+        let (splits, unbound) = settings().assign_splits(Some(0), events.clone());
+        assert_eq!(splits, events);
+        assert!(unbound.is_empty());
+    }
+
+    #[test]
+    fn nearby_watchers_share_a_read() {
+        let watchers = [
+            MemoryWatcher::new(0x10, Width::Word),
+            MemoryWatcher::new(0x11, Width::Byte),
+            MemoryWatcher::new(0x10 + 2 + MAX_READ_GAP, Width::Byte),
+        ];
+        assert_eq!(
+            plan_reads(&watchers),
+            vec![(WRAM_START + 0x10, 3 + MAX_READ_GAP as usize)]
+        );
+    }
+
+    #[test]
+    fn distant_watchers_get_their_own_reads() {
+        let watchers = [
+            MemoryWatcher::new(0x900, Width::Dword),
+            MemoryWatcher::new(0x10, Width::Byte),
+            MemoryWatcher::new(0x10 + 1 + MAX_READ_GAP + 1, Width::Byte),
+        ];
+        assert_eq!(
+            plan_reads(&watchers),
+            vec![
+                (WRAM_START + 0x10, 1),
+                (WRAM_START + 0x10 + 1 + MAX_READ_GAP + 1, 1),
+                (WRAM_START + 0x900, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_stay_short_enough_to_share_a_request() {
+        let watchers: Vec<MemoryWatcher> = (0..0x400)
+            .step_by(16)
+            .map(|address| MemoryWatcher::new(address, Width::Word))
+            .collect();
+        let plan = plan_reads(&watchers);
+        assert!(plan.len() > 1);
+        assert!(plan.iter().all(|&(_, size)| size <= MAX_OPERAND_SIZE));
+        for w in watchers.iter() {
+            let (start, end) = (WRAM_START + w.address, WRAM_START + w.address + 2);
+            assert!(plan
+                .iter()
+                .any(|&(address, size)| address <= start && end <= address + size as u32));
+        }
+    }
+
+    #[test]
+    fn overlapping_ranges_are_read_once() {
+        assert_eq!(
+            plan_ranges([(0x100, 4), (0x102, 4), (0x100, 1), (0x100, 4)]),
+            vec![(0x100, 6)]
+        );
+        // Too long to merge with anything, but still read
+        let long = MAX_OPERAND_SIZE + 1;
+        assert_eq!(
+            plan_ranges([(0x100, long), (0x100 + long as u32, 1)]),
+            vec![(0x100, long), (0x100 + long as u32, 1)]
+        );
+    }
+
+    #[test]
+    fn stored_reads_land_where_they_were_read_from() {
+        let plan = [(WRAM_START + 0x10, 2), (WRAM_START + 0x100, 1)];
+        let mut memory = vec![0; WRAM_SIZE];
+        store_reads(&plan, &[vec![1, 2], vec![3]], &mut memory);
+        assert_eq!(&memory[0x10..0x12], &[1, 2]);
+        assert_eq!(memory[0x100], 3);
+        assert_eq!(memory.iter().filter(|&&b| b != 0).count(), 3);
+    }
+}
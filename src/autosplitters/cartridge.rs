@@ -0,0 +1,194 @@
+use super::{MemorySource, Region};
+use std::error::Error;
+use std::fmt;
+
+// The internal header lives at $00:FFC0 in the SNES address space. In the
+// usb2snes address space ROM starts at 0, so where that lands depends on
+// the mapping mode of the cartridge.
+const LOROM_HEADER: u32 = 0x7FC0;
+const HIROM_HEADER: u32 = 0xFFC0;
+const HEADER_SIZE: usize = 0x20;
+const TITLE_SIZE: usize = 21;
+
+/// The parts of the SNES internal cartridge header we use to figure out
+/// what game is running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub region: u8,
+    pub version: u8,
+    pub checksum: u16,
+}
+
+impl CartridgeHeader {
+    /// Read the header of whatever is currently loaded on the attached device
+    pub fn read(client: &mut dyn MemorySource) -> Result<CartridgeHeader, Box<dyn Error>> {
+        let data =
+            client.get_addresses(&[(LOROM_HEADER, HEADER_SIZE), (HIROM_HEADER, HEADER_SIZE)])?;
+        let lorom = CartridgeHeader::parse(&data[0]);
+        let hirom = CartridgeHeader::parse(&data[1]);
+        // A header whose checksum and complement agree is almost certainly
+        // the real one. ROM hacks frequently don't bother fixing the
+        // checksum, so fall back to whichever one has a readable title.
+        let header = [&lorom, &hirom]
+            .into_iter()
+            .find(|(_, valid)| *valid)
+            .or_else(|| {
+                [&lorom, &hirom]
+                    .into_iter()
+                    .find(|(h, _)| h.has_printable_title())
+            })
+            .unwrap_or(&lorom);
+        Ok(header.0.clone())
+    }
+
+    /// Parse the 32 bytes at $FFC0. The `bool` is whether the checksum
+    /// and its complement are consistent.
+    pub fn parse(bytes: &[u8]) -> (CartridgeHeader, bool) {
+        let title = bytes[..TITLE_SIZE]
+            .iter()
+            .map(|&b| b as char)
+            .collect::<String>()
+            .trim_end()
+            .to_owned();
+        let complement = u16::from_le_bytes([bytes[0x1C], bytes[0x1D]]);
+        let checksum = u16::from_le_bytes([bytes[0x1E], bytes[0x1F]]);
+        (
+            CartridgeHeader {
+                title,
+                region: bytes[0x19],
+                version: bytes[0x1B],
+                checksum,
+            },
+            checksum ^ complement == 0xFFFF,
+        )
+    }
+
+    /// The video region implied by the destination code. Europe and
+    /// Australia got PAL consoles, everywhere else NTSC.
+    pub fn video_region(&self) -> Region {
+        match self.region {
+            0x02..=0x0C | 0x11 => Region::Pal,
+            _ => Region::Ntsc,
+        }
+    }
+
+    fn has_printable_title(&self) -> bool {
+        !self.title.is_empty() && self.title.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+    }
+}
+
+impl fmt::Display for CartridgeHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" (region {:#04x}, version {}, checksum {:#06x})",
+            self.title, self.region, self.version, self.checksum
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header's 32 bytes, with the checksum and its complement
+    /// consistent or not
+    fn header_bytes(title: &str, region: u8, checksum_valid: bool) -> Vec<u8> {
+        let mut bytes = vec![b' '; HEADER_SIZE];
+        bytes[..title.len()].copy_from_slice(title.as_bytes());
+        bytes[0x19] = region;
+        bytes[0x1B] = 1;
+        let checksum: u16 = 0x1234;
+        let complement = if checksum_valid { !checksum } else { 0 };
+        bytes[0x1C..0x1E].copy_from_slice(&complement.to_le_bytes());
+        bytes[0x1E..0x20].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    struct Headers {
+        lorom: Vec<u8>,
+        hirom: Vec<u8>,
+    }
+
+    impl MemorySource for Headers {
+        fn get_addresses(
+            &mut self,
+            pairs: &[(u32, usize)],
+        ) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+            Ok(pairs
+                .iter()
+                .map(|&(address, _)| match address {
+                    LOROM_HEADER => self.lorom.clone(),
+                    _ => self.hirom.clone(),
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let (header, valid) = CartridgeHeader::parse(&header_bytes("Super Metroid", 0x01, true));
+        assert!(valid);
+        assert_eq!(
+            header,
+            CartridgeHeader {
+                title: "Super Metroid".to_owned(),
+                region: 0x01,
+                version: 1,
+                checksum: 0x1234,
+            }
+        );
+        let (_, valid) = CartridgeHeader::parse(&header_bytes("Super Metroid", 0x01, false));
+        assert!(!valid);
+    }
+
+    #[test]
+    fn the_header_with_a_valid_checksum_wins() {
+        let mut headers = Headers {
+            lorom: header_bytes("LOROM GAME", 0x01, true),
+            hirom: header_bytes("HIROM GAME", 0x01, false),
+        };
+        assert_eq!(
+            CartridgeHeader::read(&mut headers).unwrap().title,
+            "LOROM GAME"
+        );
+        let mut headers = Headers {
+            lorom: header_bytes("LOROM GAME", 0x01, false),
+            hirom: header_bytes("HIROM GAME", 0x01, true),
+        };
+        assert_eq!(
+            CartridgeHeader::read(&mut headers).unwrap().title,
+            "HIROM GAME"
+        );
+    }
+
+    #[test]
+    fn without_a_valid_checksum_the_readable_title_wins() {
+        let mut garbage = vec![0xFF; HEADER_SIZE];
+        garbage[0] = 0x01;
+        let mut headers = Headers {
+            lorom: garbage,
+            hirom: header_bytes("SM ROM HACK", 0x01, false),
+        };
+        assert_eq!(
+            CartridgeHeader::read(&mut headers).unwrap().title,
+            "SM ROM HACK"
+        );
+    }
+
+    #[test]
+    fn video_region() {
+        let region = |code| {
+            CartridgeHeader::parse(&header_bytes("", code, true))
+                .0
+                .video_region()
+        };
+        assert_eq!(region(0x00), Region::Ntsc); // Japan
+        assert_eq!(region(0x01), Region::Ntsc); // North America
+        assert_eq!(region(0x02), Region::Pal); // Europe
+        assert_eq!(region(0x0C), Region::Pal);
+        assert_eq!(region(0x0D), Region::Ntsc); // South Korea
+        assert_eq!(region(0x11), Region::Pal); // Australia
+    }
+}
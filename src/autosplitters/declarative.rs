@@ -0,0 +1,386 @@
+//! Autosplitters described by a data file instead of Rust code.
+//!
+//! A definition names the memory watchers it needs, some constants, and the
+//! start, reset, and split conditions as [expressions](super::expression).
+//! Definitions can be written in TOML or JSON (picked by file extension).
+//! Here is a small TOML example:
+//!
+//! ```toml
+//! name = "Super Metroid"
+//! # Only autosplit when the cartridge title starts with this
+//! title = "SUPER METROID"
+//! start = ["transition(gameState, 2, 0x1F)"]
+//! reset = ["roomID.old != 0 && roomID.current == 0"]
+//! # Optional, in milliseconds
+//! game_time = "igtHours.current * 3600000 + igtMinutes.current * 60000 + igtSeconds.current * 1000"
+//!
+//! [constants]
+//! morphBallRoom = 0x9E9F
+//!
+//! [watchers]
+//! roomID = { address = 0x079B, width = "word" }
+//! gameState = { address = 0x0998 }
+//! unlockedEquips2 = { address = 0x09A4 }
+//! igtSeconds = { address = 0x09DC }
+//! igtMinutes = { address = 0x09DE }
+//! igtHours = { address = 0x09E0 }
+//!
+//! [[settings]]
+//! name = "items"
+//! description = "Split on item pickups"
+//!
+//! [[settings]]
+//! name = "morphBall"
+//! parent = "items"
+//! split = "roomID.current == morphBallRoom && bit_set(unlockedEquips2, 0x04)"
+//! ```
+//!
+//! Watcher addresses are offsets into WRAM, the same as the addresses in an
+//! ASL script, and default to a width of one byte. A watcher's `width` can
+//! be `"byte"`, `"word"`, `"long"` (24 bits) or `"dword"`, and its
+//! `encoding` `"unsigned"` (the default), `"signed"` or `"bcd"`. A `mask`
+//! makes it a bitfield, whose value is the masked bits shifted down, and
+//! `history` is how many polls back `changed_within` can look:
+//!
+//! ```toml
+//! timerSeconds = { address = 0x0F30, encoding = "bcd" }
+//! facing = { address = 0x0A1E, mask = 0x0C, history = 30 }
+//! ```
+//!
+//! Settings without a
+//! `split` condition are only there to group other settings. A top level
+//! `split` list holds conditions that apply regardless of settings, which
+//! can check settings themselves with `setting(name)`.
+
+mod asl;
+
+use super::expression::{Expr, Scope};
+use super::{
+    plan_reads, store_reads, AutoSplitter, Encoding, MemoryWatcher, Settings, SplitReason, Width,
+    WRAM_SIZE,
+};
+use livesplit_core::TimeSpan;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    name: String,
+    title: Option<String>,
+    #[serde(default)]
+    constants: BTreeMap<String, i64>,
+    watchers: BTreeMap<String, WatcherFile>,
+    #[serde(default)]
+    start: Vec<String>,
+    #[serde(default)]
+    reset: Vec<String>,
+    #[serde(default)]
+    split: Vec<String>,
+    game_time: Option<String>,
+    #[serde(default)]
+    settings: Vec<SettingFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatcherFile {
+    address: u32,
+    #[serde(default = "default_width")]
+    width: Width,
+    #[serde(default)]
+    encoding: Encoding,
+    mask: Option<u32>,
+    /// How many past polls to keep values for
+    #[serde(default)]
+    history: usize,
+}
+
+fn default_width() -> Width {
+    Width::Byte
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingFile {
+    name: String,
+    parent: Option<String>,
+    #[serde(default = "default_enabled")]
+    default: bool,
+    description: Option<String>,
+    split: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+struct DefinitionScope<'a> {
+    watchers: &'a HashMap<&'a str, usize>,
+    constants: &'a BTreeMap<String, i64>,
+    settings: &'a [SettingFile],
+}
+
+impl Scope for DefinitionScope<'_> {
+    fn watcher(&self, name: &str) -> Option<usize> {
+        self.watchers.get(name).copied()
+    }
+
+    fn constant(&self, name: &str) -> Option<i64> {
+        self.constants.get(name).copied()
+    }
+
+    fn setting(&self, name: &str) -> bool {
+        self.settings.iter().any(|s| s.name == name)
+    }
+}
+
+/// A parsed and validated autosplitter definition
+pub struct Definition {
+    name: String,
+    title: Option<String>,
+    watchers: Vec<MemoryWatcher>,
+    start: Vec<Expr>,
+    reset: Vec<Expr>,
+    game_time: Option<Expr>,
+    // Splits gated on a setting, and ones that always apply. Gated splits
+    // are reported by the setting's name, the others by their condition.
+    splits: Vec<(Option<String>, SplitReason, Expr)>,
+    settings: Settings,
+}
+
+impl Definition {
+    /// Load a definition, treating `.json` files as JSON, `.asl` files as
+    /// LiveSplit autosplitter scripts, and anything else as TOML
+    pub fn load(path: &Path) -> Result<Definition, Box<dyn Error>> {
+        let source = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "json" => Definition::from_json(&source),
+            "asl" => {
+                let name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("ASL script");
+                Definition::from_asl(name, &source)
+            }
+            _ => Definition::from_toml(&source),
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<Definition, Box<dyn Error>> {
+        Definition::from_file(toml::from_str(source)?)
+    }
+
+    pub fn from_json(source: &str) -> Result<Definition, Box<dyn Error>> {
+        Definition::from_file(serde_json::from_str(source)?)
+    }
+
+    /// Import a LiveSplit ASL script. Parts of the script that can't be
+    /// translated are printed as warnings.
+    pub fn from_asl(name: &str, source: &str) -> Result<Definition, Box<dyn Error>> {
+        let (file, warnings) = asl::import(name, source)?;
+        for warning in warnings {
+            println!("Importing {}: {}", name, warning);
+        }
+        Definition::from_file(file)
+    }
+
+    fn from_file(file: DefinitionFile) -> Result<Definition, Box<dyn Error>> {
+        let mut watchers = vec![];
+        let mut indices = HashMap::new();
+        for (i, (name, w)) in file.watchers.iter().enumerate() {
+            if w.address as usize + w.width.size() > WRAM_SIZE {
+                Err(format!(
+                    "Watcher \"{}\" at {:#x} is outside of WRAM",
+                    name, w.address
+                ))?;
+            }
+            let mut watcher = MemoryWatcher::new(w.address, w.width)
+                .with_encoding(w.encoding)
+                .with_history(w.history);
+            if let Some(mask) = w.mask {
+                watcher = watcher.with_mask(mask);
+            }
+            watchers.push(watcher);
+            indices.insert(name.as_str(), i);
+        }
+        let scope = DefinitionScope {
+            watchers: &indices,
+            constants: &file.constants,
+            settings: &file.settings,
+        };
+        let parse = |what: &str, source: &str| {
+            Expr::parse(source, &scope)
+                .map_err(|e| format!("Failed to parse {} \"{}\": {}", what, source, e))
+        };
+
+        let start = file
+            .start
+            .iter()
+            .map(|s| parse("start condition", s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let reset = file
+            .reset
+            .iter()
+            .map(|s| parse("reset condition", s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut splits = file
+            .split
+            .iter()
+            .map(|s| Ok((None, s.as_str().into(), parse("split condition", s)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let game_time = file
+            .game_time
+            .as_ref()
+            .map(|s| parse("game time", s))
+            .transpose()?;
+
+        let mut settings = Settings::new();
+        for s in file.settings.iter() {
+            if settings.contains(&s.name) {
+                Err(format!("Setting \"{}\" is defined twice", s.name))?;
+            }
+            match &s.parent {
+                None => settings.insert(&s.name, s.default),
+                Some(parent) => {
+                    if !settings.contains(parent) {
+                        Err(format!(
+                            "Setting \"{}\" has parent \"{}\", which must be defined before it",
+                            s.name, parent
+                        ))?;
+                    }
+                    settings.insert_with_parent(&s.name, s.default, parent)
+                }
+            }
+            if let Some(description) = &s.description {
+                settings.describe(&s.name, description);
+            }
+            if let Some(split) = &s.split {
+                splits.push((
+                    Some(s.name.clone()),
+                    s.name.as_str().into(),
+                    parse(&format!("split \"{}\"", s.name), split)?,
+                ));
+            }
+        }
+        settings.modified_after_creation = false;
+
+        Ok(Definition {
+            name: file.name,
+            title: file.title,
+            watchers,
+            start,
+            reset,
+            game_time,
+            splits,
+            settings,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Definitions without a title match any cartridge
+    pub fn matches(&self, title: &str) -> bool {
+        match &self.title {
+            None => true,
+            Some(t) => title.to_uppercase().starts_with(&t.to_uppercase()),
+        }
+    }
+
+    pub fn default_settings(&self) -> Settings {
+        self.settings.clone()
+    }
+}
+
+/// Runs a [`Definition`] against the SNES memory
+pub struct DeclarativeAutoSplitter {
+    definition: Arc<Definition>,
+    watchers: Vec<MemoryWatcher>,
+    plan: Vec<(u32, usize)>,
+    data: Vec<u8>,
+    // Same as SNESState, the watchers need two updates
+    // before old and current are both meaningful.
+    do_extra_update: bool,
+}
+
+impl DeclarativeAutoSplitter {
+    pub fn new(definition: Arc<Definition>) -> DeclarativeAutoSplitter {
+        DeclarativeAutoSplitter {
+            watchers: definition.watchers.clone(),
+            plan: plan_reads(&definition.watchers),
+            definition,
+            data: vec![0; WRAM_SIZE],
+            do_extra_update: true,
+        }
+    }
+
+    // Game time isn't given the current settings, so any settings it
+    // refers to have their default values.
+    fn eval(&self, e: &Expr) -> i64 {
+        e.eval(&self.watchers, &self.definition.settings)
+    }
+
+    fn any(&self, conditions: &[Expr], settings: &Settings) -> bool {
+        conditions
+            .iter()
+            .any(|e| e.eval(&self.watchers, settings) != 0)
+    }
+}
+
+impl AutoSplitter for DeclarativeAutoSplitter {
+    fn read_plan(&self) -> Vec<(u32, usize)> {
+        self.plan.clone()
+    }
+
+    fn update(&mut self, snes_data: &[Vec<u8>]) {
+        store_reads(&self.plan, snes_data, &mut self.data);
+        for w in self.watchers.iter_mut() {
+            if self.do_extra_update {
+                w.update_value(&self.data);
+            }
+            w.update_value(&self.data);
+        }
+        self.do_extra_update = false;
+    }
+
+    fn start(&self, settings: &Settings) -> bool {
+        self.any(&self.definition.start, settings)
+    }
+
+    fn reset(&self, settings: &Settings) -> bool {
+        self.any(&self.definition.reset, settings)
+    }
+
+    fn split(&mut self, settings: &Settings) -> Vec<SplitReason> {
+        self.definition
+            .splits
+            .iter()
+            .filter(|(name, _, e)| {
+                name.as_ref().is_none_or(|n| settings.get(n))
+                    && e.eval(&self.watchers, settings) != 0
+            })
+            .map(|(_, reason, _)| reason.clone())
+            .collect()
+    }
+
+    fn gametime_to_seconds(&self) -> Option<TimeSpan> {
+        self.definition
+            .game_time
+            .as_ref()
+            .map(|e| TimeSpan::from_milliseconds(self.eval(e) as f64))
+    }
+
+    fn default_settings(&self) -> Settings {
+        self.definition.default_settings()
+    }
+}
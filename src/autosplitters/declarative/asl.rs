@@ -0,0 +1,1134 @@
+//! Import of LiveSplit ASL scripts.
+//!
+//! Only the subset of ASL that SNES autosplitters commonly use is
+//! understood:
+//!
+//! * `settings.Add`, `settings.SetToolTip`, and
+//!   `settings.CurrentDefaultParent` in `startup`
+//! * `new MemoryWatcher<byte>(memoryOffset + 0x079B) { Name = "roomID" }`
+//!   declarations, with `byte`, `sbyte`, `ushort`, `short`, `uint`, and `int`
+//!   values
+//! * `vars.name = new Dictionary<string, int> { ... }` tables and numeric
+//!   `vars.name = 5;` values that are never changed later, which become
+//!   constants
+//! * `start`, `reset`, `split`, and `gameTime` blocks made of local
+//!   variables, `if`/`else`, and `return`, with expressions over
+//!   `vars.watchers["name"].Old`/`.Current`, `settings["name"]`, the
+//!   constants, and integer arithmetic
+//!
+//! Anything else is reported as a warning and treated as `false`, so a
+//! split that depends on it never fires rather than firing at random.
+//! State kept in `vars` between polls and floating point math are the usual
+//! culprits.
+
+use super::{DefinitionFile, SettingFile, WatcherFile};
+use crate::autosplitters::{Encoding, Width};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Float,
+    Str(String),
+    Punct(&'static str),
+}
+
+// Longest first so that `&&` isn't lexed as `&` `&`
+const PUNCTUATION: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<<", ">>", "+=", "-=", "|=", "&=", "^=", "*=", "/=", "++",
+    "--", "=>", "{", "}", "(", ")", "[", "]", ";", ",", ".", "=", "<", ">", "+", "-", "*", "/",
+    "%", "!", "&", "|", "^", "~", "?", ":",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    'outer: while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if source[i..].starts_with("//") {
+            i += source[i..].find('\n').unwrap_or(source.len() - i);
+            continue;
+        }
+        if source[i..].starts_with("/*") {
+            i += source[i..].find("*/").ok_or("Unterminated comment")? + 2;
+            continue;
+        }
+        let start = i;
+        if c == b'"' || c == b'\'' || source[i..].starts_with("@\"") {
+            let verbatim = c == b'@';
+            if verbatim {
+                i += 1;
+            }
+            let quote = bytes[i];
+            i += 1;
+            let mut text = String::new();
+            loop {
+                let rest = &source[i..];
+                let ch = rest.chars().next().ok_or("Unterminated string")?;
+                i += ch.len_utf8();
+                if ch as u32 == quote as u32 {
+                    break;
+                }
+                if ch == '\\' && !verbatim {
+                    let escaped = source[i..].chars().next().ok_or("Unterminated string")?;
+                    i += escaped.len_utf8();
+                    text.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        c => c,
+                    });
+                } else {
+                    text.push(ch);
+                }
+            }
+            tokens.push(Token::Str(text));
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let hex = source[i..].starts_with("0x") || source[i..].starts_with("0X");
+            if hex {
+                i += 2;
+            }
+            let digits = i;
+            while i < bytes.len() && (bytes[i].is_ascii_hexdigit() || (!hex && bytes[i] == b'.')) {
+                // `e` and friends are hex digits, so decimal numbers stop
+                // at the first letter
+                if !hex && bytes[i].is_ascii_alphabetic() {
+                    break;
+                }
+                i += 1;
+            }
+            let text = &source[digits..i];
+            let float = !hex && (text.contains('.') || matches!(bytes.get(i), Some(b'f' | b'd')));
+            while i < bytes.len() && bytes[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            if float {
+                tokens.push(Token::Float);
+            } else {
+                let n = i64::from_str_radix(text, if hex { 16 } else { 10 })
+                    .map_err(|_| format!("Invalid number \"{}\"", &source[start..i]))?;
+                tokens.push(Token::Number(n));
+            }
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(source[start..i].to_owned()));
+            continue;
+        }
+        for p in PUNCTUATION {
+            if source[i..].starts_with(p) {
+                tokens.push(Token::Punct(p));
+                i += p.len();
+                continue 'outer;
+            }
+        }
+        // Anything else (like `#` or `$`) can't appear in the subset we
+        // translate, so it's safe to drop.
+        i += source[i..].chars().next().map_or(1, |c| c.len_utf8());
+    }
+    Ok(tokens)
+}
+
+fn is_punct(t: Option<&Token>, p: &str) -> bool {
+    matches!(t, Some(Token::Punct(q)) if *q == p)
+}
+
+fn is_ident(t: Option<&Token>, name: &str) -> bool {
+    matches!(t, Some(Token::Ident(i)) if i == name)
+}
+
+/// Index just past the bracket that closes the one at `open`
+fn matching(tokens: &[Token], open: usize) -> Result<usize, Box<dyn Error>> {
+    let mut depth = 0;
+    for (i, t) in tokens.iter().enumerate().skip(open) {
+        match t {
+            Token::Punct("{" | "(" | "[") => depth += 1,
+            Token::Punct("}" | ")" | "]") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("Unterminated block")?
+}
+
+/// The bodies of the top level `name { ... }` blocks
+fn blocks(tokens: &[Token]) -> Result<HashMap<String, &[Token]>, Box<dyn Error>> {
+    let mut blocks = HashMap::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Token::Ident(name) = &tokens[i] {
+            let mut open = i + 1;
+            if is_punct(tokens.get(open), "(") {
+                open = matching(tokens, open)?;
+            }
+            if is_punct(tokens.get(open), "{") {
+                let close = matching(tokens, open)?;
+                blocks.insert(name.clone(), &tokens[open + 1..close - 1]);
+                i = close;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    Ok(blocks)
+}
+
+fn warn(warnings: &mut Vec<String>, message: String) {
+    if !warnings.contains(&message) {
+        warnings.push(message);
+    }
+}
+
+fn constant_name(table: &str, key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", table, key)
+}
+
+/// `vars.table["key"]` lookups and plain `vars.name` values
+#[derive(Default)]
+struct Constants {
+    tables: HashMap<String, HashMap<String, String>>,
+    values: BTreeMap<String, i64>,
+}
+
+fn find_constants(
+    tokens: &[Token],
+    assigned_later: &HashSet<String>,
+) -> Result<Constants, Box<dyn Error>> {
+    let mut constants = Constants::default();
+    let mut i = 0;
+    while i + 4 < tokens.len() {
+        let name = match (&tokens[i], &tokens[i + 1], &tokens[i + 2], &tokens[i + 3]) {
+            (Token::Ident(v), Token::Punct("."), Token::Ident(name), Token::Punct("="))
+                if v == "vars" && !assigned_later.contains(name) =>
+            {
+                name.clone()
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        i += 4;
+        match (&tokens[i], tokens.get(i + 1)) {
+            (Token::Number(n), Some(Token::Punct(";"))) => {
+                constants.values.insert(name, *n);
+            }
+            (Token::Punct("-"), Some(Token::Number(n))) => {
+                constants.values.insert(name, -n);
+            }
+            (Token::Ident(new), Some(Token::Ident(ty)))
+                if new == "new" && ty.starts_with("Dictionary") =>
+            {
+                let open = match tokens[i..].iter().position(|t| is_punct(Some(t), "{")) {
+                    Some(open) => i + open,
+                    None => continue,
+                };
+                let close = matching(tokens, open)?;
+                let mut table = HashMap::new();
+                // Entries look like `{ "key", value }`
+                for entry in tokens[open + 1..close - 1].split(|t| is_punct(Some(t), "}")) {
+                    let value = match entry {
+                        [.., Token::Str(_), Token::Punct(","), Token::Number(n)] => *n,
+                        [.., Token::Str(_), Token::Punct(","), Token::Punct("-"), Token::Number(n)] => {
+                            -n
+                        }
+                        _ => continue,
+                    };
+                    if let Some(Token::Str(key)) = entry.iter().find(|t| matches!(t, Token::Str(_)))
+                    {
+                        let constant = constant_name(&name, key);
+                        constants.values.insert(constant.clone(), value);
+                        table.insert(key.clone(), constant);
+                    }
+                }
+                constants.tables.insert(name, table);
+                i = close;
+            }
+            _ => {}
+        }
+    }
+    Ok(constants)
+}
+
+/// The `vars.name` values assigned to while the script runs. Those hold
+/// state between polls, so they can't be treated as constants.
+fn assigned_vars(blocks: &[&[Token]]) -> HashSet<String> {
+    let mut assigned = HashSet::new();
+    for tokens in blocks {
+        for w in tokens.windows(4) {
+            if let [Token::Ident(v), Token::Punct("."), Token::Ident(name), Token::Punct(op)] = w {
+                if v == "vars" && matches!(*op, "=" | "+=" | "-=" | "|=" | "&=" | "++" | "--") {
+                    assigned.insert(name.clone());
+                }
+            }
+        }
+    }
+    assigned
+}
+
+fn find_watchers(
+    tokens: &[Token],
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, WatcherFile>, Box<dyn Error>> {
+    let mut watchers = BTreeMap::new();
+    let mut i = 0;
+    while i + 5 < tokens.len() {
+        let ty = match &tokens[i..i + 5] {
+            [Token::Ident(new), Token::Ident(mw), Token::Punct("<"), Token::Ident(ty), Token::Punct(">")]
+                if new == "new" && mw == "MemoryWatcher" =>
+            {
+                ty.clone()
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let args = i + 5;
+        let body = matching(tokens, args)?;
+        let name = match &tokens[body..] {
+            [Token::Punct("{"), Token::Ident(n), Token::Punct("="), Token::Str(name), ..]
+                if n == "Name" =>
+            {
+                name.clone()
+            }
+            _ => {
+                warn(
+                    warnings,
+                    "Skipped a MemoryWatcher without a Name".to_owned(),
+                );
+                i = body;
+                continue;
+            }
+        };
+        i = body;
+        let (width, encoding) = match ty.as_str() {
+            "byte" => (Width::Byte, Encoding::Unsigned),
+            "sbyte" => (Width::Byte, Encoding::Signed),
+            "ushort" => (Width::Word, Encoding::Unsigned),
+            "short" => (Width::Word, Encoding::Signed),
+            "uint" => (Width::Dword, Encoding::Unsigned),
+            "int" => (Width::Dword, Encoding::Signed),
+            _ => {
+                warn(
+                    warnings,
+                    format!("Watcher \"{}\" has unsupported type {}", name, ty),
+                );
+                continue;
+            }
+        };
+        // The address is usually written as an offset from the start of
+        // WRAM, but a full $7E/$7F bank address works too.
+        let address = tokens[args..body].iter().rev().find_map(|t| match t {
+            Token::Number(n) => Some(*n),
+            _ => None,
+        });
+        let address = match address {
+            Some(a) if (0x7E0000..0x800000).contains(&a) => a - 0x7E0000,
+            Some(a) if (0..0x20000).contains(&a) => a,
+            _ => {
+                warn(
+                    warnings,
+                    format!("Watcher \"{}\" doesn't have a WRAM address", name),
+                );
+                continue;
+            }
+        };
+        watchers.insert(
+            name,
+            WatcherFile {
+                address: address as u32,
+                width,
+                encoding,
+                mask: None,
+                history: 0,
+            },
+        );
+    }
+    Ok(watchers)
+}
+
+fn find_settings(tokens: &[Token], warnings: &mut Vec<String>) -> Vec<SettingFile> {
+    let mut settings: Vec<SettingFile> = vec![];
+    let mut default_parent: Option<String> = None;
+    for statement in tokens.split(|t| is_punct(Some(t), ";")) {
+        // Drop anything before the call, like the `{` of an `if` block
+        let statement = match statement
+            .windows(2)
+            .position(|w| is_ident(w.first(), "settings") && is_punct(w.get(1), "."))
+        {
+            Some(start) => &statement[start..],
+            None => continue,
+        };
+        let (method, args) = match statement {
+            [Token::Ident(s), Token::Punct("."), Token::Ident(method), rest @ ..]
+                if s == "settings" =>
+            {
+                (method.as_str(), rest)
+            }
+            _ => continue,
+        };
+        if method == "CurrentDefaultParent" {
+            default_parent = match args {
+                [Token::Punct("="), Token::Str(parent)] => Some(parent.clone()),
+                _ => None,
+            };
+            continue;
+        }
+        let args: Vec<&Token> = args
+            .iter()
+            .filter(|t| !matches!(t, Token::Punct("(" | ")" | ",")))
+            .collect();
+        match (method, args.as_slice()) {
+            ("Add", [Token::Str(name), rest @ ..]) => {
+                if settings.iter().any(|s| s.name == *name) {
+                    warn(warnings, format!("Setting \"{}\" is added twice", name));
+                    continue;
+                }
+                let default = !is_ident(rest.first().copied(), "false");
+                let label = match rest.get(1) {
+                    Some(Token::Str(label)) => Some(label.clone()),
+                    _ => None,
+                };
+                let parent = match rest.get(2) {
+                    Some(Token::Str(parent)) => Some(parent.clone()),
+                    Some(_) => None,
+                    None => default_parent.clone(),
+                };
+                settings.push(SettingFile {
+                    name: name.clone(),
+                    parent,
+                    default,
+                    description: label,
+                    split: None,
+                });
+            }
+            ("SetToolTip", [Token::Str(name), Token::Str(tip)]) => {
+                if let Some(s) = settings.iter_mut().find(|s| s.name == *name) {
+                    s.description = Some(tip.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+// The type names that can appear in casts and declarations
+const ASSIGNMENTS: &[&str] = &["=", "+=", "-=", "|=", "&=", "^=", "*=", "/="];
+
+const TYPES: &[&str] = &[
+    "var", "bool", "int", "uint", "byte", "sbyte", "short", "ushort", "long", "ulong",
+];
+
+fn ternary(condition: &str, then: &str, otherwise: &str) -> String {
+    format!(
+        "(((({}) != 0) * ({})) + ((({}) == 0) * ({})))",
+        condition, then, condition, otherwise
+    )
+}
+
+/// Translates the statements of one block into a single expression
+struct Translator<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    block: &'a str,
+    watchers: &'a BTreeMap<String, WatcherFile>,
+    constants: &'a Constants,
+    settings: &'a [SettingFile],
+    locals: HashMap<String, String>,
+    // Each `return`, along with the condition under which it was reached
+    returns: Vec<(String, String)>,
+    warnings: &'a mut Vec<String>,
+}
+
+type Translation = Result<String, String>;
+
+impl Translator<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn eat(&mut self, p: &str) -> bool {
+        if is_punct(self.peek(), p) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, p: &str) -> Result<(), String> {
+        if self.eat(p) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", p))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek().cloned() {
+            Some(Token::Ident(i)) => {
+                self.pos += 1;
+                Ok(i)
+            }
+            _ => Err("expected a name".to_owned()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.peek().cloned() {
+            Some(Token::Str(s)) => {
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => Err("expected a string".to_owned()),
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        let message = format!("{}: {}", self.block, message);
+        warn(self.warnings, message);
+    }
+
+    /// Skip past the bracket at `pos` and everything up to its match. The
+    /// block being translated was matched as a whole, so brackets inside
+    /// it are always closed.
+    fn skip_brackets(&mut self) {
+        self.pos = matching(self.tokens, self.pos).unwrap_or(self.tokens.len());
+    }
+
+    /// Skip to just past the end of the current statement
+    fn skip_statement(&mut self) {
+        while let Some(t) = self.peek() {
+            match t {
+                Token::Punct(";") => {
+                    self.pos += 1;
+                    return;
+                }
+                Token::Punct("{" | "(" | "[") => {
+                    self.skip_brackets();
+                    if is_punct(self.tokens.get(self.pos - 1), "}") && !is_punct(self.peek(), ";") {
+                        return;
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Translate a whole block. `None` means nothing was returned.
+    fn translate(mut self) -> Option<String> {
+        self.statements("1");
+        let mut result: Option<String> = None;
+        for (condition, value) in self.returns.iter().rev() {
+            result = Some(match result {
+                None if condition == "1" => value.clone(),
+                None => ternary(condition, value, "0"),
+                Some(_) if condition == "1" => value.clone(),
+                Some(otherwise) => ternary(condition, value, &otherwise),
+            });
+        }
+        result
+    }
+
+    /// Returns true when the statements unconditionally returned
+    fn statements(&mut self, condition: &str) -> bool {
+        while self.peek().is_some() && !is_punct(self.peek(), "}") {
+            if self.statement(condition) {
+                // Whatever follows can never run
+                while self.peek().is_some() && !is_punct(self.peek(), "}") {
+                    self.pos += 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    fn expression_or_false(&mut self) -> String {
+        let start = self.pos;
+        match self.expression() {
+            Ok(e) => e,
+            Err(e) => {
+                self.warn(format!("{}, using false instead", e));
+                self.pos = start;
+                self.skip_expression();
+                "0".to_owned()
+            }
+        }
+    }
+
+    /// Skip an expression that failed to translate, stopping before the
+    /// `;` or `)` that ends it
+    fn skip_expression(&mut self) {
+        while let Some(t) = self.peek() {
+            match t {
+                Token::Punct(";" | ")" | "}") => return,
+                Token::Punct("{" | "(" | "[") => self.skip_brackets(),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: String, condition: &str) {
+        let value = if condition == "1" {
+            value
+        } else {
+            let old = self.locals.get(name).cloned().unwrap_or_else(|| "0".into());
+            ternary(condition, &value, &old)
+        };
+        self.locals.insert(name.to_owned(), value);
+    }
+
+    fn statement(&mut self, condition: &str) -> bool {
+        let t = self.peek().cloned();
+        match t {
+            Some(Token::Punct("{")) => {
+                self.pos += 1;
+                let returned = self.statements(condition);
+                self.eat("}");
+                returned
+            }
+            Some(Token::Punct(";")) => {
+                self.pos += 1;
+                false
+            }
+            Some(Token::Ident(keyword)) if keyword == "if" => {
+                self.pos += 1;
+                let test = if self.eat("(") {
+                    let test = self.expression_or_false();
+                    self.eat(")");
+                    test
+                } else {
+                    "0".to_owned()
+                };
+                let (then, otherwise) = if condition == "1" {
+                    (test.clone(), format!("!({})", test))
+                } else {
+                    (
+                        format!("({}) && ({})", condition, test),
+                        format!("({}) && !({})", condition, test),
+                    )
+                };
+                let then_returned = self.statement(&then);
+                let else_returned = if is_ident(self.peek(), "else") {
+                    self.pos += 1;
+                    self.statement(&otherwise)
+                } else {
+                    false
+                };
+                then_returned && else_returned
+            }
+            Some(Token::Ident(keyword)) if keyword == "return" => {
+                self.pos += 1;
+                let value = self.expression_or_false();
+                self.eat(";");
+                self.returns.push((condition.to_owned(), value));
+                true
+            }
+            Some(Token::Ident(keyword))
+                if matches!(
+                    keyword.as_str(),
+                    "for" | "foreach" | "while" | "do" | "switch"
+                ) =>
+            {
+                self.warn(format!("\"{}\" isn't supported, skipping it", keyword));
+                self.pos += 1;
+                if self.eat("(") {
+                    self.pos -= 1;
+                    self.skip_brackets();
+                }
+                self.skip_statement();
+                false
+            }
+            Some(Token::Ident(ty))
+                if TYPES.contains(&ty.as_str())
+                    && matches!(self.peek_at(1), Some(Token::Ident(_))) =>
+            {
+                self.pos += 1;
+                let name = self.ident().unwrap_or_default();
+                let value = if self.eat("=") {
+                    self.expression_or_false()
+                } else {
+                    "0".to_owned()
+                };
+                // A declaration inside an `if` is only visible there, so
+                // there's no need to make it conditional.
+                self.locals.insert(name, value);
+                self.skip_statement();
+                false
+            }
+            Some(Token::Ident(first)) => {
+                // Assignments to locals and to `current.name`
+                let name = if (first == "current" || first == "old")
+                    && is_punct(self.peek_at(1), ".")
+                {
+                    match self.peek_at(2) {
+                        Some(Token::Ident(n)) => {
+                            let name = format!("{}.{}", first, n);
+                            self.pos += 3;
+                            name
+                        }
+                        _ => {
+                            self.skip_statement();
+                            return false;
+                        }
+                    }
+                } else if first == "vars" && is_punct(self.peek_at(1), ".") {
+                    if let Some(Token::Ident(n)) = self.peek_at(2) {
+                        if matches!(self.peek_at(3), Some(Token::Punct(p))
+                            if ASSIGNMENTS.contains(p) || *p == "++" || *p == "--")
+                        {
+                            self.warn(format!(
+                                "keeping state in vars.{} isn't supported, splits using it won't fire",
+                                n
+                            ));
+                        }
+                    }
+                    self.skip_statement();
+                    return false;
+                } else {
+                    self.pos += 1;
+                    first
+                };
+                let op = match self.peek() {
+                    Some(Token::Punct(op)) if ASSIGNMENTS.contains(op) => *op,
+                    _ => {
+                        // Most likely a call like `vars.DebugOutput(...)`
+                        self.skip_statement();
+                        return false;
+                    }
+                };
+                self.pos += 1;
+                let value = self.expression_or_false();
+                let value = if op == "=" {
+                    value
+                } else {
+                    let old = self
+                        .locals
+                        .get(&name)
+                        .cloned()
+                        .unwrap_or_else(|| "0".into());
+                    let op = &op[..op.len() - 1];
+                    format!("(({}) {} ({}))", old, op, value)
+                };
+                self.assign(&name, value, condition);
+                self.skip_statement();
+                false
+            }
+            _ => {
+                self.skip_statement();
+                false
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Translation {
+        let condition = self.binary(0)?;
+        if self.eat("?") {
+            let then = self.expression()?;
+            self.expect(":")?;
+            let otherwise = self.expression()?;
+            return Ok(ternary(&condition, &then, &otherwise));
+        }
+        Ok(condition)
+    }
+
+    fn binary(&mut self, level: usize) -> Translation {
+        // From loosest to tightest binding, same as C#
+        const LEVELS: &[&[&str]] = &[
+            &["||"],
+            &["&&"],
+            &["|"],
+            &["^"],
+            &["&"],
+            &["==", "!="],
+            &["<", "<=", ">", ">="],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        'operators: loop {
+            for op in LEVELS[level] {
+                if self.eat(op) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = format!("({} {} {})", lhs, op, rhs);
+                    continue 'operators;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Translation {
+        for op in ["!", "-", "~"] {
+            if self.eat(op) {
+                return Ok(format!("{}{}", op, self.unary()?));
+            }
+        }
+        // Casts between integer types don't change anything for the small
+        // values we read
+        if is_punct(self.peek(), "(")
+            && matches!(self.peek_at(1), Some(Token::Ident(t)) if TYPES.contains(&t.as_str()))
+            && is_punct(self.peek_at(2), ")")
+        {
+            self.pos += 3;
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Translation {
+        let t = self.peek().cloned().ok_or("unexpected end of block")?;
+        self.pos += 1;
+        match t {
+            Token::Number(n) => Ok(n.to_string()),
+            Token::Float => Err("floating point numbers aren't supported".to_owned()),
+            Token::Str(_) => Err("strings aren't supported".to_owned()),
+            Token::Punct("(") => {
+                let e = self.expression()?;
+                self.expect(")")?;
+                Ok(format!("({})", e))
+            }
+            Token::Punct(p) => Err(format!("unexpected '{}'", p)),
+            Token::Ident(i) if i == "true" => Ok("1".to_owned()),
+            Token::Ident(i) if i == "false" => Ok("0".to_owned()),
+            Token::Ident(i) if i == "settings" && self.eat("[") => {
+                let name = self.string()?;
+                self.expect("]")?;
+                if !self.settings.iter().any(|s| s.name == name) {
+                    return Err(format!("unknown setting \"{}\"", name));
+                }
+                if name.contains('"') {
+                    return Err(format!("setting name {} can't be quoted", name));
+                }
+                Ok(format!("setting(\"{}\")", name))
+            }
+            Token::Ident(i) if i == "vars" && self.eat(".") => self.vars(),
+            Token::Ident(i) if (i == "current" || i == "old") && self.eat(".") => {
+                let name = self.ident()?;
+                let local = format!("{}.{}", i, name);
+                if let Some(e) = self.locals.get(&local) {
+                    return Ok(format!("({})", e));
+                }
+                if self.watchers.contains_key(&name) {
+                    let field = if i == "current" { "current" } else { "old" };
+                    return Ok(format!("{}.{}", name, field));
+                }
+                Err(format!("unknown state \"{}\"", local))
+            }
+            Token::Ident(i) if i == "TimeSpan" && self.eat(".") => {
+                let method = self.ident()?;
+                self.expect("(")?;
+                let e = self.expression()?;
+                self.expect(")")?;
+                match method.as_str() {
+                    "FromMilliseconds" => Ok(e),
+                    "FromSeconds" => Ok(format!("(({}) * 1000)", e)),
+                    _ => Err(format!("TimeSpan.{} isn't supported", method)),
+                }
+            }
+            Token::Ident(i) => {
+                if is_punct(self.peek(), "(") || is_punct(self.peek(), ".") {
+                    return Err(format!("calling \"{}\" isn't supported", i));
+                }
+                match self.locals.get(&i) {
+                    Some(e) => Ok(format!("({})", e)),
+                    None => Err(format!("unknown variable \"{}\"", i)),
+                }
+            }
+        }
+    }
+
+    /// Everything after `vars.`
+    fn vars(&mut self) -> Translation {
+        let name = self.ident()?;
+        if name == "watchers" {
+            self.expect("[")?;
+            let watcher = self.string()?;
+            self.expect("]")?;
+            self.expect(".")?;
+            let field = self.ident()?;
+            if !self.watchers.contains_key(&watcher) {
+                return Err(format!("watcher \"{}\" wasn't imported", watcher));
+            }
+            return match field.as_str() {
+                "Old" => Ok(format!("{}.old", watcher)),
+                "Current" => Ok(format!("{}.current", watcher)),
+                "Changed" => Ok(format!("changed({})", watcher)),
+                _ => Err(format!("watcher property {} isn't supported", field)),
+            };
+        }
+        if self.eat("[") {
+            let key = self.string()?;
+            self.expect("]")?;
+            return self
+                .constants
+                .tables
+                .get(&name)
+                .and_then(|table| table.get(&key))
+                .cloned()
+                .ok_or(format!("vars.{}[\"{}\"] isn't a known constant", name, key));
+        }
+        if self.constants.values.contains_key(&name) {
+            return Ok(name);
+        }
+        Err(format!("vars.{} isn't a constant", name))
+    }
+}
+
+/// Translate an ASL script into a definition. The warnings list the parts
+/// of the script that couldn't be translated.
+pub(super) fn import(
+    name: &str,
+    source: &str,
+) -> Result<(DefinitionFile, Vec<String>), Box<dyn Error>> {
+    let tokens = tokenize(source)?;
+    let blocks = blocks(&tokens)?;
+    let mut warnings = vec![];
+    let empty: &[Token] = &[];
+    let block = |name: &str| blocks.get(name).copied().unwrap_or(empty);
+
+    let settings = find_settings(block("startup"), &mut warnings);
+    let watchers = find_watchers(&tokens, &mut warnings)?;
+    if watchers.is_empty() {
+        Err("The script doesn't declare any MemoryWatchers")?;
+    }
+    let runtime_blocks = ["update", "start", "reset", "split", "gameTime"].map(block);
+    let constants = find_constants(&tokens, &assigned_vars(&runtime_blocks))?;
+
+    let translate = |block_name: &str, warnings: &mut Vec<String>| {
+        let translator = Translator {
+            tokens: block(block_name),
+            pos: 0,
+            block: block_name,
+            watchers: &watchers,
+            constants: &constants,
+            settings: &settings,
+            locals: HashMap::new(),
+            returns: vec![],
+            warnings,
+        };
+        translator.translate()
+    };
+    let start = translate("start", &mut warnings);
+    let reset = translate("reset", &mut warnings);
+    let split = translate("split", &mut warnings);
+    // A game time with untranslatable parts would be wrong, rather than
+    // just missing a split, so leave it out entirely.
+    let before = warnings.len();
+    let mut game_time = translate("gameTime", &mut warnings);
+    if warnings.len() != before {
+        warnings.push("gameTime couldn't be fully translated, so it was left out".to_owned());
+        game_time = None;
+    }
+
+    let file = DefinitionFile {
+        name: name.to_owned(),
+        title: None,
+        constants: constants.values,
+        watchers,
+        start: start.into_iter().collect(),
+        reset: reset.into_iter().collect(),
+        split: split.into_iter().collect(),
+        game_time,
+        settings,
+    };
+    Ok((file, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DeclarativeAutoSplitter, Definition};
+    use super::*;
+    use crate::autosplitters::{AutoSplitter, WRAM_SIZE, WRAM_START};
+    use std::sync::Arc;
+
+    const WATCHERS: &str = r#"
+        init {
+            vars.watchers = new MemoryWatcherList {
+                new MemoryWatcher<byte>(memoryOffset + 0x10) { Name = "a" },
+                new MemoryWatcher<ushort>(memoryOffset + 0x79B) { Name = "room" },
+            };
+        }
+    "#;
+
+    fn import_script(script: &str) -> (DefinitionFile, Vec<String>) {
+        import("test", &format!("{}{}", WATCHERS, script)).unwrap()
+    }
+
+    /// Whether the imported script splits when memory goes from `before`
+    /// to `after`
+    fn splits(file: DefinitionFile, before: &[(usize, u16)], after: &[(usize, u16)]) -> bool {
+        let definition = Arc::new(Definition::from_file(file).unwrap());
+        let settings = definition.default_settings();
+        let mut autosplitter = DeclarativeAutoSplitter::new(definition);
+        let plan = autosplitter.read_plan();
+        let mut memory = vec![0u8; WRAM_SIZE];
+        let mut poll = |autosplitter: &mut DeclarativeAutoSplitter, values: &[(usize, u16)]| {
+            for &(address, value) in values {
+                memory[address..address + 2].copy_from_slice(&value.to_le_bytes());
+            }
+            let data: Vec<Vec<u8>> = plan
+                .iter()
+                .map(|&(address, size)| {
+                    let offset = (address - WRAM_START) as usize;
+                    memory[offset..offset + size].to_vec()
+                })
+                .collect();
+            autosplitter.update(&data);
+        };
+        poll(&mut autosplitter, before);
+        poll(&mut autosplitter, after);
+        !autosplitter.split(&settings).is_empty()
+    }
+
+    #[test]
+    fn tokenize_skips_comments_and_reads_literals() {
+        let tokens = tokenize(
+            r#"// a comment
+            /* a block
+               comment */ @"C:\splits" "a\"b" 0x1F 42 1.5 2f"#,
+        )
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Str(r"C:\splits".to_owned()),
+                Token::Str("a\"b".to_owned()),
+                Token::Number(0x1F),
+                Token::Number(42),
+                Token::Float,
+                Token::Float,
+            ]
+        );
+        assert!(tokenize("/* never closed").is_err());
+        assert!(tokenize("\"never closed").is_err());
+    }
+
+    #[test]
+    fn if_else_and_return_become_one_condition() {
+        let script = r#"
+            split {
+                if (vars.watchers["a"].Current == 1) {
+                    return true;
+                } else if (vars.watchers["a"].Current == 2) {
+                    return false;
+                }
+                return vars.watchers["a"].Current == 3;
+            }
+        "#;
+        let (file, warnings) = import_script(script);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(file.split.len(), 1);
+        let split = |a| splits(import_script(script).0, &[], &[(0x10, a)]);
+        assert!(split(1));
+        assert!(!split(2));
+        assert!(split(3));
+        assert!(!split(4));
+    }
+
+    #[test]
+    fn vars_tables_become_constants() {
+        let (file, warnings) = import_script(
+            r#"
+            startup {
+                vars.rooms = new Dictionary<string, int> {
+                    { "landing site", 0x91F8 },
+                    { "parlor", -2 },
+                };
+                vars.bossRoom = 0xA59F;
+            }
+            split {
+                return old.room != current.room
+                    && vars.watchers["room"].Current == vars.rooms["landing site"];
+            }
+            "#,
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(file.constants.get("rooms_landing_site"), Some(&0x91F8));
+        assert_eq!(file.constants.get("rooms_parlor"), Some(&-2));
+        assert_eq!(file.constants.get("bossRoom"), Some(&0xA59F));
+        assert!(file.split[0].contains("rooms_landing_site"));
+        assert!(splits(file, &[(0x79B, 0x1234)], &[(0x79B, 0x91F8)]));
+    }
+
+    #[test]
+    fn state_kept_in_vars_is_a_warning() {
+        let (file, warnings) = import_script(
+            r#"
+            startup { vars.count = 0; }
+            split {
+                vars.count++;
+                return vars.count == 3;
+            }
+            "#,
+        );
+        assert!(!file.constants.contains_key("count"));
+        assert!(
+            warnings
+                .iter()
+                .any(|w| w.contains("keeping state in vars.count isn't supported")),
+            "{:?}",
+            warnings
+        );
+        // The split that depends on it never fires
+        assert!(!splits(file, &[], &[]));
+    }
+
+    #[test]
+    fn game_time_with_warnings_is_left_out() {
+        let (file, warnings) = import_script(
+            r#"gameTime { return TimeSpan.FromMilliseconds(vars.watchers["a"].Current * 1000); }"#,
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(file.game_time.is_some());
+        let (file, warnings) = import_script(r#"gameTime { return TimeSpan.FromSeconds(1.5); }"#);
+        assert!(file.game_time.is_none());
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("gameTime couldn't be fully translated")));
+    }
+
+    #[test]
+    fn unterminated_blocks_are_errors() {
+        for script in [
+            "start {",
+            "split { if (vars.watchers[\"a\"].Current == 1) {",
+            "vars.rooms = new Dictionary<string, int> {",
+        ] {
+            let error = import("test", &format!("{}{}", WATCHERS, script))
+                .err()
+                .unwrap_or_else(|| panic!("{} imported", script));
+            assert_eq!(error.to_string(), "Unterminated block");
+        }
+    }
+}
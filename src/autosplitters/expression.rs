@@ -0,0 +1,641 @@
+//! A small expression language for autosplitter conditions.
+//!
+//! Expressions work on integers and follow C precedence, so bit tests need
+//! parentheses just like in an ASL script: `(eventFlags.current & 0x40) != 0`.
+//! Watchers are referenced as `name.old` and `name.current`, and any other
+//! bare identifier must be a named constant. `setting(name)` is 1 when the
+//! setting is enabled (names that aren't identifiers can be quoted, as in
+//! `setting("any%")`). A handful of helper functions take a watcher name as
+//! their first argument:
+//!
+//! * `changed(w)`: `w.old != w.current`
+//! * `transition(w, a, b)`: `w.old == a && w.current == b`
+//! * `entered(w, v)`: `w.old != v && w.current == v`
+//! * `bit_set(w, mask)`: some bit of `mask` went from clear to set
+//! * `bit_cleared(w, mask)`: some bit of `mask` went from set to clear
+//! * `increased_by(w, n)`: `w.old + n == w.current`
+//! * `gained(w, n)`: `w` went up by a multiple of `n`, for counters that can
+//!   go up more than once between polls
+//! * `changed_within(w, n)`: `w` changed in one of the last `n` polls
+//! * `changed_within_ms(w, ms)`: `w` changed in the last `ms` milliseconds
+//!   of real time
+//!
+//! The last two only look as far back as the watcher's `history`.
+
+use super::{MemoryWatcher, Settings};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Resolves the names used in an expression while it's being parsed
+pub trait Scope {
+    /// Index of the watcher in the slice passed to [`Expr::eval`]
+    fn watcher(&self, name: &str) -> Option<usize>;
+    fn constant(&self, name: &str) -> Option<i64>;
+    /// Whether `name` is a known setting
+    fn setting(&self, name: &str) -> bool;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Field {
+    Old,
+    Current,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Negate,
+    BitNot,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Function {
+    Changed,
+    Transition,
+    Entered,
+    BitSet,
+    BitCleared,
+    IncreasedBy,
+    Gained,
+    ChangedWithin,
+    ChangedWithinMs,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "changed" => Some(Function::Changed),
+            "transition" => Some(Function::Transition),
+            "entered" => Some(Function::Entered),
+            "bit_set" => Some(Function::BitSet),
+            "bit_cleared" => Some(Function::BitCleared),
+            "increased_by" => Some(Function::IncreasedBy),
+            "gained" => Some(Function::Gained),
+            "changed_within" => Some(Function::ChangedWithin),
+            "changed_within_ms" => Some(Function::ChangedWithinMs),
+            _ => None,
+        }
+    }
+
+    /// Number of arguments after the watcher
+    fn arity(&self) -> usize {
+        match self {
+            Function::Changed => 0,
+            Function::Transition => 2,
+            Function::Entered
+            | Function::BitSet
+            | Function::BitCleared
+            | Function::IncreasedBy
+            | Function::Gained
+            | Function::ChangedWithin
+            | Function::ChangedWithinMs => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Watcher(usize, Field),
+    Setting(Arc<str>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, usize, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+impl Expr {
+    pub fn parse(source: &str, scope: &dyn Scope) -> Result<Expr, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            scope,
+            end: source.len(),
+        };
+        let expr = parser.expression(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.error("Unexpected trailing input")),
+        }
+    }
+
+    pub fn eval(&self, watchers: &[MemoryWatcher], settings: &Settings) -> i64 {
+        let eval = |e: &Expr| e.eval(watchers, settings);
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Watcher(w, Field::Old) => watchers[*w].old,
+            Expr::Watcher(w, Field::Current) => watchers[*w].current,
+            Expr::Setting(name) => settings.get(name) as i64,
+            Expr::Unary(UnaryOp::Not, e) => (eval(e) == 0) as i64,
+            Expr::Unary(UnaryOp::Negate, e) => eval(e).wrapping_neg(),
+            Expr::Unary(UnaryOp::BitNot, e) => !eval(e),
+            // Short circuit the logical operators
+            Expr::Binary(BinaryOp::Or, l, r) => (eval(l) != 0 || eval(r) != 0) as i64,
+            Expr::Binary(BinaryOp::And, l, r) => (eval(l) != 0 && eval(r) != 0) as i64,
+            Expr::Binary(op, l, r) => {
+                let l = eval(l);
+                let r = eval(r);
+                match op {
+                    BinaryOp::Or | BinaryOp::And => unreachable!(),
+                    BinaryOp::BitOr => l | r,
+                    BinaryOp::BitXor => l ^ r,
+                    BinaryOp::BitAnd => l & r,
+                    BinaryOp::Eq => (l == r) as i64,
+                    BinaryOp::Ne => (l != r) as i64,
+                    BinaryOp::Lt => (l < r) as i64,
+                    BinaryOp::Le => (l <= r) as i64,
+                    BinaryOp::Gt => (l > r) as i64,
+                    BinaryOp::Ge => (l >= r) as i64,
+                    BinaryOp::Shl => l.wrapping_shl(r as u32),
+                    BinaryOp::Shr => l.wrapping_shr(r as u32),
+                    BinaryOp::Add => l.wrapping_add(r),
+                    BinaryOp::Sub => l.wrapping_sub(r),
+                    BinaryOp::Mul => l.wrapping_mul(r),
+                    // Division by zero is treated as 0 rather than taking
+                    // down the polling thread
+                    BinaryOp::Div => l.checked_div(r).unwrap_or(0),
+                    BinaryOp::Rem => l.checked_rem(r).unwrap_or(0),
+                }
+            }
+            Expr::Call(f, w, args) => {
+                let old = watchers[*w].old;
+                let current = watchers[*w].current;
+                let arg = |i: usize| eval(&args[i]);
+                let result = match f {
+                    Function::Changed => old != current,
+                    Function::Transition => old == arg(0) && current == arg(1),
+                    Function::Entered => {
+                        let v = arg(0);
+                        old != v && current == v
+                    }
+                    Function::BitSet => {
+                        let mask = arg(0);
+                        (old & mask) != mask && (current & !old & mask) != 0
+                    }
+                    Function::BitCleared => {
+                        let mask = arg(0);
+                        (old & mask) != 0 && (old & !current & mask) != 0
+                    }
+                    Function::IncreasedBy => old.checked_add(arg(0)) == Some(current),
+                    Function::Gained => {
+                        let step = arg(0);
+                        current > old
+                            && step != 0
+                            && current
+                                .checked_sub(old)
+                                .is_some_and(|gain| gain % step == 0)
+                    }
+                    Function::ChangedWithin => watchers[*w].changed_within(arg(0).max(0) as usize),
+                    Function::ChangedWithinMs => {
+                        let ms = arg(0).max(0) as u128;
+                        watchers[*w]
+                            .last_changed()
+                            .is_some_and(|when| when.elapsed().as_millis() <= ms)
+                    }
+                };
+                result as i64
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Str(String),
+    Op(&'static str),
+}
+
+// Longest operators first so that `<=` isn't lexed as `<` `=`
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")", ",", ".",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    'outer: while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if c.is_ascii_digit() {
+            let (digits, radix) = if source[i..].starts_with("0x") || source[i..].starts_with("0X")
+            {
+                i += 2;
+                (i, 16)
+            } else {
+                (i, 10)
+            };
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let text = source[digits..i].replace('_', "");
+            let n = i64::from_str_radix(&text, radix).map_err(|_| ParseError {
+                message: format!("Invalid number \"{}\"", &source[start..i]),
+                position: start,
+            })?;
+            tokens.push((Token::Number(n), start));
+            continue;
+        }
+        if c == b'"' {
+            let end = source[i + 1..].find('"').ok_or(ParseError {
+                message: "Unterminated string".to_owned(),
+                position: start,
+            })?;
+            tokens.push((Token::Str(source[i + 1..i + 1 + end].to_owned()), start));
+            i += end + 2;
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(source[start..i].to_owned()), start));
+            continue;
+        }
+        for op in OPERATORS {
+            if source[i..].starts_with(op) {
+                tokens.push((Token::Op(op), start));
+                i += op.len();
+                continue 'outer;
+            }
+        }
+        return Err(ParseError {
+            message: format!(
+                "Unexpected character '{}'",
+                source[i..].chars().next().unwrap_or('?')
+            ),
+            position: start,
+        });
+    }
+    Ok(tokens)
+}
+
+// Binary operators from loosest to tightest binding
+const PRECEDENCE: &[&[(&str, BinaryOp)]] = &[
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+    &[
+        ("<", BinaryOp::Lt),
+        ("<=", BinaryOp::Le),
+        (">", BinaryOp::Gt),
+        (">=", BinaryOp::Ge),
+    ],
+    &[("<<", BinaryOp::Shl), (">>", BinaryOp::Shr)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    &[
+        ("*", BinaryOp::Mul),
+        ("/", BinaryOp::Div),
+        ("%", BinaryOp::Rem),
+    ],
+];
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    scope: &'a dyn Scope,
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, p)| *p)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_owned(),
+            position: self.position(),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), ParseError> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", op)))
+        }
+    }
+
+    fn expression(&mut self, level: usize) -> Result<Expr, ParseError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut lhs = self.expression(level + 1)?;
+        'operators: loop {
+            for (text, op) in PRECEDENCE[level] {
+                if self.eat(text) {
+                    let rhs = self.expression(level + 1)?;
+                    lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+                    continue 'operators;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("!") {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.unary()?)));
+        }
+        if self.eat("~") {
+            return Ok(Expr::Unary(UnaryOp::BitNot, Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn watcher(&mut self, name: &str) -> Result<usize, ParseError> {
+        self.scope
+            .watcher(name)
+            .ok_or_else(|| self.error(&format!("Unknown watcher \"{}\"", name)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Op("(")) => {
+                let e = self.expression(0)?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) if name == "setting" && self.eat("(") => {
+                let position = self.position();
+                let setting = match self.next() {
+                    Some(Token::Ident(s)) | Some(Token::Str(s)) => s,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(self.error("Expected a setting name"));
+                    }
+                };
+                if !self.scope.setting(&setting) {
+                    return Err(ParseError {
+                        message: format!("Unknown setting \"{}\"", setting),
+                        position,
+                    });
+                }
+                self.expect(")")?;
+                Ok(Expr::Setting(setting.into()))
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat("(") {
+                    let f = Function::from_name(&name).ok_or(ParseError {
+                        message: format!("Unknown function \"{}\"", name),
+                        position,
+                    })?;
+                    let w = match self.next() {
+                        Some(Token::Ident(w)) => {
+                            self.pos -= 1;
+                            let w = self.watcher(&w)?;
+                            self.pos += 1;
+                            w
+                        }
+                        _ => return Err(self.error("Expected a watcher name")),
+                    };
+                    let mut args = vec![];
+                    for _ in 0..f.arity() {
+                        self.expect(",")?;
+                        args.push(self.expression(0)?);
+                    }
+                    self.expect(")")?;
+                    return Ok(Expr::Call(f, w, args));
+                }
+                if self.eat(".") {
+                    let field = match self.next() {
+                        Some(Token::Ident(f)) if f == "old" => Field::Old,
+                        Some(Token::Ident(f)) if f == "current" => Field::Current,
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error("Expected \"old\" or \"current\""));
+                        }
+                    };
+                    let w = self.scope.watcher(&name).ok_or(ParseError {
+                        message: format!("Unknown watcher \"{}\"", name),
+                        position,
+                    })?;
+                    return Ok(Expr::Watcher(w, field));
+                }
+                self.scope
+                    .constant(&name)
+                    .map(Expr::Number)
+                    .ok_or(ParseError {
+                        message: format!("Unknown constant \"{}\"", name),
+                        position,
+                    })
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.error("Expected an expression"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autosplitters::Width;
+
+    struct TestScope;
+
+    impl Scope for TestScope {
+        fn watcher(&self, name: &str) -> Option<usize> {
+            ["a", "b"].iter().position(|&w| w == name)
+        }
+
+        fn constant(&self, name: &str) -> Option<i64> {
+            (name == "ten").then_some(10)
+        }
+
+        fn setting(&self, name: &str) -> bool {
+            name == "on" || name == "any%"
+        }
+    }
+
+    /// Evaluate with watcher `a` going from `old` to `current`, and `b`
+    /// staying at 0
+    fn eval_with(source: &str, old: i64, current: i64) -> i64 {
+        let expr = Expr::parse(source, &TestScope).unwrap();
+        let mut a = MemoryWatcher::new(0, Width::Dword);
+        a.old = old;
+        a.current = current;
+        let b = MemoryWatcher::new(4, Width::Byte);
+        let mut settings = Settings::new();
+        settings.insert("on", true);
+        settings.insert("any%", false);
+        expr.eval(&[a, b], &settings)
+    }
+
+    fn eval(source: &str) -> i64 {
+        eval_with(source, 0, 0)
+    }
+
+    fn parse_error(source: &str) -> ParseError {
+        Expr::parse(source, &TestScope).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("6 & 3 == 3"), 0);
+        assert_eq!(eval("(6 & 3) == 2"), 1);
+        assert_eq!(eval("1 | 2 ^ 3 & 4"), 3);
+        assert_eq!(eval("0 || 1 && 0"), 0);
+        assert_eq!(eval("!0 + -2 * ~0"), 3);
+        assert_eq!(eval("7 / 0 + 7 % 0"), 0);
+    }
+
+    #[test]
+    fn numbers_and_names() {
+        assert_eq!(eval("0x1F + 0X10"), 47);
+        assert_eq!(eval("0xFF_FF"), 0xFFFF);
+        assert_eq!(eval("ten * 2"), 20);
+        assert_eq!(eval_with("a.old * 100 + a.current", 3, 4), 304);
+        assert_eq!(eval("setting(on) + setting(\"any%\")"), 1);
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert_eq!(
+            parse_error("ten + eleven").message,
+            "Unknown constant \"eleven\""
+        );
+        assert_eq!(parse_error("c.old").message, "Unknown watcher \"c\"");
+        assert_eq!(parse_error("changed(c)").message, "Unknown watcher \"c\"");
+        assert_eq!(
+            parse_error("a.older").message,
+            "Expected \"old\" or \"current\""
+        );
+        assert_eq!(
+            parse_error("setting(off)").message,
+            "Unknown setting \"off\""
+        );
+        assert_eq!(
+            parse_error("grew(a, 1)").message,
+            "Unknown function \"grew\""
+        );
+    }
+
+    #[test]
+    fn parse_errors_carry_a_position() {
+        assert_eq!(parse_error("1 + $").position, 4);
+        assert_eq!(parse_error("ten + eleven").position, 6);
+        assert_eq!(parse_error("(1 + 2").position, 6);
+        assert_eq!(parse_error("1 2").position, 2);
+        assert_eq!(parse_error("0x").position, 0);
+        assert_eq!(parse_error("transition(a, 1)").position, 15);
+        assert_eq!(
+            parse_error("1 +").to_string(),
+            "Expected an expression at offset 3"
+        );
+    }
+
+    #[test]
+    fn changed_and_transitions() {
+        assert_eq!(eval_with("changed(a)", 1, 2), 1);
+        assert_eq!(eval_with("changed(a)", 2, 2), 0);
+        assert_eq!(eval_with("transition(a, 1, 2)", 1, 2), 1);
+        assert_eq!(eval_with("transition(a, 1, 2)", 0, 2), 0);
+        assert_eq!(eval_with("entered(a, 2)", 1, 2), 1);
+        assert_eq!(eval_with("entered(a, 2)", 2, 2), 0);
+    }
+
+    #[test]
+    fn bit_set_and_cleared() {
+        assert_eq!(eval_with("bit_set(a, 0x04)", 0x01, 0x05), 1);
+        assert_eq!(eval_with("bit_set(a, 0x04)", 0x05, 0x05), 0);
+        assert_eq!(eval_with("bit_set(a, 0x04)", 0x05, 0x01), 0);
+        // Any bit of the mask counts, unless they were all set already
+        assert_eq!(eval_with("bit_set(a, 0x06)", 0x02, 0x06), 1);
+        assert_eq!(eval_with("bit_set(a, 0x06)", 0x06, 0x07), 0);
+        assert_eq!(eval_with("bit_cleared(a, 0x04)", 0x05, 0x01), 1);
+        assert_eq!(eval_with("bit_cleared(a, 0x04)", 0x01, 0x01), 0);
+    }
+
+    #[test]
+    fn increased_by_and_gained() {
+        assert_eq!(eval_with("increased_by(a, 5)", 10, 15), 1);
+        assert_eq!(eval_with("increased_by(a, 5)", 10, 20), 0);
+        // Too big to add to the old value, which no watcher can match
+        assert_eq!(eval_with("increased_by(a, 0x7FFFFFFFFFFFFFFF)", 1, 0), 0);
+        assert_eq!(eval_with("gained(a, 5)", 10, 15), 1);
+        assert_eq!(eval_with("gained(a, 5)", 10, 20), 1);
+        assert_eq!(eval_with("gained(a, 5)", 10, 12), 0);
+        assert_eq!(eval_with("gained(a, 5)", 15, 10), 0);
+        assert_eq!(eval_with("gained(a, 0)", 10, 15), 0);
+        // Likewise a gain too big to compute
+        assert_eq!(eval_with("gained(a, 1)", i64::MIN, i64::MAX), 0);
+    }
+}
@@ -0,0 +1,392 @@
+//! Recordings of the memory read from the SNES, so that a session can be
+//! fed back through an autosplitter without a console.
+//!
+//! A recording starts with [`MAGIC`] and a version byte, followed by one
+//! entry per read:
+//!
+//! * `u32` milliseconds since the recording started
+//! * `u16` number of ranges
+//! * for each range: `u32` address, `u32` size, and a `u8` tag. Tag 0 means
+//!   the bytes are the same as the last time this range was read; tag 1 is
+//!   followed by `size` bytes.
+//!
+//! All integers are little endian. Since most of memory doesn't change from
+//! one poll to the next, most ranges end up taking 9 bytes.
+
+use super::MemorySource;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const MAGIC: &[u8; 8] = b"ANNELID\0";
+const VERSION: u8 = 1;
+
+const SAME: u8 = 0;
+const BYTES: u8 = 1;
+
+/// Writes reads to a recording as they happen
+pub struct RecordingWriter<W: Write> {
+    out: W,
+    started: Instant,
+    last: HashMap<(u32, usize), Vec<u8>>,
+}
+
+impl RecordingWriter<BufWriter<File>> {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        RecordingWriter::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> RecordingWriter<W> {
+    pub fn new(mut out: W) -> std::io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        Ok(RecordingWriter {
+            out,
+            started: Instant::now(),
+            last: HashMap::new(),
+        })
+    }
+
+    pub fn write_read(&mut self, pairs: &[(u32, usize)], data: &[Vec<u8>]) -> std::io::Result<()> {
+        let elapsed = self.started.elapsed().as_millis() as u32;
+        self.out.write_all(&elapsed.to_le_bytes())?;
+        self.out.write_all(&(pairs.len() as u16).to_le_bytes())?;
+        for (&(address, size), bytes) in pairs.iter().zip(data.iter()) {
+            self.out.write_all(&address.to_le_bytes())?;
+            self.out.write_all(&(size as u32).to_le_bytes())?;
+            if self.last.get(&(address, size)) == Some(bytes) {
+                self.out.write_all(&[SAME])?;
+            } else {
+                self.out.write_all(&[BYTES])?;
+                self.out.write_all(bytes)?;
+                self.last.insert((address, size), bytes.clone());
+            }
+        }
+        // Flush on every read so that a crash doesn't lose the moments
+        // leading up to it, which are usually the interesting ones
+        self.out.flush()
+    }
+}
+
+/// A memory source that records everything read through it
+pub struct Recorder<S> {
+    source: S,
+    writer: Option<RecordingWriter<BufWriter<File>>>,
+}
+
+impl<S: MemorySource> Recorder<S> {
+    /// Without a writer, reads are passed through unrecorded
+    pub fn new(source: S, writer: Option<RecordingWriter<BufWriter<File>>>) -> Self {
+        Recorder { source, writer }
+    }
+}
+
+impl<S: MemorySource> MemorySource for Recorder<S> {
+    fn get_addresses(&mut self, pairs: &[(u32, usize)]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        let data = self.source.get_addresses(pairs)?;
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(e) = writer.write_read(pairs, &data) {
+                // Losing the recording is no reason to stop autosplitting
+                println!("Stopped recording: {}", e);
+                self.writer = None;
+            }
+        }
+        Ok(data)
+    }
+
+    fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        self.source.reset()
+    }
+}
+
+impl<S> Deref for Recorder<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.source
+    }
+}
+
+impl<S> DerefMut for Recorder<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+}
+
+/// One read from a recording
+pub struct RecordedRead {
+    pub elapsed: Duration,
+    pub ranges: Vec<(u32, Vec<u8>)>,
+}
+
+impl RecordedRead {
+    fn parse(
+        input: &mut Input,
+        last: &mut HashMap<(u32, usize), Vec<u8>>,
+    ) -> Result<RecordedRead, Box<dyn Error>> {
+        let elapsed = Duration::from_millis(input.u32()? as u64);
+        let count = input.u16()?;
+        let mut ranges = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let address = input.u32()?;
+            let size = input.u32()? as usize;
+            let data = match input.take(1)?[0] {
+                SAME => last
+                    .get(&(address, size))
+                    .ok_or_else(|| format!("Repeated read of {:#x} before any data", address))?
+                    .clone(),
+                BYTES => {
+                    let data = input.take(size)?.to_vec();
+                    last.insert((address, size), data.clone());
+                    data
+                }
+                tag => Err(format!("Unknown tag {} at offset {}", tag, input.pos - 1))?,
+            };
+            ranges.push((address, data));
+        }
+        Ok(RecordedRead { elapsed, ranges })
+    }
+
+    /// The bytes at `address`, pieced together from as many ranges as it
+    /// takes, since recordings made with another read plan split memory
+    /// up differently
+    fn get(&self, address: u32, size: usize) -> Option<Vec<u8>> {
+        (address..address + size as u32)
+            .map(|byte| {
+                self.ranges.iter().find_map(|(start, bytes)| {
+                    bytes.get(byte.checked_sub(*start)? as usize).copied()
+                })
+            })
+            .collect()
+    }
+}
+
+/// A memory source that plays back a recording. Each request is answered
+/// by the next recorded read that covers all of it, so reads the replaying
+/// side doesn't make, like cartridge header checks with game detection
+/// turned off, are skipped over.
+pub struct Replay {
+    reads: Vec<RecordedRead>,
+    next: usize,
+    started: Option<Instant>,
+    realtime: bool,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, Box<dyn Error>> {
+        let mut bytes = vec![];
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Replay::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Replay, Box<dyn Error>> {
+        let mut input = Input { bytes, pos: 0 };
+        if input.take(MAGIC.len())? != MAGIC {
+            Err("Not an annelid recording")?;
+        }
+        let version = input.take(1)?[0];
+        if version != VERSION {
+            Err(format!("Unsupported recording version {}", version))?;
+        }
+        let mut last: HashMap<(u32, usize), Vec<u8>> = HashMap::new();
+        let mut reads = vec![];
+        while input.pos < bytes.len() {
+            match RecordedRead::parse(&mut input, &mut last) {
+                Ok(read) => reads.push(read),
+                // annelid was probably closed mid-write
+                Err(e) if e.is::<Truncated>() => {
+                    println!("Recording is truncated after {} reads", reads.len());
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Replay {
+            reads,
+            next: 0,
+            started: None,
+            realtime: false,
+        })
+    }
+
+    /// Wait between reads so they're answered as far apart as they were
+    /// recorded, instead of as fast as they're asked for
+    pub fn set_realtime(&mut self, realtime: bool) {
+        self.realtime = realtime;
+    }
+
+    /// When the last read answered was recorded
+    pub fn elapsed(&self) -> Duration {
+        self.next
+            .checked_sub(1)
+            .map_or(Duration::ZERO, |i| self.reads[i].elapsed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.reads.len()
+    }
+}
+
+impl MemorySource for Replay {
+    fn get_addresses(&mut self, pairs: &[(u32, usize)]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        for i in self.next..self.reads.len() {
+            let read = &self.reads[i];
+            let data: Option<Vec<Vec<u8>>> = pairs
+                .iter()
+                .map(|&(address, size)| read.get(address, size))
+                .collect();
+            if let Some(data) = data {
+                if self.realtime {
+                    let started = *self
+                        .started
+                        .get_or_insert_with(|| Instant::now() - read.elapsed);
+                    if let Some(wait) =
+                        (started + read.elapsed).checked_duration_since(Instant::now())
+                    {
+                        std::thread::sleep(wait);
+                    }
+                }
+                self.next = i + 1;
+                return Ok(data);
+            }
+        }
+        self.next = self.reads.len();
+        Err("End of recording".into())
+    }
+}
+
+#[derive(Debug)]
+struct Truncated;
+
+impl std::fmt::Display for Truncated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Recording is truncated")
+    }
+}
+
+impl Error for Truncated {}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        let bytes = self.bytes.get(self.pos..self.pos + n).ok_or(Truncated)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &[(u32, usize)] = &[(0xF50000, 4), (0xF50100, 2)];
+
+    fn frames() -> Vec<Vec<Vec<u8>>> {
+        vec![
+            vec![vec![1, 2, 3, 4], vec![5, 6]],
+            // Only the second range changes
+            vec![vec![1, 2, 3, 4], vec![7, 8]],
+            vec![vec![1, 2, 3, 4], vec![7, 8]],
+        ]
+    }
+
+    fn record(frames: &[Vec<Vec<u8>>]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut writer = RecordingWriter::new(&mut bytes).unwrap();
+        for frame in frames {
+            writer.write_read(PLAN, frame).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = record(&frames());
+        let mut replay = Replay::parse(&bytes).unwrap();
+        assert_eq!(replay.reads.len(), 3);
+        for frame in frames() {
+            assert_eq!(replay.get_addresses(PLAN).unwrap(), frame);
+        }
+        assert!(replay.is_finished());
+        assert!(replay.get_addresses(PLAN).is_err());
+    }
+
+    #[test]
+    fn unchanged_ranges_arent_repeated() {
+        let header = MAGIC.len() + 1;
+        let frame = |ranges: &[usize]| 4 + 2 + ranges.iter().map(|size| 9 + size).sum::<usize>();
+        let expected = header + frame(&[4, 2]) + frame(&[0, 2]) + frame(&[0, 0]);
+        assert_eq!(record(&frames()).len(), expected);
+    }
+
+    #[test]
+    fn replay_pieces_together_ranges_from_another_plan() {
+        let mut replay = Replay::parse(&record(&frames())).unwrap();
+        assert_eq!(
+            replay
+                .get_addresses(&[(0xF50001, 2), (0xF50101, 1)])
+                .unwrap(),
+            vec![vec![2, 3], vec![6]]
+        );
+        // Nothing recorded covers this, so the recording runs out
+        assert!(replay.get_addresses(&[(0xF50004, 1)]).is_err());
+    }
+
+    #[test]
+    fn bad_magic_and_newer_versions_are_rejected() {
+        let mut bytes = record(&frames());
+        bytes[0] = b'X';
+        let error = Replay::parse(&bytes).err().unwrap();
+        assert_eq!(error.to_string(), "Not an annelid recording");
+
+        let mut bytes = record(&frames());
+        bytes[MAGIC.len()] = VERSION + 1;
+        let error = Replay::parse(&bytes).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!("Unsupported recording version {}", VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn truncated_recordings_dont_panic() {
+        let bytes = record(&frames());
+        let header = MAGIC.len() + 1;
+        for len in 0..header {
+            assert!(Replay::parse(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+        // A recording cut off mid-read, like when annelid is closed while
+        // writing, keeps the reads before it
+        for len in header..bytes.len() {
+            let replay = Replay::parse(&bytes[..len]).unwrap();
+            assert!(replay.reads.len() < 3, "{} bytes", len);
+        }
+        assert_eq!(Replay::parse(&bytes).unwrap().reads.len(), 3);
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let mut bytes = record(&frames()[..1]);
+        // The tag of the first range
+        bytes[MAGIC.len() + 1 + 4 + 2 + 8] = 7;
+        let error = Replay::parse(&bytes).err().unwrap();
+        assert!(error.to_string().starts_with("Unknown tag 7"), "{}", error);
+    }
+}
@@ -1,15 +1,16 @@
 #![allow(non_upper_case_globals)]
 
+use super::{
+    plan_reads, store_reads, AutoSplitter, MemoryWatcher, Region, Settings, SplitReason, Width,
+    WRAM_SIZE,
+};
 use livesplit_core::TimeSpan;
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use std::error::Error;
+use std::collections::HashMap;
 use std::ops::Index;
-use std::time::Instant;
 use time::Duration;
 
 lazy_static! {
-    static ref roomIDEnum: HashMap<&'static str, u32> = {
+    static ref roomIDEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         m.insert("landingSite",                    0x91F8 );
         m.insert("crateriaPowerBombRoom",          0x93AA );
@@ -156,7 +157,7 @@ lazy_static! {
         m.insert("ceresRidley",                    0xE0B5 );
         m
     };
-    static ref mapInUseEnum: HashMap<&'static str, u32> = {
+    static ref mapInUseEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         m.insert( "crateria",   0x0 );
         m.insert( "brinstar",   0x1 );
@@ -168,17 +169,24 @@ lazy_static! {
         m
     };
 
-    static ref gameStateEnum: HashMap<&'static str, u32> = {
+    static ref gameStateEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
+        m.insert( "optionsMenu",            0x2  );
+        m.insert( "fileSelectMenus",        0x4  );
+        m.insert( "fileSelectMap",          0x5  );
+        m.insert( "loadingGameData",        0x6  );
         m.insert( "normalGameplay",         0x8  );
         m.insert( "doorTransition",         0xB  );
+        m.insert( "samusOutOfHealth",       0x13 ); // the first state of the death sequence
+        m.insert( "introCutscene",          0x1E );
+        m.insert( "newGame",                0x1F );
         m.insert( "startOfCeresCutscene",   0x20 );
         m.insert( "preEndCutscene",         0x26 ); // briefly at this value during the black screen transition after the ship fades out
         m.insert( "endCutscene",            0x27 );
         m
     };
 
-    static ref unlockFlagEnum: HashMap<&'static str, u32> = {
+    static ref unlockFlagEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         // First item byte
         m.insert( "variaSuit",      0x1 );
@@ -203,7 +211,7 @@ lazy_static! {
         m
     };
 
-    static ref motherBrainMaxHPEnum: HashMap<&'static str, u32> = {
+    static ref motherBrainMaxHPEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         m.insert( "phase1", 0xBB8  );    // 3000
         m.insert( "phase2", 0x4650 );   // 18000
@@ -211,14 +219,14 @@ lazy_static! {
         m
     };
 
-    static ref eventFlagEnum: HashMap<&'static str, u32> = {
+    static ref eventFlagEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         m.insert( "zebesAblaze",    0x40 );
         m.insert( "tubeBroken",     0x8  );
         m
     };
 
-    static ref bossFlagEnum: HashMap<&'static str, u32> = {
+    static ref bossFlagEnum: HashMap<&'static str, i64> = {
         let mut m = HashMap::new();
         // Crateria
         m.insert( "bombTorizo",     0x4 );
@@ -242,1026 +250,819 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Settings {
-    data: HashMap<std::sync::Arc<str>, (bool, Option<String>)>,
-    #[serde(skip)]
-    modified_after_creation: bool,
+/// The Super Metroid settings tree, with the defaults from SuperMetroid.asl
+pub fn default_settings() -> Settings {
+    let mut settings = Settings::new();
+    // Start the timer automatically
+    settings.insert("startConditions", true);
+    // Start when a new game is started from the file select
+    settings.insert_with_parent("normalStart", true, "startConditions");
+    // Start when the intro cutscene ends
+    settings.insert_with_parent("cutsceneStart", true, "startConditions");
+    // Start when loading a save from the file select map, for categories like Spore Spawn RTA
+    settings.insert_with_parent("zebesStart", true, "startConditions");
+    // Reset the timer automatically
+    settings.insert("resetConditions", true);
+    // Reset on a soft reset (L + R + Start + Select) or power cycle
+    settings.insert_with_parent("softReset", true, "resetConditions");
+    // Reset on going back to the file select, such as quitting from the game over screen
+    settings.insert_with_parent("fileSelectReset", false, "resetConditions");
+    // Reset when Samus dies, for practicing a segment from a save
+    settings.insert_with_parent("deathReset", false, "resetConditions");
+    // Undo splits for progress lost by dying or reloading a save
+    settings.insert("undoOnReload", false);
+    // Split on Missiles, Super Missiles, and Power Bombs
+    settings.insert("ammoPickups", true);
+    // Split on the first Missile pickup
+    settings.insert_with_parent("firstMissile", false, "ammoPickups");
+    // Split on each Missile upgrade
+    settings.insert_with_parent("allMissiles", false, "ammoPickups");
+    // Split on specific Missile Pack locations
+    settings.insert_with_parent("specificMissiles", false, "ammoPickups");
+    // Split on Crateria Missile Pack locations
+    settings.insert_with_parent("crateriaMissiles", false, "specificMissiles");
+    // Split on picking up the Missile Pack located at the bottom left of the West Ocean
+    settings.insert_with_parent("oceanBottomMissiles", false, "crateriaMissiles");
+    // Split on picking up the Missile Pack located in the ceiling tile in West Ocean
+    settings.insert_with_parent("oceanTopMissiles", false, "crateriaMissiles");
+    // Split on picking up the Missile Pack located in the Morphball maze section of West Ocean
+    settings.insert_with_parent("oceanMiddleMissiles", false, "crateriaMissiles");
+    // Split on picking up the Missile Pack in The Moat, also known as The Lake
+    settings.insert_with_parent("moatMissiles", false, "crateriaMissiles");
+    // Split on picking up the Missile Pack in the Pit Room
+    settings.insert_with_parent("oldTourianMissiles", false, "crateriaMissiles");
+    // Split on picking up the right side Missile Pack at the end of Gauntlet(Green Pirates Shaft)
+    settings.insert_with_parent("gauntletRightMissiles", false, "crateriaMissiles");
+    // Split on picking up the left side Missile Pack at the end of Gauntlet(Green Pirates Shaft)
+    settings.insert_with_parent("gauntletLeftMissiles", false, "crateriaMissiles");
+    // Split on picking up the Missile Pack located in The Final Missile
+    settings.insert_with_parent("dentalPlan", false, "crateriaMissiles");
+    // Split on Brinstar Missile Pack locations
+    settings.insert_with_parent("brinstarMissiles", false, "specificMissiles");
+    // Split on picking up the Missile Pack located below the crumble bridge in the Early Supers Room
+    settings.insert_with_parent("earlySuperBridgeMissiles", false, "brinstarMissiles");
+    // Split on picking up the first Missile Pack behind the Brinstar Reserve Tank
+    settings.insert_with_parent("greenBrinstarReserveMissiles", false, "brinstarMissiles");
+    // Split on picking up the second Missile Pack behind the Brinstar Reserve Tank Room
+    settings.insert_with_parent(
+        "greenBrinstarExtraReserveMissiles",
+        false,
+        "brinstarMissiles",
+    );
+    // Split on picking up the Missile Pack located left of center in Big Pink
+    settings.insert_with_parent("bigPinkTopMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack located at the bottom left of Big Pink
+    settings.insert_with_parent("chargeMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack in Green Hill Zone
+    settings.insert_with_parent("greenHillsMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack in the Blue Brinstar Energy Tank Room
+    settings.insert_with_parent("blueBrinstarETankMissiles", false, "brinstarMissiles");
+    // Split on picking up the first Missile Pack of the game(First Missile Room)
+    settings.insert_with_parent("alphaMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack located on the pedestal in Billy Mays' Room
+    settings.insert_with_parent("billyMaysMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack located in the floor of Billy Mays' Room
+    settings.insert_with_parent("butWaitTheresMoreMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack in the Alpha Power Bombs Room
+    settings.insert_with_parent("redBrinstarMissiles", false, "brinstarMissiles");
+    // Split on picking up the Missile Pack in the Warehouse Kihunter Room
+    settings.insert_with_parent("warehouseMissiles", false, "brinstarMissiles");
+    // Split on Norfair Missile Pack locations
+    settings.insert_with_parent("norfairMissiles", false, "specificMissiles");
+    // Split on picking up the Missile Pack in Cathedral
+    settings.insert_with_parent("cathedralMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in Crumble Shaft
+    settings.insert_with_parent("crumbleShaftMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in Crocomire Escape
+    settings.insert_with_parent("crocomireEscapeMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Hi Jump Energy Tank Room
+    settings.insert_with_parent("hiJumpMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Post Crocomire Missile Room, also known as Cosine Room
+    settings.insert_with_parent("postCrocomireMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Post Crocomire Jump Room
+    settings.insert_with_parent("grappleMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Norfair Reserve Tank Room
+    settings.insert_with_parent("norfairReserveMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Green Bubbles Missile Room
+    settings.insert_with_parent("greenBubblesMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in Bubble Mountain
+    settings.insert_with_parent("bubbleMountainMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in Speed Booster Hall
+    settings.insert_with_parent("speedBoostMissiles", false, "norfairMissiles");
+    // Split on picking up the Wave Missile Pack in Double Chamber
+    settings.insert_with_parent("waveMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Golden Torizo's Room
+    settings.insert_with_parent("goldTorizoMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Mickey Mouse Room
+    settings.insert_with_parent("mickeyMouseMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the Lower Norfair Springball Maze Room
+    settings.insert_with_parent("lowerNorfairSpringMazeMissiles", false, "norfairMissiles");
+    // Split on picking up the Missile Pack in the The Musketeers' Room
+    settings.insert_with_parent("threeMusketeersMissiles", false, "norfairMissiles");
+    // Split on Wrecked Ship Missile Pack locations
+    settings.insert_with_parent("wreckedShipMissiles", false, "specificMissiles");
+    // Split on picking up the Missile Pack in Wrecked Ship Main Shaft
+    settings.insert_with_parent("wreckedShipMainShaftMissiles", false, "wreckedShipMissiles");
+    // Split on picking up the Missile Pack in Bowling Alley
+    settings.insert_with_parent("bowlingMissiles", false, "wreckedShipMissiles");
+    // Split on picking up the Missile Pack in the Wrecked Ship East Missile Room
+    settings.insert_with_parent("atticMissiles", false, "wreckedShipMissiles");
+    // Split on Maridia Missile Pack locations
+    settings.insert_with_parent("maridiaMissiles", false, "specificMissiles");
+    // Split on picking up the Missile Pack in Main Street
+    settings.insert_with_parent("mainStreetMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in the Mama Turtle Room
+    settings.insert_with_parent("mamaTurtleMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in Watering Hole
+    settings.insert_with_parent("wateringHoleMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in the Pseudo Plasma Spark Room
+    settings.insert_with_parent("beachMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in West Sand Hole
+    settings.insert_with_parent("leftSandPitMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in East Sand Hole
+    settings.insert_with_parent("rightSandPitMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in Aqueduct
+    settings.insert_with_parent("aqueductMissiles", false, "maridiaMissiles");
+    // Split on picking up the Missile Pack in The Precious Room
+    settings.insert_with_parent("preDraygonMissiles", false, "maridiaMissiles");
+    // Split on the first Super Missile pickup
+    settings.insert_with_parent("firstSuper", false, "ammoPickups");
+    // Split on each Super Missile upgrade
+    settings.insert_with_parent("allSupers", false, "ammoPickups");
+    // Split on specific Super Missile Pack locations
+    settings.insert_with_parent("specificSupers", false, "ammoPickups");
+    // Split on picking up the Super Missile Pack in the Crateria Super Room
+    settings.insert_with_parent("climbSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Spore Spawn Super Room (NOTE: SSTRA splits when the dialogue box disappears, not on touch. Use Spore Spawn RTA Finish for SSTRA runs.)
+    settings.insert_with_parent("sporeSpawnSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Early Supers Room
+    settings.insert_with_parent("earlySupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Etecoon Super Room
+    settings.insert_with_parent("etecoonSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Golden Torizo's Room
+    settings.insert_with_parent("goldTorizoSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Wrecked Ship West Super Room
+    settings.insert_with_parent("wreckedShipLeftSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in the Wrecked Ship East Super Room
+    settings.insert_with_parent("wreckedShipRightSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in Main Street
+    settings.insert_with_parent("crabSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in Watering Hole
+    settings.insert_with_parent("wateringHoleSupers", false, "specificSupers");
+    // Split on picking up the Super Missile Pack in Aqueduct
+    settings.insert_with_parent("aqueductSupers", false, "specificSupers");
+    // Split on the first Power Bomb pickup
+    settings.insert_with_parent("firstPowerBomb", true, "ammoPickups");
+    // Split on each Power Bomb upgrade
+    settings.insert_with_parent("allPowerBombs", false, "ammoPickups");
+    // Split on specific Power Bomb Pack locations
+    settings.insert_with_parent("specificBombs", false, "ammoPickups");
+    // Split on picking up the Power Bomb Pack in the Crateria Power Bomb Room
+    settings.insert_with_parent("landingSiteBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Etecoon Room section of Green Brinstar Main Shaft
+    settings.insert_with_parent("etecoonBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Pink Brinstar Power Bomb Room
+    settings.insert_with_parent("pinkBrinstarBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Morph Ball Room
+    settings.insert_with_parent("blueBrinstarBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Alpha Power Bomb Room
+    settings.insert_with_parent("alphaBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Beta Power Bomb Room
+    settings.insert_with_parent("betaBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Post Crocomire Power Bomb Room
+    settings.insert_with_parent("crocomireBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in the Lower Norfair Escape Power Bomb Room
+    settings.insert_with_parent("lowerNorfairEscapeBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in Wasteland
+    settings.insert_with_parent("shameBombs", false, "specificBombs");
+    // Split on picking up the Power Bomb Pack in East Sand Hall
+    settings.insert_with_parent("rightSandPitBombs", false, "specificBombs");
+
+    // Split on Varia and Gravity pickups
+    settings.insert("suitUpgrades", true);
+    // Split on picking up the Varia Suit
+    settings.insert_with_parent("variaSuit", true, "suitUpgrades");
+    // Split on picking up the Gravity Suit
+    settings.insert_with_parent("gravSuit", true, "suitUpgrades");
+
+    // Split on beam upgrades
+    settings.insert("beamUpgrades", true);
+    // Split on picking up the Charge Beam
+    settings.insert_with_parent("chargeBeam", false, "beamUpgrades");
+    // Split on picking up the Spazer
+    settings.insert_with_parent("spazer", false, "beamUpgrades");
+    // Split on picking up the Wave Beam
+    settings.insert_with_parent("wave", true, "beamUpgrades");
+    // Split on picking up the Ice Beam
+    settings.insert_with_parent("ice", false, "beamUpgrades");
+    // Split on picking up the Plasma Beam
+    settings.insert_with_parent("plasma", false, "beamUpgrades");
+
+    // Split on boot upgrades
+    settings.insert("bootUpgrades", false);
+    // Split on picking up the Hi-Jump Boots
+    settings.insert_with_parent("hiJump", false, "bootUpgrades");
+    // Split on picking up Space Jump
+    settings.insert_with_parent("spaceJump", false, "bootUpgrades");
+    // Split on picking up the Speed Booster
+    settings.insert_with_parent("speedBooster", false, "bootUpgrades");
+
+    // Split on Energy Tanks and Reserve Tanks
+    settings.insert("energyUpgrades", false);
+    // Split on picking up the first Energy Tank
+    settings.insert_with_parent("firstETank", false, "energyUpgrades");
+    // Split on picking up each Energy Tank
+    settings.insert_with_parent("allETanks", false, "energyUpgrades");
+    // Split on specific Energy Tank locations
+    settings.insert_with_parent("specificETanks", false, "energyUpgrades");
+    // Split on picking up the Energy Tank in the Gauntlet Energy Tank Room
+    settings.insert_with_parent("gauntletETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Terminator Room
+    settings.insert_with_parent("terminatorETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Blue Brinstar Energy Tank Room
+    settings.insert_with_parent("ceilingETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Etecoon Energy Tank Room
+    settings.insert_with_parent("etecoonsETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in Waterway
+    settings.insert_with_parent("waterwayETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Hopper Energy Tank Room
+    settings.insert_with_parent("waveGateETank", false, "specificETanks");
+    // Split on picking up the Kraid Energy Tank in the Warehouse Energy Tank Room
+    settings.insert_with_parent("kraidETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in Crocomire's Room
+    settings.insert_with_parent("crocomireETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Hi Jump Energy Tank Room
+    settings.insert_with_parent("hiJumpETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Ridley Tank Room
+    settings.insert_with_parent("ridleyETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Lower Norfair Fireflea Room
+    settings.insert_with_parent("firefleaETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Wrecked Ship Energy Tank Room
+    settings.insert_with_parent("wreckedShipETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Mama Turtle Room
+    settings.insert_with_parent("tatoriETank", false, "specificETanks");
+    // Split on picking up the Energy Tank in the Botwoon Energy Tank Room
+    settings.insert_with_parent("botwoonETank", false, "specificETanks");
+    // Split on picking up each Reserve Tank
+    settings.insert_with_parent("reserveTanks", false, "energyUpgrades");
+    // Split on specific Reserve Tank locations
+    settings.insert_with_parent("specificRTanks", false, "energyUpgrades");
+    // Split on picking up the Reserve Tank in the Brinstar Reserve Tank Room
+    settings.insert_with_parent("brinstarReserve", false, "specificRTanks");
+    // Split on picking up the Reserve Tank in the Norfair Reserve Tank Room
+    settings.insert_with_parent("norfairReserve", false, "specificRTanks");
+    // Split on picking up the Reserve Tank in Bowling Alley
+    settings.insert_with_parent("wreckedShipReserve", false, "specificRTanks");
+    // Split on picking up the Reserve Tank in West Sand Hole
+    settings.insert_with_parent("maridiaReserve", false, "specificRTanks");
+
+    // Split on the miscellaneous upgrades
+    settings.insert("miscUpgrades", false);
+    // Split on picking up the Morphing Ball
+    settings.insert_with_parent("morphBall", false, "miscUpgrades");
+    // Split on picking up the Bomb
+    settings.insert_with_parent("bomb", false, "miscUpgrades");
+    // Split on picking up the Spring Ball
+    settings.insert_with_parent("springBall", false, "miscUpgrades");
+    // Split on picking up the Screw Attack
+    settings.insert_with_parent("screwAttack", false, "miscUpgrades");
+    // Split on picking up the Grapple Beam
+    settings.insert_with_parent("grapple", false, "miscUpgrades");
+    // Split on picking up the X-Ray Scope
+    settings.insert_with_parent("xray", false, "miscUpgrades");
+
+    // Split on transitions between areas
+    settings.insert("areaTransitions", true);
+    // Split on entering miniboss rooms (except Bomb Torizo)
+    settings.insert_with_parent("miniBossRooms", false, "areaTransitions");
+    // Split on entering major boss rooms
+    settings.insert_with_parent("bossRooms", false, "areaTransitions");
+    // Split on elevator transitions between areas (except Statue Room to Tourian)
+    settings.insert_with_parent("elevatorTransitions", false, "areaTransitions");
+    // Split on leaving Ceres Station
+    settings.insert_with_parent("ceresEscape", false, "areaTransitions");
+    // Split on entering the Wrecked Ship Entrance from the lower door of West Ocean
+    settings.insert_with_parent("wreckedShipEntrance", false, "areaTransitions");
+    // Split on entering Red Tower from Noob Bridge
+    settings.insert_with_parent("redTowerMiddleEntrance", false, "areaTransitions");
+    // Split on entering Red Tower from Skree Boost room
+    settings.insert_with_parent("redTowerBottomEntrance", false, "areaTransitions");
+    // Split on entering Kraid's Lair
+    settings.insert_with_parent("kraidsLair", false, "areaTransitions");
+    // Split on entering Rising Tide from Cathedral
+    settings.insert_with_parent("risingTideEntrance", false, "areaTransitions");
+    // Split on exiting Attic
+    settings.insert_with_parent("atticExit", false, "areaTransitions");
+    // Split on blowing up the tube to enter Maridia
+    settings.insert_with_parent("tubeBroken", false, "areaTransitions");
+    // Split on exiting West Cacattack Alley
+    settings.insert_with_parent("cacExit", false, "areaTransitions");
+    // Split on entering Toilet Bowl from either direction
+    settings.insert_with_parent("toilet", false, "areaTransitions");
+    // Split on entering Kronic Boost room
+    settings.insert_with_parent("kronicBoost", false, "areaTransitions");
+    // Split on the elevator down to Lower Norfair
+    settings.insert_with_parent("lowerNorfairEntrance", false, "areaTransitions");
+    // Split on entering Worst Room in the Game
+    settings.insert_with_parent("writg", false, "areaTransitions");
+    // Split on entering Red Kihunter Shaft from either Amphitheatre or Wastelands (NOTE: will split twice)
+    settings.insert_with_parent("redKiShaft", false, "areaTransitions");
+    // Split on entering Metal Pirates Room from Wasteland
+    settings.insert_with_parent("metalPirates", false, "areaTransitions");
+    // Split on entering Lower Norfair Springball Maze Room
+    settings.insert_with_parent("lowerNorfairSpringMaze", false, "areaTransitions");
+    // Split on moving from the Three Musketeers' Room to the Single Chamber
+    settings.insert_with_parent("lowerNorfairExit", false, "areaTransitions");
+    // Split on entering the Statues Room with all four major bosses defeated
+    settings.insert_with_parent("goldenFour", true, "areaTransitions");
+    // Split on the elevator down to Tourian
+    settings.insert_with_parent("tourianEntrance", false, "areaTransitions");
+    // Split on exiting each of the Metroid rooms in Tourian
+    settings.insert_with_parent("metroids", false, "areaTransitions");
+    // Split on moving from the Dust Torizo Room to the Big Boy Room
+    settings.insert_with_parent("babyMetroidRoom", false, "areaTransitions");
+    // Split on moving from Tourian Escape Room 4 to The Climb
+    settings.insert_with_parent("escapeClimb", false, "areaTransitions");
+
+    // Split on defeating minibosses
+    settings.insert("miniBosses", false);
+    // Split on starting the Ceres Escape
+    settings.insert_with_parent("ceresRidley", false, "miniBosses");
+    // Split on Bomb Torizo's drops appearing
+    settings.insert_with_parent("bombTorizo", false, "miniBosses");
+    // Split on the last hit to Spore Spawn
+    settings.insert_with_parent("sporeSpawn", false, "miniBosses");
+    // Split on Crocomire's drops appearing
+    settings.insert_with_parent("crocomire", false, "miniBosses");
+    // Split on Botwoon's vertical column being fully destroyed
+    settings.insert_with_parent("botwoon", false, "miniBosses");
+    // Split on Golden Torizo's drops appearing
+    settings.insert_with_parent("goldenTorizo", false, "miniBosses");
+
+    // Split on defeating major bosses
+    settings.insert("bosses", true);
+    // Split shortly after Kraid's drops appear
+    settings.insert_with_parent("kraid", false, "bosses");
+    // Split on Phantoon's drops appearing
+    settings.insert_with_parent("phantoon", false, "bosses");
+    // Split on Draygon's drops appearing
+    settings.insert_with_parent("draygon", false, "bosses");
+    // Split on Ridley's drops appearing
+    settings.insert_with_parent("ridley", true, "bosses");
+    // Split on Mother Brain's head hitting the ground at the end of the first phase
+    settings.insert_with_parent("mb1", false, "bosses");
+    // Split on the Baby Metroid detaching from Mother Brain's head
+    settings.insert_with_parent("mb2", true, "bosses");
+    // Split on the start of the Zebes Escape
+    settings.insert_with_parent("mb3", false, "bosses");
+
+    // Split on facing forward at the end of Zebes Escape
+    settings.insert("rtaFinish", true);
+    // Split on In-Game Time finalizing, when the end cutscene starts
+    settings.insert("igtFinish", false);
+    // Split on the end of a Spore Spawn RTA run, when the text box clears after collecting the Super Missiles
+    settings.insert("sporeSpawnRTAFinish", false);
+    // Split on the end of a 100 Missile RTA run, when the text box clears after collecting the hundredth missile
+    settings.insert("hundredMissileRTAFinish", false);
+    settings.modified_after_creation = false;
+    settings
 }
 
-impl Settings {
-    pub fn new() -> Self {
-        let mut settings = Settings {
-            data: HashMap::new(),
-            modified_after_creation: false,
-        };
-        // Split on Missiles, Super Missiles, and Power Bombs
-        settings.insert("ammoPickups", true);
-        // Split on the first Missile pickup
-        settings.insert_with_parent("firstMissile", false, "ammoPickups");
-        // Split on each Missile upgrade
-        settings.insert_with_parent("allMissiles", false, "ammoPickups");
-        // Split on specific Missile Pack locations
-        settings.insert_with_parent("specificMissiles", false, "ammoPickups");
-        // Split on Crateria Missile Pack locations
-        settings.insert_with_parent("crateriaMissiles", false, "specificMissiles");
-        // Split on picking up the Missile Pack located at the bottom left of the West Ocean
-        settings.insert_with_parent("oceanBottomMissiles", false, "crateriaMissiles");
-        // Split on picking up the Missile Pack located in the ceiling tile in West Ocean
-        settings.insert_with_parent("oceanTopMissiles", false, "crateriaMissiles");
-        // Split on picking up the Missile Pack located in the Morphball maze section of West Ocean
-        settings.insert_with_parent("oceanMiddleMissiles", false, "crateriaMissiles");
-        // Split on picking up the Missile Pack in The Moat, also known as The Lake
-        settings.insert_with_parent("moatMissiles", false, "crateriaMissiles");
-        // Split on picking up the Missile Pack in the Pit Room
-        settings.insert_with_parent("oldTourianMissiles", false, "crateriaMissiles");
-        // Split on picking up the right side Missile Pack at the end of Gauntlet(Green Pirates Shaft)
-        settings.insert_with_parent("gauntletRightMissiles", false, "crateriaMissiles");
-        // Split on picking up the left side Missile Pack at the end of Gauntlet(Green Pirates Shaft)
-        settings.insert_with_parent("gauntletLeftMissiles", false, "crateriaMissiles");
-        // Split on picking up the Missile Pack located in The Final Missile
-        settings.insert_with_parent("dentalPlan", false, "crateriaMissiles");
-        // Split on Brinstar Missile Pack locations
-        settings.insert_with_parent("brinstarMissiles", false, "specificMissiles");
-        // Split on picking up the Missile Pack located below the crumble bridge in the Early Supers Room
-        settings.insert_with_parent("earlySuperBridgeMissiles", false, "brinstarMissiles");
-        // Split on picking up the first Missile Pack behind the Brinstar Reserve Tank
-        settings.insert_with_parent("greenBrinstarReserveMissiles", false, "brinstarMissiles");
-        // Split on picking up the second Missile Pack behind the Brinstar Reserve Tank Room
-        settings.insert_with_parent(
-            "greenBrinstarExtraReserveMissiles",
-            false,
-            "brinstarMissiles",
-        );
-        // Split on picking up the Missile Pack located left of center in Big Pink
-        settings.insert_with_parent("bigPinkTopMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack located at the bottom left of Big Pink
-        settings.insert_with_parent("chargeMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack in Green Hill Zone
-        settings.insert_with_parent("greenHillsMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack in the Blue Brinstar Energy Tank Room
-        settings.insert_with_parent("blueBrinstarETankMissiles", false, "brinstarMissiles");
-        // Split on picking up the first Missile Pack of the game(First Missile Room)
-        settings.insert_with_parent("alphaMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack located on the pedestal in Billy Mays' Room
-        settings.insert_with_parent("billyMaysMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack located in the floor of Billy Mays' Room
-        settings.insert_with_parent("butWaitTheresMoreMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack in the Alpha Power Bombs Room
-        settings.insert_with_parent("redBrinstarMissiles", false, "brinstarMissiles");
-        // Split on picking up the Missile Pack in the Warehouse Kihunter Room
-        settings.insert_with_parent("warehouseMissiles", false, "brinstarMissiles");
-        // Split on Norfair Missile Pack locations
-        settings.insert_with_parent("norfairMissiles", false, "specificMissiles");
-        // Split on picking up the Missile Pack in Cathedral
-        settings.insert_with_parent("cathedralMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in Crumble Shaft
-        settings.insert_with_parent("crumbleShaftMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in Crocomire Escape
-        settings.insert_with_parent("crocomireEscapeMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Hi Jump Energy Tank Room
-        settings.insert_with_parent("hiJumpMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Post Crocomire Missile Room, also known as Cosine Room
-        settings.insert_with_parent("postCrocomireMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Post Crocomire Jump Room
-        settings.insert_with_parent("grappleMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Norfair Reserve Tank Room
-        settings.insert_with_parent("norfairReserveMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Green Bubbles Missile Room
-        settings.insert_with_parent("greenBubblesMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in Bubble Mountain
-        settings.insert_with_parent("bubbleMountainMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in Speed Booster Hall
-        settings.insert_with_parent("speedBoostMissiles", false, "norfairMissiles");
-        // Split on picking up the Wave Missile Pack in Double Chamber
-        settings.insert_with_parent("waveMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Golden Torizo's Room
-        settings.insert_with_parent("goldTorizoMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Mickey Mouse Room
-        settings.insert_with_parent("mickeyMouseMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the Lower Norfair Springball Maze Room
-        settings.insert_with_parent("lowerNorfairSpringMazeMissiles", false, "norfairMissiles");
-        // Split on picking up the Missile Pack in the The Musketeers' Room
-        settings.insert_with_parent("threeMusketeersMissiles", false, "norfairMissiles");
-        // Split on Wrecked Ship Missile Pack locations
-        settings.insert_with_parent("wreckedShipMissiles", false, "specificMissiles");
-        // Split on picking up the Missile Pack in Wrecked Ship Main Shaft
-        settings.insert_with_parent("wreckedShipMainShaftMissiles", false, "wreckedShipMissiles");
-        // Split on picking up the Missile Pack in Bowling Alley
-        settings.insert_with_parent("bowlingMissiles", false, "wreckedShipMissiles");
-        // Split on picking up the Missile Pack in the Wrecked Ship East Missile Room
-        settings.insert_with_parent("atticMissiles", false, "wreckedShipMissiles");
-        // Split on Maridia Missile Pack locations
-        settings.insert_with_parent("maridiaMissiles", false, "specificMissiles");
-        // Split on picking up the Missile Pack in Main Street
-        settings.insert_with_parent("mainStreetMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in the Mama Turtle Room
-        settings.insert_with_parent("mamaTurtleMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in Watering Hole
-        settings.insert_with_parent("wateringHoleMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in the Pseudo Plasma Spark Room
-        settings.insert_with_parent("beachMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in West Sand Hole
-        settings.insert_with_parent("leftSandPitMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in East Sand Hole
-        settings.insert_with_parent("rightSandPitMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in Aqueduct
-        settings.insert_with_parent("aqueductMissiles", false, "maridiaMissiles");
-        // Split on picking up the Missile Pack in The Precious Room
-        settings.insert_with_parent("preDraygonMissiles", false, "maridiaMissiles");
-        // Split on the first Super Missile pickup
-        settings.insert_with_parent("firstSuper", false, "ammoPickups");
-        // Split on each Super Missile upgrade
-        settings.insert_with_parent("allSupers", false, "ammoPickups");
-        // Split on specific Super Missile Pack locations
-        settings.insert_with_parent("specificSupers", false, "ammoPickups");
-        // Split on picking up the Super Missile Pack in the Crateria Super Room
-        settings.insert_with_parent("climbSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Spore Spawn Super Room (NOTE: SSTRA splits when the dialogue box disappears, not on touch. Use Spore Spawn RTA Finish for SSTRA runs.)
-        settings.insert_with_parent("sporeSpawnSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Early Supers Room
-        settings.insert_with_parent("earlySupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Etecoon Super Room
-        settings.insert_with_parent("etecoonSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Golden Torizo's Room
-        settings.insert_with_parent("goldTorizoSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Wrecked Ship West Super Room
-        settings.insert_with_parent("wreckedShipLeftSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in the Wrecked Ship East Super Room
-        settings.insert_with_parent("wreckedShipRightSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in Main Street
-        settings.insert_with_parent("crabSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in Watering Hole
-        settings.insert_with_parent("wateringHoleSupers", false, "specificSupers");
-        // Split on picking up the Super Missile Pack in Aqueduct
-        settings.insert_with_parent("aqueductSupers", false, "specificSupers");
-        // Split on the first Power Bomb pickup
-        settings.insert_with_parent("firstPowerBomb", true, "ammoPickups");
-        // Split on each Power Bomb upgrade
-        settings.insert_with_parent("allPowerBombs", false, "ammoPickups");
-        // Split on specific Power Bomb Pack locations
-        settings.insert_with_parent("specificBombs", false, "ammoPickups");
-        // Split on picking up the Power Bomb Pack in the Crateria Power Bomb Room
-        settings.insert_with_parent("landingSiteBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Etecoon Room section of Green Brinstar Main Shaft
-        settings.insert_with_parent("etecoonBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Pink Brinstar Power Bomb Room
-        settings.insert_with_parent("pinkBrinstarBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Morph Ball Room
-        settings.insert_with_parent("blueBrinstarBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Alpha Power Bomb Room
-        settings.insert_with_parent("alphaBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Beta Power Bomb Room
-        settings.insert_with_parent("betaBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Post Crocomire Power Bomb Room
-        settings.insert_with_parent("crocomireBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in the Lower Norfair Escape Power Bomb Room
-        settings.insert_with_parent("lowerNorfairEscapeBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in Wasteland
-        settings.insert_with_parent("shameBombs", false, "specificBombs");
-        // Split on picking up the Power Bomb Pack in East Sand Hall
-        settings.insert_with_parent("rightSandPitBombs", false, "specificBombs");
-
-        // Split on Varia and Gravity pickups
-        settings.insert("suitUpgrades", true);
-        // Split on picking up the Varia Suit
-        settings.insert_with_parent("variaSuit", true, "suitUpgrades");
-        // Split on picking up the Gravity Suit
-        settings.insert_with_parent("gravSuit", true, "suitUpgrades");
-
-        // Split on beam upgrades
-        settings.insert("beamUpgrades", true);
-        // Split on picking up the Charge Beam
-        settings.insert_with_parent("chargeBeam", false, "beamUpgrades");
-        // Split on picking up the Spazer
-        settings.insert_with_parent("spazer", false, "beamUpgrades");
-        // Split on picking up the Wave Beam
-        settings.insert_with_parent("wave", true, "beamUpgrades");
-        // Split on picking up the Ice Beam
-        settings.insert_with_parent("ice", false, "beamUpgrades");
-        // Split on picking up the Plasma Beam
-        settings.insert_with_parent("plasma", false, "beamUpgrades");
-
-        // Split on boot upgrades
-        settings.insert("bootUpgrades", false);
-        // Split on picking up the Hi-Jump Boots
-        settings.insert_with_parent("hiJump", false, "bootUpgrades");
-        // Split on picking up Space Jump
-        settings.insert_with_parent("spaceJump", false, "bootUpgrades");
-        // Split on picking up the Speed Booster
-        settings.insert_with_parent("speedBooster", false, "bootUpgrades");
-
-        // Split on Energy Tanks and Reserve Tanks
-        settings.insert("energyUpgrades", false);
-        // Split on picking up the first Energy Tank
-        settings.insert_with_parent("firstETank", false, "energyUpgrades");
-        // Split on picking up each Energy Tank
-        settings.insert_with_parent("allETanks", false, "energyUpgrades");
-        // Split on specific Energy Tank locations
-        settings.insert_with_parent("specificETanks", false, "energyUpgrades");
-        // Split on picking up the Energy Tank in the Gauntlet Energy Tank Room
-        settings.insert_with_parent("gauntletETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Terminator Room
-        settings.insert_with_parent("terminatorETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Blue Brinstar Energy Tank Room
-        settings.insert_with_parent("ceilingETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Etecoon Energy Tank Room
-        settings.insert_with_parent("etecoonsETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in Waterway
-        settings.insert_with_parent("waterwayETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Hopper Energy Tank Room
-        settings.insert_with_parent("waveGateETank", false, "specificETanks");
-        // Split on picking up the Kraid Energy Tank in the Warehouse Energy Tank Room
-        settings.insert_with_parent("kraidETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in Crocomire's Room
-        settings.insert_with_parent("crocomireETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Hi Jump Energy Tank Room
-        settings.insert_with_parent("hiJumpETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Ridley Tank Room
-        settings.insert_with_parent("ridleyETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Lower Norfair Fireflea Room
-        settings.insert_with_parent("firefleaETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Wrecked Ship Energy Tank Room
-        settings.insert_with_parent("wreckedShipETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Mama Turtle Room
-        settings.insert_with_parent("tatoriETank", false, "specificETanks");
-        // Split on picking up the Energy Tank in the Botwoon Energy Tank Room
-        settings.insert_with_parent("botwoonETank", false, "specificETanks");
-        // Split on picking up each Reserve Tank
-        settings.insert_with_parent("reserveTanks", false, "energyUpgrades");
-        // Split on specific Reserve Tank locations
-        settings.insert_with_parent("specificRTanks", false, "energyUpgrades");
-        // Split on picking up the Reserve Tank in the Brinstar Reserve Tank Room
-        settings.insert_with_parent("brinstarReserve", false, "specificRTanks");
-        // Split on picking up the Reserve Tank in the Norfair Reserve Tank Room
-        settings.insert_with_parent("norfairReserve", false, "specificRTanks");
-        // Split on picking up the Reserve Tank in Bowling Alley
-        settings.insert_with_parent("wreckedShipReserve", false, "specificRTanks");
-        // Split on picking up the Reserve Tank in West Sand Hole
-        settings.insert_with_parent("maridiaReserve", false, "specificRTanks");
-
-        // Split on the miscellaneous upgrades
-        settings.insert("miscUpgrades", false);
-        // Split on picking up the Morphing Ball
-        settings.insert_with_parent("morphBall", false, "miscUpgrades");
-        // Split on picking up the Bomb
-        settings.insert_with_parent("bomb", false, "miscUpgrades");
-        // Split on picking up the Spring Ball
-        settings.insert_with_parent("springBall", false, "miscUpgrades");
-        // Split on picking up the Screw Attack
-        settings.insert_with_parent("screwAttack", false, "miscUpgrades");
-        // Split on picking up the Grapple Beam
-        settings.insert_with_parent("grapple", false, "miscUpgrades");
-        // Split on picking up the X-Ray Scope
-        settings.insert_with_parent("xray", false, "miscUpgrades");
-
-        // Split on transitions between areas
-        settings.insert("areaTransitions", true);
-        // Split on entering miniboss rooms (except Bomb Torizo)
-        settings.insert_with_parent("miniBossRooms", false, "areaTransitions");
-        // Split on entering major boss rooms
-        settings.insert_with_parent("bossRooms", false, "areaTransitions");
-        // Split on elevator transitions between areas (except Statue Room to Tourian)
-        settings.insert_with_parent("elevatorTransitions", false, "areaTransitions");
-        // Split on leaving Ceres Station
-        settings.insert_with_parent("ceresEscape", false, "areaTransitions");
-        // Split on entering the Wrecked Ship Entrance from the lower door of West Ocean
-        settings.insert_with_parent("wreckedShipEntrance", false, "areaTransitions");
-        // Split on entering Red Tower from Noob Bridge
-        settings.insert_with_parent("redTowerMiddleEntrance", false, "areaTransitions");
-        // Split on entering Red Tower from Skree Boost room
-        settings.insert_with_parent("redTowerBottomEntrance", false, "areaTransitions");
-        // Split on entering Kraid's Lair
-        settings.insert_with_parent("kraidsLair", false, "areaTransitions");
-        // Split on entering Rising Tide from Cathedral
-        settings.insert_with_parent("risingTideEntrance", false, "areaTransitions");
-        // Split on exiting Attic
-        settings.insert_with_parent("atticExit", false, "areaTransitions");
-        // Split on blowing up the tube to enter Maridia
-        settings.insert_with_parent("tubeBroken", false, "areaTransitions");
-        // Split on exiting West Cacattack Alley
-        settings.insert_with_parent("cacExit", false, "areaTransitions");
-        // Split on entering Toilet Bowl from either direction
-        settings.insert_with_parent("toilet", false, "areaTransitions");
-        // Split on entering Kronic Boost room
-        settings.insert_with_parent("kronicBoost", false, "areaTransitions");
-        // Split on the elevator down to Lower Norfair
-        settings.insert_with_parent("lowerNorfairEntrance", false, "areaTransitions");
-        // Split on entering Worst Room in the Game
-        settings.insert_with_parent("writg", false, "areaTransitions");
-        // Split on entering Red Kihunter Shaft from either Amphitheatre or Wastelands (NOTE: will split twice)
-        settings.insert_with_parent("redKiShaft", false, "areaTransitions");
-        // Split on entering Metal Pirates Room from Wasteland
-        settings.insert_with_parent("metalPirates", false, "areaTransitions");
-        // Split on entering Lower Norfair Springball Maze Room
-        settings.insert_with_parent("lowerNorfairSpringMaze", false, "areaTransitions");
-        // Split on moving from the Three Musketeers' Room to the Single Chamber
-        settings.insert_with_parent("lowerNorfairExit", false, "areaTransitions");
-        // Split on entering the Statues Room with all four major bosses defeated
-        settings.insert_with_parent("goldenFour", true, "areaTransitions");
-        // Split on the elevator down to Tourian
-        settings.insert_with_parent("tourianEntrance", false, "areaTransitions");
-        // Split on exiting each of the Metroid rooms in Tourian
-        settings.insert_with_parent("metroids", false, "areaTransitions");
-        // Split on moving from the Dust Torizo Room to the Big Boy Room
-        settings.insert_with_parent("babyMetroidRoom", false, "areaTransitions");
-        // Split on moving from Tourian Escape Room 4 to The Climb
-        settings.insert_with_parent("escapeClimb", false, "areaTransitions");
-
-        // Split on defeating minibosses
-        settings.insert("miniBosses", false);
-        // Split on starting the Ceres Escape
-        settings.insert_with_parent("ceresRidley", false, "miniBosses");
-        // Split on Bomb Torizo's drops appearing
-        settings.insert_with_parent("bombTorizo", false, "miniBosses");
-        // Split on the last hit to Spore Spawn
-        settings.insert_with_parent("sporeSpawn", false, "miniBosses");
-        // Split on Crocomire's drops appearing
-        settings.insert_with_parent("crocomire", false, "miniBosses");
-        // Split on Botwoon's vertical column being fully destroyed
-        settings.insert_with_parent("botwoon", false, "miniBosses");
-        // Split on Golden Torizo's drops appearing
-        settings.insert_with_parent("goldenTorizo", false, "miniBosses");
-
-        // Split on defeating major bosses
-        settings.insert("bosses", true);
-        // Split shortly after Kraid's drops appear
-        settings.insert_with_parent("kraid", false, "bosses");
-        // Split on Phantoon's drops appearing
-        settings.insert_with_parent("phantoon", false, "bosses");
-        // Split on Draygon's drops appearing
-        settings.insert_with_parent("draygon", false, "bosses");
-        // Split on Ridley's drops appearing
-        settings.insert_with_parent("ridley", true, "bosses");
-        // Split on Mother Brain's head hitting the ground at the end of the first phase
-        settings.insert_with_parent("mb1", false, "bosses");
-        // Split on the Baby Metroid detaching from Mother Brain's head
-        settings.insert_with_parent("mb2", true, "bosses");
-        // Split on the start of the Zebes Escape
-        settings.insert_with_parent("mb3", false, "bosses");
-
-        // Split on facing forward at the end of Zebes Escape
-        settings.insert("rtaFinish", true);
-        // Split on In-Game Time finalizing, when the end cutscene starts
-        settings.insert("igtFinish", false);
-        // Split on the end of a Spore Spawn RTA run, when the text box clears after collecting the Super Missiles
-        settings.insert("sporeSpawnRTAFinish", false);
-        // Split on the end of a 100 Missile RTA run, when the text box clears after collecting the hundredth missile
-        settings.insert("hundredMissileRTAFinish", false);
-        settings.modified_after_creation = false;
-        settings
-    }
-
-    fn insert(&mut self, name: &str, value: bool) {
-        self.modified_after_creation = true;
-        self.data.insert(name.into(), (value, None));
-    }
-
-    fn insert_with_parent(&mut self, name: &str, value: bool, parent: &str) {
-        self.modified_after_creation = true;
-        self.data
-            .insert(name.into(), (value, Some(parent.to_owned())));
-    }
-
-    #[allow(dead_code)]
-    fn contains(&self, var: &str) -> bool {
-        self.data.contains_key(var)
-    }
-
-    fn get(&self, var: &str) -> bool {
-        match self.data.get(var) {
-            None => false,
-            Some((b, None)) => *b,
-            Some((b, Some(ref p))) => *b && self.get(p),
-        }
-    }
-
-    fn set(&mut self, var: &str, value: bool) {
-        let val = match self.data.get_mut(var) {
-            None => (value, None),
-            Some((_, x)) => (value, x.clone()),
-        };
-        self.data.insert(std::sync::Arc::from(var), val);
-    }
-
-    /// The keys which have no parent defined
-    pub fn roots(&self) -> Vec<String> {
-        let mut rs = vec![];
-        for (key, (_, parent)) in self.data.iter() {
-            if parent.is_none() {
-                rs.push(key.to_string());
-            }
-        }
-        rs
-    }
-
-    /// The immediate childern (if any)
-    pub fn children(&self, key: &str) -> Vec<String> {
-        let mut rs = vec![];
-        for (k, (_, parent)) in self.data.iter() {
-            if let Some(parent) = parent {
-                if key == parent {
-                    rs.push(k.to_string())
-                }
-            }
-        }
-        rs
-    }
-
-    pub fn lookup(&mut self, var: &str) -> bool {
-        match self.data.get(var) {
-            None => panic!(),
-            Some((b, _)) => *b,
-        }
-    }
-
-    pub fn lookup_mut(&mut self, var: &str) -> &mut bool {
-        // TODO: this is a conservative overapproximation. We don't actually
-        // know if the caller wrote to the &mut bool we gave them.
-        self.modified_after_creation = true;
-        match self.data.get_mut(var) {
-            None => panic!(),
-            Some((b, _)) => b,
-        }
-    }
-
-    pub fn has_been_modified(&self) -> bool {
-        self.modified_after_creation
-    }
-
-    pub fn split_on_misc_upgrades(&mut self) {
-        self.set("miscUpgrades", true);
-        self.set("morphBall", true);
-        self.set("bomb", true);
-        self.set("springBall", true);
-        self.set("screwAttack", true);
-        self.set("grapple", true);
-        self.set("xray", true);
-    }
+pub fn split_on_misc_upgrades(settings: &mut Settings) {
+    settings.set("miscUpgrades", true);
+    settings.set("morphBall", true);
+    settings.set("bomb", true);
+    settings.set("springBall", true);
+    settings.set("screwAttack", true);
+    settings.set("grapple", true);
+    settings.set("xray", true);
+}
 
-    pub fn split_on_hundo(&mut self) {
-        self.set("ammoPickups", true);
-        self.set("allMissiles", true);
-        self.set("allSupers", true);
-        self.set("allPowerBombs", true);
-        self.set("beamUpgrades", true);
-        self.set("chargeBeam", true);
-        self.set("spazer", true);
-        self.set("wave", true);
-        self.set("ice", true);
-        self.set("plasma", true);
-        self.set("bootUpgrades", true);
-        self.set("hiJump", true);
-        self.set("spaceJump", true);
-        self.set("speedBooster", true);
-        self.set("energyUpgrades", true);
-        self.set("allETanks", true);
-        self.set("reserveTanks", true);
-        self.split_on_misc_upgrades();
-        self.set("areaTransitions", true); // should already be true
-        self.set("tubeBroken", true);
-        self.set("ceresEscape", true);
-        self.set("bosses", true); // should already be true
-        self.set("kraid", true);
-        self.set("phantoon", true);
-        self.set("draygon", true);
-        self.set("ridley", true);
-        self.set("mb1", true);
-        self.set("mb2", true);
-        self.set("mb3", true);
-        self.set("miniBosses", true);
-        self.set("ceresRidley", true);
-        self.set("bombTorizo", true);
-        self.set("crocomire", true);
-        self.set("botwoon", true);
-        self.set("goldenTorizo", true);
-        self.set("babyMetroidRoom", true);
-    }
-    pub fn split_on_anypercent(&mut self) {
-        self.set("ammoPickups", true);
-        self.set("specificMissiles", true);
-        self.set("specificSupers", true);
-        self.set("wreckedShipLeftSupers", true);
-        self.set("specificPowerBombs", true);
-        self.set("firstMissile", true);
-        self.set("firstSuper", true);
-        self.set("firstPowerBomb", true);
-        self.set("brinstarMissiles", true);
-        self.set("norfairMissiles", true);
-        self.set("chargeMissiles", true);
-        self.set("waveMissiles", true);
-        self.set("beamUpgrades", true);
-        self.set("chargeBeam", true);
-        self.set("wave", true);
-        self.set("ice", true);
-        self.set("plasma", true);
-        self.set("bootUpgrades", true);
-        self.set("hiJump", true);
-        self.set("speedBooster", true);
-        self.set("specificETanks", true);
-        self.set("energyUpgrades", true);
-        self.set("terminatorETank", true);
-        self.set("hiJumpETank", true);
-        self.set("botwoonETank", true);
-        self.set("miscUpgrades", true);
-        self.set("morphBall", true);
-        self.set("spaceJump", true);
-        self.set("bomb", true);
-        self.set("areaTransitions", true); // should already be true
-        self.set("tubeBroken", true);
-        self.set("ceresEscape", true);
-        self.set("bosses", true); // should already be true
-        self.set("kraid", true);
-        self.set("phantoon", true);
-        self.set("draygon", true);
-        self.set("ridley", true);
-        self.set("mb1", true);
-        self.set("mb2", true);
-        self.set("mb3", true);
-        self.set("miniBosses", true);
-        self.set("ceresRidley", true);
-        self.set("bombTorizo", true);
-        self.set("botwoon", true);
-        self.set("goldenTorizo", true);
-        self.set("babyMetroidRoom", true);
-    }
+pub fn split_on_hundo(settings: &mut Settings) {
+    settings.set("ammoPickups", true);
+    settings.set("allMissiles", true);
+    settings.set("allSupers", true);
+    settings.set("allPowerBombs", true);
+    settings.set("beamUpgrades", true);
+    settings.set("chargeBeam", true);
+    settings.set("spazer", true);
+    settings.set("wave", true);
+    settings.set("ice", true);
+    settings.set("plasma", true);
+    settings.set("bootUpgrades", true);
+    settings.set("hiJump", true);
+    settings.set("spaceJump", true);
+    settings.set("speedBooster", true);
+    settings.set("energyUpgrades", true);
+    settings.set("allETanks", true);
+    settings.set("reserveTanks", true);
+    split_on_misc_upgrades(settings);
+    settings.set("areaTransitions", true); // should already be true
+    settings.set("tubeBroken", true);
+    settings.set("ceresEscape", true);
+    settings.set("bosses", true); // should already be true
+    settings.set("kraid", true);
+    settings.set("phantoon", true);
+    settings.set("draygon", true);
+    settings.set("ridley", true);
+    settings.set("mb1", true);
+    settings.set("mb2", true);
+    settings.set("mb3", true);
+    settings.set("miniBosses", true);
+    settings.set("ceresRidley", true);
+    settings.set("bombTorizo", true);
+    settings.set("crocomire", true);
+    settings.set("botwoon", true);
+    settings.set("goldenTorizo", true);
+    settings.set("babyMetroidRoom", true);
 }
 
-impl Default for Settings {
-    fn default() -> Self {
-        Self::new()
-    }
+pub fn split_on_anypercent(settings: &mut Settings) {
+    settings.set("ammoPickups", true);
+    settings.set("specificMissiles", true);
+    settings.set("specificSupers", true);
+    settings.set("wreckedShipLeftSupers", true);
+    settings.set("specificPowerBombs", true);
+    settings.set("firstMissile", true);
+    settings.set("firstSuper", true);
+    settings.set("firstPowerBomb", true);
+    settings.set("brinstarMissiles", true);
+    settings.set("norfairMissiles", true);
+    settings.set("chargeMissiles", true);
+    settings.set("waveMissiles", true);
+    settings.set("beamUpgrades", true);
+    settings.set("chargeBeam", true);
+    settings.set("wave", true);
+    settings.set("ice", true);
+    settings.set("plasma", true);
+    settings.set("bootUpgrades", true);
+    settings.set("hiJump", true);
+    settings.set("speedBooster", true);
+    settings.set("specificETanks", true);
+    settings.set("energyUpgrades", true);
+    settings.set("terminatorETank", true);
+    settings.set("hiJumpETank", true);
+    settings.set("botwoonETank", true);
+    settings.set("miscUpgrades", true);
+    settings.set("morphBall", true);
+    settings.set("spaceJump", true);
+    settings.set("bomb", true);
+    settings.set("areaTransitions", true); // should already be true
+    settings.set("tubeBroken", true);
+    settings.set("ceresEscape", true);
+    settings.set("bosses", true); // should already be true
+    settings.set("kraid", true);
+    settings.set("phantoon", true);
+    settings.set("draygon", true);
+    settings.set("ridley", true);
+    settings.set("mb1", true);
+    settings.set("mb2", true);
+    settings.set("mb3", true);
+    settings.set("miniBosses", true);
+    settings.set("ceresRidley", true);
+    settings.set("bombTorizo", true);
+    settings.set("botwoon", true);
+    settings.set("goldenTorizo", true);
+    settings.set("babyMetroidRoom", true);
 }
 
 #[allow(non_snake_case)]
 #[allow(clippy::all)]
 // TODO: probably makes sense to move this to the SNESState impl
-fn split(settings: &Settings, snes: &mut SNESState) -> bool {
+fn split(settings: &Settings, snes: &mut SNESState) -> Vec<SplitReason> {
     // Ammo pickup section
     let firstMissile = settings.get("firstMissile")
         && snes["maxMissiles"].old == 0
-        && snes["maxMissiles"].current == 5;
-    let allMissiles = settings.get("allMissiles")
-        && (snes["maxMissiles"].old + 5) == (snes["maxMissiles"].current);
+        && snes.gained("maxMissiles", 5);
+    let allMissiles = settings.get("allMissiles") && snes.gained("maxMissiles", 5);
     let oceanBottomMissiles = settings.get("oceanBottomMissiles")
-        && snes["roomID"].current == roomIDEnum["westOcean"]
-        && (snes["crateriaItems"].old + 2) == (snes["crateriaItems"].current);
+        && snes.was_in_room("westOcean")
+        && snes.bit_set("crateriaItems", 2);
     let oceanTopMissiles = settings.get("oceanTopMissiles")
-        && snes["roomID"].current == roomIDEnum["westOcean"]
-        && (snes["crateriaItems"].old + 4) == (snes["crateriaItems"].current);
+        && snes.was_in_room("westOcean")
+        && snes.bit_set("crateriaItems", 4);
     let oceanMiddleMissiles = settings.get("oceanMiddleMissiles")
-        && snes["roomID"].current == roomIDEnum["westOcean"]
-        && (snes["crateriaItems"].old + 8) == (snes["crateriaItems"].current);
+        && snes.was_in_room("westOcean")
+        && snes.bit_set("crateriaItems", 8);
     let moatMissiles = settings.get("moatMissiles")
-        && snes["roomID"].current == roomIDEnum["crateriaMoat"]
-        && (snes["crateriaItems"].old + 16) == (snes["crateriaItems"].current);
+        && snes.was_in_room("crateriaMoat")
+        && snes.bit_set("crateriaItems", 16);
     let oldTourianMissiles = settings.get("oldTourianMissiles")
-        && snes["roomID"].current == roomIDEnum["pitRoom"]
-        && (snes["crateriaItems"].old + 64) == (snes["crateriaItems"].current);
+        && snes.was_in_room("pitRoom")
+        && snes.bit_set("crateriaItems", 64);
     let gauntletRightMissiles = settings.get("gauntletRightMissiles")
-        && snes["roomID"].current == roomIDEnum["greenPirateShaft"]
-        && (snes["brinteriaItems"].old + 2) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("greenPirateShaft")
+        && snes.bit_set("brinteriaItems", 2);
     let gauntletLeftMissiles = settings.get("gauntletLeftMissiles")
-        && snes["roomID"].current == roomIDEnum["greenPirateShaft"]
-        && (snes["brinteriaItems"].old + 4) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("greenPirateShaft")
+        && snes.bit_set("brinteriaItems", 4);
     let dentalPlan = settings.get("dentalPlan")
-        && snes["roomID"].current == roomIDEnum["theFinalMissile"]
-        && (snes["brinteriaItems"].old + 16) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("theFinalMissile")
+        && snes.bit_set("brinteriaItems", 16);
     let earlySuperBridgeMissiles = settings.get("earlySuperBridgeMissiles")
-        && snes["roomID"].current == roomIDEnum["earlySupers"]
-        && (snes["brinteriaItems"].old + 128) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("earlySupers")
+        && snes.bit_set("brinteriaItems", 128);
     let greenBrinstarReserveMissiles = settings.get("greenBrinstarReserveMissiles")
-        && snes["roomID"].current == roomIDEnum["brinstarReserveRoom"]
-        && (snes["brinstarItems2"].old + 8) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("brinstarReserveRoom")
+        && snes.bit_set("brinstarItems2", 8);
     let greenBrinstarExtraReserveMissiles = settings.get("greenBrinstarExtraReserveMissiles")
-        && snes["roomID"].current == roomIDEnum["brinstarReserveRoom"]
-        && (snes["brinstarItems2"].old + 4) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("brinstarReserveRoom")
+        && snes.bit_set("brinstarItems2", 4);
     let bigPinkTopMissiles = settings.get("bigPinkTopMissiles")
-        && snes["roomID"].current == roomIDEnum["bigPink"]
-        && (snes["brinstarItems2"].old + 32) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("bigPink")
+        && snes.bit_set("brinstarItems2", 32);
     let chargeMissiles = settings.get("chargeMissiles")
-        && snes["roomID"].current == roomIDEnum["bigPink"]
-        && (snes["brinstarItems2"].old + 64) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("bigPink")
+        && snes.bit_set("brinstarItems2", 64);
     let greenHillsMissiles = settings.get("greenHillsMissiles")
-        && snes["roomID"].current == roomIDEnum["greenHills"]
-        && (snes["brinstarItems3"].old + 2) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("greenHills")
+        && snes.bit_set("brinstarItems3", 2);
     let blueBrinstarETankMissiles = settings.get("blueBrinstarETankMissiles")
-        && snes["roomID"].current == roomIDEnum["blueBrinstarETankRoom"]
-        && (snes["brinstarItems3"].old + 16) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("blueBrinstarETankRoom")
+        && snes.bit_set("brinstarItems3", 16);
     let alphaMissiles = settings.get("alphaMissiles")
-        && snes["roomID"].current == roomIDEnum["alphaMissileRoom"]
-        && (snes["brinstarItems4"].old + 4) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("alphaMissileRoom")
+        && snes.bit_set("brinstarItems4", 4);
     let billyMaysMissiles = settings.get("billyMaysMissiles")
-        && snes["roomID"].current == roomIDEnum["billyMays"]
-        && (snes["brinstarItems4"].old + 16) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("billyMays")
+        && snes.bit_set("brinstarItems4", 16);
     let butWaitTheresMoreMissiles = settings.get("butWaitTheresMoreMissiles")
-        && snes["roomID"].current == roomIDEnum["billyMays"]
-        && (snes["brinstarItems4"].old + 32) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("billyMays")
+        && snes.bit_set("brinstarItems4", 32);
     let redBrinstarMissiles = settings.get("redBrinstarMissiles")
-        && snes["roomID"].current == roomIDEnum["alphaPowerBombsRoom"]
-        && (snes["brinstarItems5"].old + 2) == (snes["brinstarItems5"].current);
+        && snes.was_in_room("alphaPowerBombsRoom")
+        && snes.bit_set("brinstarItems5", 2);
     let warehouseMissiles = settings.get("warehouseMissiles")
-        && snes["roomID"].current == roomIDEnum["warehouseKiHunters"]
-        && (snes["brinstarItems5"].old + 16) == (snes["brinstarItems5"].current);
+        && snes.was_in_room("warehouseKiHunters")
+        && snes.bit_set("brinstarItems5", 16);
     let cathedralMissiles = settings.get("cathedralMissiles")
-        && snes["roomID"].current == roomIDEnum["cathedral"]
-        && (snes["norfairItems1"].old + 2) == (snes["norfairItems1"].current);
+        && snes.was_in_room("cathedral")
+        && snes.bit_set("norfairItems1", 2);
     let crumbleShaftMissiles = settings.get("crumbleShaftMissiles")
-        && snes["roomID"].current == roomIDEnum["crumbleShaft"]
-        && (snes["norfairItems1"].old + 8) == (snes["norfairItems1"].current);
+        && snes.was_in_room("crumbleShaft")
+        && snes.bit_set("norfairItems1", 8);
     let crocomireEscapeMissiles = settings.get("crocomireEscapeMissiles")
-        && snes["roomID"].current == roomIDEnum["crocomireEscape"]
-        && (snes["norfairItems1"].old + 64) == (snes["norfairItems1"].current);
+        && snes.was_in_room("crocomireEscape")
+        && snes.bit_set("norfairItems1", 64);
     let hiJumpMissiles = settings.get("hiJumpMissiles")
-        && snes["roomID"].current == roomIDEnum["hiJumpShaft"]
-        && (snes["norfairItems1"].old + 128) == (snes["norfairItems1"].current);
+        && snes.was_in_room("hiJumpShaft")
+        && snes.bit_set("norfairItems1", 128);
     let postCrocomireMissiles = settings.get("postCrocomireMissiles")
-        && snes["roomID"].current == roomIDEnum["cosineRoom"]
-        && (snes["norfairItems2"].old + 4) == (snes["norfairItems2"].current);
+        && snes.was_in_room("cosineRoom")
+        && snes.bit_set("norfairItems2", 4);
     let grappleMissiles = settings.get("grappleMissiles")
-        && snes["roomID"].current == roomIDEnum["preGrapple"]
-        && (snes["norfairItems2"].old + 8) == (snes["norfairItems2"].current);
+        && snes.was_in_room("preGrapple")
+        && snes.bit_set("norfairItems2", 8);
     let norfairReserveMissiles = settings.get("norfairReserveMissiles")
-        && snes["roomID"].current == roomIDEnum["norfairReserveRoom"]
-        && (snes["norfairItems2"].old + 64) == (snes["norfairItems2"].current);
+        && snes.was_in_room("norfairReserveRoom")
+        && snes.bit_set("norfairItems2", 64);
     let greenBubblesMissiles = settings.get("greenBubblesMissiles")
-        && snes["roomID"].current == roomIDEnum["greenBubblesRoom"]
-        && (snes["norfairItems2"].old + 128) == (snes["norfairItems2"].current);
+        && snes.was_in_room("greenBubblesRoom")
+        && snes.bit_set("norfairItems2", 128);
     let bubbleMountainMissiles = settings.get("bubbleMountainMissiles")
-        && snes["roomID"].current == roomIDEnum["bubbleMountain"]
-        && (snes["norfairItems3"].old + 1) == (snes["norfairItems3"].current);
+        && snes.was_in_room("bubbleMountain")
+        && snes.bit_set("norfairItems3", 1);
     let speedBoostMissiles = settings.get("speedBoostMissiles")
-        && snes["roomID"].current == roomIDEnum["speedBoostHall"]
-        && (snes["norfairItems3"].old + 2) == (snes["norfairItems3"].current);
+        && snes.was_in_room("speedBoostHall")
+        && snes.bit_set("norfairItems3", 2);
     let waveMissiles = settings.get("waveMissiles")
-        && snes["roomID"].current == roomIDEnum["doubleChamber"]
-        && (snes["norfairItems3"].old + 8) == (snes["norfairItems3"].current);
+        && snes.was_in_room("doubleChamber")
+        && snes.bit_set("norfairItems3", 8);
     let goldTorizoMissiles = settings.get("goldTorizoMissiles")
-        && snes["roomID"].current == roomIDEnum["goldenTorizo"]
-        && (snes["norfairItems3"].old + 64) == (snes["norfairItems3"].current);
+        && snes.was_in_room("goldenTorizo")
+        && snes.bit_set("norfairItems3", 64);
     let mickeyMouseMissiles = settings.get("mickeyMouseMissiles")
-        && snes["roomID"].current == roomIDEnum["mickeyMouse"]
-        && (snes["norfairItems4"].old + 2) == (snes["norfairItems4"].current);
+        && snes.was_in_room("mickeyMouse")
+        && snes.bit_set("norfairItems4", 2);
     let lowerNorfairSpringMazeMissiles = settings.get("lowerNorfairSpringMazeMissiles")
-        && snes["roomID"].current == roomIDEnum["lowerNorfairSpringMaze"]
-        && (snes["norfairItems4"].old + 4) == (snes["norfairItems4"].current);
+        && snes.was_in_room("lowerNorfairSpringMaze")
+        && snes.bit_set("norfairItems4", 4);
     let threeMusketeersMissiles = settings.get("threeMusketeersMissiles")
-        && snes["roomID"].current == roomIDEnum["threeMusketeers"]
-        && (snes["norfairItems4"].old + 32) == (snes["norfairItems4"].current);
+        && snes.was_in_room("threeMusketeers")
+        && snes.bit_set("norfairItems4", 32);
     let wreckedShipMainShaftMissiles = settings.get("wreckedShipMainShaftMissiles")
-        && snes["roomID"].current == roomIDEnum["wreckedShipMainShaft"]
-        && (snes["wreckedShipItems"].old + 1) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("wreckedShipMainShaft")
+        && snes.bit_set("wreckedShipItems", 1);
     let bowlingMissiles = settings.get("bowlingMissiles")
-        && snes["roomID"].current == roomIDEnum["bowling"]
-        && (snes["wreckedShipItems"].old + 4) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("bowling")
+        && snes.bit_set("wreckedShipItems", 4);
     let atticMissiles = settings.get("atticMissiles")
-        && snes["roomID"].current == roomIDEnum["atticWorkerRobotRoom"]
-        && (snes["wreckedShipItems"].old + 8) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("atticWorkerRobotRoom")
+        && snes.bit_set("wreckedShipItems", 8);
     let mainStreetMissiles = settings.get("mainStreetMissiles")
-        && snes["roomID"].current == roomIDEnum["mainStreet"]
-        && (snes["maridiaItems1"].old + 1) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("mainStreet")
+        && snes.bit_set("maridiaItems1", 1);
     let mamaTurtleMissiles = settings.get("mamaTurtleMissiles")
-        && snes["roomID"].current == roomIDEnum["mamaTurtle"]
-        && (snes["maridiaItems1"].old + 8) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("mamaTurtle")
+        && snes.bit_set("maridiaItems1", 8);
     let wateringHoleMissiles = settings.get("wateringHoleMissiles")
-        && snes["roomID"].current == roomIDEnum["wateringHole"]
-        && (snes["maridiaItems1"].old + 32) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("wateringHole")
+        && snes.bit_set("maridiaItems1", 32);
     let beachMissiles = settings.get("beachMissiles")
-        && snes["roomID"].current == roomIDEnum["beach"]
-        && (snes["maridiaItems1"].old + 64) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("beach")
+        && snes.bit_set("maridiaItems1", 64);
     let leftSandPitMissiles = settings.get("leftSandPitMissiles")
-        && snes["roomID"].current == roomIDEnum["leftSandPit"]
-        && (snes["maridiaItems2"].old + 1) == (snes["maridiaItems2"].current);
+        && snes.was_in_room("leftSandPit")
+        && snes.bit_set("maridiaItems2", 1);
     let rightSandPitMissiles = settings.get("rightSandPitMissiles")
-        && snes["roomID"].current == roomIDEnum["rightSandPit"]
-        && (snes["maridiaItems2"].old + 4) == (snes["maridiaItems2"].current);
+        && snes.was_in_room("rightSandPit")
+        && snes.bit_set("maridiaItems2", 4);
     let aqueductMissiles = settings.get("aqueductMissiles")
-        && snes["roomID"].current == roomIDEnum["aqueduct"]
-        && (snes["maridiaItems2"].old + 16) == (snes["maridiaItems2"].current);
+        && snes.was_in_room("aqueduct")
+        && snes.bit_set("maridiaItems2", 16);
     let preDraygonMissiles = settings.get("preDraygonMissiles")
-        && snes["roomID"].current == roomIDEnum["precious"]
-        && (snes["maridiaItems2"].old + 128) == (snes["maridiaItems2"].current);
+        && snes.was_in_room("precious")
+        && snes.bit_set("maridiaItems2", 128);
     let firstSuper =
-        settings.get("firstSuper") && snes["maxSupers"].old == 0 && snes["maxSupers"].current == 5;
-    let allSupers =
-        settings.get("allSupers") && (snes["maxSupers"].old + 5) == (snes["maxSupers"].current);
+        settings.get("firstSuper") && snes["maxSupers"].old == 0 && snes.gained("maxSupers", 5);
+    let allSupers = settings.get("allSupers") && snes.gained("maxSupers", 5);
     let climbSupers = settings.get("climbSupers")
-        && snes["roomID"].current == roomIDEnum["crateriaSupersRoom"]
-        && (snes["brinteriaItems"].old + 8) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("crateriaSupersRoom")
+        && snes.bit_set("brinteriaItems", 8);
     let sporeSpawnSupers = settings.get("sporeSpawnSupers")
-        && snes["roomID"].current == roomIDEnum["sporeSpawnSuper"]
-        && (snes["brinteriaItems"].old + 64) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("sporeSpawnSuper")
+        && snes.bit_set("brinteriaItems", 64);
     let earlySupers = settings.get("earlySupers")
-        && snes["roomID"].current == roomIDEnum["earlySupers"]
-        && (snes["brinstarItems2"].old + 1) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("earlySupers")
+        && snes.bit_set("brinstarItems2", 1);
     let etecoonSupers = (settings.get("etecoonSupers") || settings.get("etacoonSupers"))
-        && snes["roomID"].current == roomIDEnum["etecoonSuperRoom"]
-        && (snes["brinstarItems3"].old + 128) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("etecoonSuperRoom")
+        && snes.bit_set("brinstarItems3", 128);
     let goldTorizoSupers = settings.get("goldTorizoSupers")
-        && snes["roomID"].current == roomIDEnum["goldenTorizo"]
-        && (snes["norfairItems3"].old + 128) == (snes["norfairItems3"].current);
+        && snes.was_in_room("goldenTorizo")
+        && snes.bit_set("norfairItems3", 128);
     let wreckedShipLeftSupers = settings.get("wreckedShipLeftSupers")
-        && snes["roomID"].current == roomIDEnum["wreckedShipLeftSuperRoom"]
-        && (snes["wreckedShipItems"].old + 32) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("wreckedShipLeftSuperRoom")
+        && snes.bit_set("wreckedShipItems", 32);
     let wreckedShipRightSupers = settings.get("wreckedShipRightSupers")
-        && snes["roomID"].current == roomIDEnum["wreckedShipRightSuperRoom"]
-        && (snes["wreckedShipItems"].old + 64) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("wreckedShipRightSuperRoom")
+        && snes.bit_set("wreckedShipItems", 64);
     let crabSupers = settings.get("crabSupers")
-        && snes["roomID"].current == roomIDEnum["mainStreet"]
-        && (snes["maridiaItems1"].old + 2) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("mainStreet")
+        && snes.bit_set("maridiaItems1", 2);
     let wateringHoleSupers = settings.get("wateringHoleSupers")
-        && snes["roomID"].current == roomIDEnum["wateringHole"]
-        && (snes["maridiaItems1"].old + 16) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("wateringHole")
+        && snes.bit_set("maridiaItems1", 16);
     let aqueductSupers = settings.get("aqueductSupers")
-        && snes["roomID"].current == roomIDEnum["aqueduct"]
-        && (snes["maridiaItems2"].old + 32) == (snes["maridiaItems2"].current);
+        && snes.was_in_room("aqueduct")
+        && snes.bit_set("maridiaItems2", 32);
     let firstPowerBomb = settings.get("firstPowerBomb")
         && snes["maxPowerBombs"].old == 0
-        && snes["maxPowerBombs"].current == 5;
-    let allPowerBombs = settings.get("allPowerBombs")
-        && (snes["maxPowerBombs"].old + 5) == (snes["maxPowerBombs"].current);
+        && snes.gained("maxPowerBombs", 5);
+    let allPowerBombs = settings.get("allPowerBombs") && snes.gained("maxPowerBombs", 5);
     let landingSiteBombs = settings.get("landingSiteBombs")
-        && snes["roomID"].current == roomIDEnum["crateriaPowerBombRoom"]
-        && (snes["crateriaItems"].old + 1) == (snes["crateriaItems"].current);
+        && snes.was_in_room("crateriaPowerBombRoom")
+        && snes.bit_set("crateriaItems", 1);
     let etecoonBombs = (settings.get("etecoonBombs") || settings.get("etacoonBombs"))
-        && snes["roomID"].current == roomIDEnum["greenBrinstarMainShaft"]
-        && (snes["brinteriaItems"].old + 32) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("greenBrinstarMainShaft")
+        && snes.bit_set("brinteriaItems", 32);
     let pinkBrinstarBombs = settings.get("pinkBrinstarBombs")
-        && snes["roomID"].current == roomIDEnum["pinkBrinstarPowerBombRoom"]
-        && (snes["brinstarItems3"].old + 1) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("pinkBrinstarPowerBombRoom")
+        && snes.bit_set("brinstarItems3", 1);
     let blueBrinstarBombs = settings.get("blueBrinstarBombs")
-        && snes["roomID"].current == roomIDEnum["morphBall"]
-        && (snes["brinstarItems3"].old + 8) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("morphBall")
+        && snes.bit_set("brinstarItems3", 8);
     let alphaBombs = settings.get("alphaBombs")
-        && snes["roomID"].current == roomIDEnum["alphaPowerBombsRoom"]
-        && (snes["brinstarItems5"].old + 1) == (snes["brinstarItems5"].current);
+        && snes.was_in_room("alphaPowerBombsRoom")
+        && snes.bit_set("brinstarItems5", 1);
     let betaBombs = settings.get("betaBombs")
-        && snes["roomID"].current == roomIDEnum["betaPowerBombRoom"]
-        && (snes["brinstarItems4"].old + 128) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("betaPowerBombRoom")
+        && snes.bit_set("brinstarItems4", 128);
     let crocomireBombs = settings.get("crocomireBombs")
-        && snes["roomID"].current == roomIDEnum["postCrocomirePowerBombRoom"]
-        && (snes["norfairItems2"].old + 2) == (snes["norfairItems2"].current);
+        && snes.was_in_room("postCrocomirePowerBombRoom")
+        && snes.bit_set("norfairItems2", 2);
     let lowerNorfairEscapeBombs = settings.get("lowerNorfairEscapeBombs")
-        && snes["roomID"].current == roomIDEnum["lowerNorfairEscapePowerBombRoom"]
-        && (snes["norfairItems4"].old + 8) == (snes["norfairItems4"].current);
+        && snes.was_in_room("lowerNorfairEscapePowerBombRoom")
+        && snes.bit_set("norfairItems4", 8);
     let shameBombs = settings.get("shameBombs")
-        && snes["roomID"].current == roomIDEnum["wasteland"]
-        && (snes["norfairItems4"].old + 16) == (snes["norfairItems4"].current);
+        && snes.was_in_room("wasteland")
+        && snes.bit_set("norfairItems4", 16);
     let rightSandPitBombs = settings.get("rightSandPitBombs")
-        && snes["roomID"].current == roomIDEnum["rightSandPit"]
-        && (snes["maridiaItems2"].old + 8) == (snes["maridiaItems2"].current);
-    let pickup = firstMissile
-        || allMissiles
-        || oceanBottomMissiles
-        || oceanTopMissiles
-        || oceanMiddleMissiles
-        || moatMissiles
-        || oldTourianMissiles
-        || gauntletRightMissiles
-        || gauntletLeftMissiles
-        || dentalPlan
-        || earlySuperBridgeMissiles
-        || greenBrinstarReserveMissiles
-        || greenBrinstarExtraReserveMissiles
-        || bigPinkTopMissiles
-        || chargeMissiles
-        || greenHillsMissiles
-        || blueBrinstarETankMissiles
-        || alphaMissiles
-        || billyMaysMissiles
-        || butWaitTheresMoreMissiles
-        || redBrinstarMissiles
-        || warehouseMissiles
-        || cathedralMissiles
-        || crumbleShaftMissiles
-        || crocomireEscapeMissiles
-        || hiJumpMissiles
-        || postCrocomireMissiles
-        || grappleMissiles
-        || norfairReserveMissiles
-        || greenBubblesMissiles
-        || bubbleMountainMissiles
-        || speedBoostMissiles
-        || waveMissiles
-        || goldTorizoMissiles
-        || mickeyMouseMissiles
-        || lowerNorfairSpringMazeMissiles
-        || threeMusketeersMissiles
-        || wreckedShipMainShaftMissiles
-        || bowlingMissiles
-        || atticMissiles
-        || mainStreetMissiles
-        || mamaTurtleMissiles
-        || wateringHoleMissiles
-        || beachMissiles
-        || leftSandPitMissiles
-        || rightSandPitMissiles
-        || aqueductMissiles
-        || preDraygonMissiles
-        || firstSuper
-        || allSupers
-        || climbSupers
-        || sporeSpawnSupers
-        || earlySupers
-        || etecoonSupers
-        || goldTorizoSupers
-        || wreckedShipLeftSupers
-        || wreckedShipRightSupers
-        || crabSupers
-        || wateringHoleSupers
-        || aqueductSupers
-        || firstPowerBomb
-        || allPowerBombs
-        || landingSiteBombs
-        || etecoonBombs
-        || pinkBrinstarBombs
-        || blueBrinstarBombs
-        || alphaBombs
-        || betaBombs
-        || crocomireBombs
-        || lowerNorfairEscapeBombs
-        || shameBombs
-        || rightSandPitBombs;
+        && snes.was_in_room("rightSandPit")
+        && snes.bit_set("maridiaItems2", 8);
 
     // Item unlock section
     let varia = settings.get("variaSuit")
-        && snes["roomID"].current == roomIDEnum["varia"]
-        && (snes["unlockedEquips2"].old & unlockFlagEnum["variaSuit"]) == 0
-        && (snes["unlockedEquips2"].current & unlockFlagEnum["variaSuit"]) > 0;
+        && snes.was_in_room("varia")
+        && snes.bit_set("unlockedEquips2", unlockFlagEnum["variaSuit"]);
     let springBall = settings.get("springBall")
-        && snes["roomID"].current == roomIDEnum["springBall"]
-        && (snes["unlockedEquips2"].old & unlockFlagEnum["springBall"]) == 0
-        && (snes["unlockedEquips2"].current & unlockFlagEnum["springBall"]) > 0;
+        && snes.was_in_room("springBall")
+        && snes.bit_set("unlockedEquips2", unlockFlagEnum["springBall"]);
     let morphBall = settings.get("morphBall")
-        && snes["roomID"].current == roomIDEnum["morphBall"]
-        && (snes["unlockedEquips2"].old & unlockFlagEnum["morphBall"]) == 0
-        && (snes["unlockedEquips2"].current & unlockFlagEnum["morphBall"]) > 0;
+        && snes.was_in_room("morphBall")
+        && snes.bit_set("unlockedEquips2", unlockFlagEnum["morphBall"]);
     let screwAttack = settings.get("screwAttack")
-        && snes["roomID"].current == roomIDEnum["screwAttack"]
-        && (snes["unlockedEquips2"].old & unlockFlagEnum["screwAttack"]) == 0
-        && (snes["unlockedEquips2"].current & unlockFlagEnum["screwAttack"]) > 0;
+        && snes.was_in_room("screwAttack")
+        && snes.bit_set("unlockedEquips2", unlockFlagEnum["screwAttack"]);
     let gravSuit = settings.get("gravSuit")
-        && snes["roomID"].current == roomIDEnum["gravity"]
-        && (snes["unlockedEquips2"].old & unlockFlagEnum["gravSuit"]) == 0
-        && (snes["unlockedEquips2"].current & unlockFlagEnum["gravSuit"]) > 0;
+        && snes.was_in_room("gravity")
+        && snes.bit_set("unlockedEquips2", unlockFlagEnum["gravSuit"]);
     let hiJump = settings.get("hiJump")
-        && snes["roomID"].current == roomIDEnum["hiJump"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["hiJump"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["hiJump"]) > 0;
+        && snes.was_in_room("hiJump")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["hiJump"]);
     let spaceJump = settings.get("spaceJump")
-        && snes["roomID"].current == roomIDEnum["spaceJump"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["spaceJump"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["spaceJump"]) > 0;
+        && snes.was_in_room("spaceJump")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["spaceJump"]);
     let bomb = settings.get("bomb")
-        && snes["roomID"].current == roomIDEnum["bombTorizo"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["bomb"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["bomb"]) > 0;
+        && snes.was_in_room("bombTorizo")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["bomb"]);
     let speedBooster = settings.get("speedBooster")
-        && snes["roomID"].current == roomIDEnum["speedBooster"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["speedBooster"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["speedBooster"]) > 0;
+        && snes.was_in_room("speedBooster")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["speedBooster"]);
     let grapple = settings.get("grapple")
-        && snes["roomID"].current == roomIDEnum["grapple"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["grapple"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["grapple"]) > 0;
+        && snes.was_in_room("grapple")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["grapple"]);
     let xray = settings.get("xray")
-        && snes["roomID"].current == roomIDEnum["xRay"]
-        && (snes["unlockedEquips"].old & unlockFlagEnum["xray"]) == 0
-        && (snes["unlockedEquips"].current & unlockFlagEnum["xray"]) > 0;
-    let unlock = varia
-        || springBall
-        || morphBall
-        || screwAttack
-        || gravSuit
-        || hiJump
-        || spaceJump
-        || bomb
-        || speedBooster
-        || grapple
-        || xray;
+        && snes.was_in_room("xRay")
+        && snes.bit_set("unlockedEquips", unlockFlagEnum["xray"]);
 
     // Beam unlock section
     let wave = settings.get("wave")
-        && snes["roomID"].current == roomIDEnum["waveBeam"]
-        && (snes["unlockedBeams"].old & unlockFlagEnum["wave"]) == 0
-        && (snes["unlockedBeams"].current & unlockFlagEnum["wave"]) > 0;
+        && snes.was_in_room("waveBeam")
+        && snes.bit_set("unlockedBeams", unlockFlagEnum["wave"]);
     let ice = settings.get("ice")
-        && snes["roomID"].current == roomIDEnum["iceBeam"]
-        && (snes["unlockedBeams"].old & unlockFlagEnum["ice"]) == 0
-        && (snes["unlockedBeams"].current & unlockFlagEnum["ice"]) > 0;
+        && snes.was_in_room("iceBeam")
+        && snes.bit_set("unlockedBeams", unlockFlagEnum["ice"]);
     let spazer = settings.get("spazer")
-        && snes["roomID"].current == roomIDEnum["spazer"]
-        && (snes["unlockedBeams"].old & unlockFlagEnum["spazer"]) == 0
-        && (snes["unlockedBeams"].current & unlockFlagEnum["spazer"]) > 0;
+        && snes.was_in_room("spazer")
+        && snes.bit_set("unlockedBeams", unlockFlagEnum["spazer"]);
     let plasma = settings.get("plasma")
-        && snes["roomID"].current == roomIDEnum["plasmaBeam"]
-        && (snes["unlockedBeams"].old & unlockFlagEnum["plasma"]) == 0
-        && (snes["unlockedBeams"].current & unlockFlagEnum["plasma"]) > 0;
+        && snes.was_in_room("plasmaBeam")
+        && snes.bit_set("unlockedBeams", unlockFlagEnum["plasma"]);
     let chargeBeam = settings.get("chargeBeam")
-        && snes["roomID"].current == roomIDEnum["bigPink"]
-        && (snes["unlockedCharge"].old & unlockFlagEnum["chargeBeam"]) == 0
-        && (snes["unlockedCharge"].current & unlockFlagEnum["chargeBeam"]) > 0;
-    let beam = wave || ice || spazer || plasma || chargeBeam;
+        && snes.was_in_room("bigPink")
+        && snes.bit_set("unlockedCharge", unlockFlagEnum["chargeBeam"]);
 
     // E-tanks and reserve tanks
-    let firstETank = settings.get("firstETank")
-        && snes["maxEnergy"].old == 99
-        && snes["maxEnergy"].current == 199;
-    let allETanks =
-        settings.get("allETanks") && (snes["maxEnergy"].old + 100) == (snes["maxEnergy"].current);
+    let firstETank =
+        settings.get("firstETank") && snes["maxEnergy"].old == 99 && snes.gained("maxEnergy", 100);
+    let allETanks = settings.get("allETanks") && snes.gained("maxEnergy", 100);
     let gauntletETank = settings.get("gauntletETank")
-        && snes["roomID"].current == roomIDEnum["gauntletETankRoom"]
-        && (snes["crateriaItems"].old + 32) == (snes["crateriaItems"].current);
+        && snes.was_in_room("gauntletETankRoom")
+        && snes.bit_set("crateriaItems", 32);
     let terminatorETank = settings.get("terminatorETank")
-        && snes["roomID"].current == roomIDEnum["terminator"]
-        && (snes["brinteriaItems"].old + 1) == (snes["brinteriaItems"].current);
+        && snes.was_in_room("terminator")
+        && snes.bit_set("brinteriaItems", 1);
     let ceilingETank = settings.get("ceilingETank")
-        && snes["roomID"].current == roomIDEnum["blueBrinstarETankRoom"]
-        && (snes["brinstarItems3"].old + 32) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("blueBrinstarETankRoom")
+        && snes.bit_set("brinstarItems3", 32);
     let etecoonsETank = (settings.get("etecoonsETank") || settings.get("etacoonsETank"))
-        && snes["roomID"].current == roomIDEnum["etecoonETankRoom"]
-        && (snes["brinstarItems3"].old + 64) == (snes["brinstarItems3"].current);
+        && snes.was_in_room("etecoonETankRoom")
+        && snes.bit_set("brinstarItems3", 64);
     let waterwayETank = settings.get("waterwayETank")
-        && snes["roomID"].current == roomIDEnum["waterway"]
-        && (snes["brinstarItems4"].old + 2) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("waterway")
+        && snes.bit_set("brinstarItems4", 2);
     let waveGateETank = settings.get("waveGateETank")
-        && snes["roomID"].current == roomIDEnum["hopperETankRoom"]
-        && (snes["brinstarItems4"].old + 8) == (snes["brinstarItems4"].current);
+        && snes.was_in_room("hopperETankRoom")
+        && snes.bit_set("brinstarItems4", 8);
     let kraidETank = settings.get("kraidETank")
-        && snes["roomID"].current == roomIDEnum["warehouseETankRoom"]
-        && (snes["brinstarItems5"].old + 8) == (snes["brinstarItems5"].current);
+        && snes.was_in_room("warehouseETankRoom")
+        && snes.bit_set("brinstarItems5", 8);
     let crocomireETank = settings.get("crocomireETank")
-        && snes["roomID"].current == roomIDEnum["crocomire"]
-        && (snes["norfairItems1"].old + 16) == (snes["norfairItems1"].current);
+        && snes.was_in_room("crocomire")
+        && snes.bit_set("norfairItems1", 16);
     let hiJumpETank = settings.get("hiJumpETank")
-        && snes["roomID"].current == roomIDEnum["hiJumpShaft"]
-        && (snes["norfairItems2"].old + 1) == (snes["norfairItems2"].current);
+        && snes.was_in_room("hiJumpShaft")
+        && snes.bit_set("norfairItems2", 1);
     let ridleyETank = settings.get("ridleyETank")
-        && snes["roomID"].current == roomIDEnum["ridleyETankRoom"]
-        && (snes["norfairItems4"].old + 64) == (snes["norfairItems4"].current);
+        && snes.was_in_room("ridleyETankRoom")
+        && snes.bit_set("norfairItems4", 64);
     let firefleaETank = settings.get("firefleaETank")
-        && snes["roomID"].current == roomIDEnum["lowerNorfairFireflea"]
-        && (snes["norfairItems5"].old + 1) == (snes["norfairItems5"].current);
+        && snes.was_in_room("lowerNorfairFireflea")
+        && snes.bit_set("norfairItems5", 1);
     let wreckedShipETank = settings.get("wreckedShipETank")
-        && snes["roomID"].current == roomIDEnum["wreckedShipETankRoom"]
-        && (snes["wreckedShipItems"].old + 16) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("wreckedShipETankRoom")
+        && snes.bit_set("wreckedShipItems", 16);
     let tatoriETank = settings.get("tatoriETank")
-        && snes["roomID"].current == roomIDEnum["mamaTurtle"]
-        && (snes["maridiaItems1"].old + 4) == (snes["maridiaItems1"].current);
+        && snes.was_in_room("mamaTurtle")
+        && snes.bit_set("maridiaItems1", 4);
     let botwoonETank = settings.get("botwoonETank")
-        && snes["roomID"].current == roomIDEnum["botwoonETankRoom"]
-        && (snes["maridiaItems3"].old + 1) == (snes["maridiaItems3"].current);
-    let reserveTanks = settings.get("reserveTanks")
-        && (snes["maxReserve"].old + 100) == (snes["maxReserve"].current);
+        && snes.was_in_room("botwoonETankRoom")
+        && snes.bit_set("maridiaItems3", 1);
+    let reserveTanks = settings.get("reserveTanks") && snes.gained("maxReserve", 100);
     let brinstarReserve = settings.get("brinstarReserve")
-        && snes["roomID"].current == roomIDEnum["brinstarReserveRoom"]
-        && (snes["brinstarItems2"].old + 2) == (snes["brinstarItems2"].current);
+        && snes.was_in_room("brinstarReserveRoom")
+        && snes.bit_set("brinstarItems2", 2);
     let norfairReserve = settings.get("norfairReserve")
-        && snes["roomID"].current == roomIDEnum["norfairReserveRoom"]
-        && (snes["norfairItems2"].old + 32) == (snes["norfairItems2"].current);
+        && snes.was_in_room("norfairReserveRoom")
+        && snes.bit_set("norfairItems2", 32);
     let wreckedShipReserve = settings.get("wreckedShipReserve")
-        && snes["roomID"].current == roomIDEnum["bowling"]
-        && (snes["wreckedShipItems"].old + 2) == (snes["wreckedShipItems"].current);
+        && snes.was_in_room("bowling")
+        && snes.bit_set("wreckedShipItems", 2);
     let maridiaReserve = settings.get("maridiaReserve")
-        && snes["roomID"].current == roomIDEnum["leftSandPit"]
-        && (snes["maridiaItems2"].old + 2) == (snes["maridiaItems2"].current);
-    let energyUpgrade = firstETank
-        || allETanks
-        || gauntletETank
-        || terminatorETank
-        || ceilingETank
-        || etecoonsETank
-        || waterwayETank
-        || waveGateETank
-        || kraidETank
-        || crocomireETank
-        || hiJumpETank
-        || ridleyETank
-        || firefleaETank
-        || wreckedShipETank
-        || tatoriETank
-        || botwoonETank
-        || reserveTanks
-        || brinstarReserve
-        || norfairReserve
-        || wreckedShipReserve
-        || maridiaReserve;
+        && snes.was_in_room("leftSandPit")
+        && snes.bit_set("maridiaItems2", 2);
 
     // Miniboss room transitions
     let mut miniBossRooms = false;
     if settings.get("miniBossRooms") {
-        let ceresRidleyRoom = snes["roomID"].old == roomIDEnum["flatRoom"]
-            && snes["roomID"].current == roomIDEnum["ceresRidley"];
-        let sporeSpawnRoom = snes["roomID"].old == roomIDEnum["sporeSpawnKeyhunter"]
-            && snes["roomID"].current == roomIDEnum["sporeSpawn"];
-        let crocomireRoom = snes["roomID"].old == roomIDEnum["crocomireSpeedway"]
-            && snes["roomID"].current == roomIDEnum["crocomire"];
-        let botwoonRoom = snes["roomID"].old == roomIDEnum["botwoonHallway"]
-            && snes["roomID"].current == roomIDEnum["botwoon"];
+        let ceresRidleyRoom = snes.moved("flatRoom", "ceresRidley");
+        let sporeSpawnRoom = snes.moved("sporeSpawnKeyhunter", "sporeSpawn");
+        let crocomireRoom = snes.moved("crocomireSpeedway", "crocomire");
+        let botwoonRoom = snes.moved("botwoonHallway", "botwoon");
         // Allow either vanilla or GGG entry
-        let goldenTorizoRoom = (snes["roomID"].old == roomIDEnum["acidStatue"]
-            || snes["roomID"].old == roomIDEnum["screwAttack"])
-            && snes["roomID"].current == roomIDEnum["goldenTorizo"];
+        let goldenTorizoRoom =
+            snes.moved("acidStatue", "goldenTorizo") || snes.moved("screwAttack", "goldenTorizo");
         miniBossRooms =
             ceresRidleyRoom || sporeSpawnRoom || crocomireRoom || botwoonRoom || goldenTorizoRoom;
     }
@@ -1269,42 +1070,27 @@ fn split(settings: &Settings, snes: &mut SNESState) -> bool {
     // Boss room transitions
     let mut bossRooms = false;
     if settings.get("bossRooms") {
-        let kraidRoom = snes["roomID"].old == roomIDEnum["kraidEyeDoor"]
-            && snes["roomID"].current == roomIDEnum["kraid"];
-        let phantoonRoom = snes["roomID"].old == roomIDEnum["basement"]
-            && snes["roomID"].current == roomIDEnum["phantoon"];
-        let draygonRoom = snes["roomID"].old == roomIDEnum["precious"]
-            && snes["roomID"].current == roomIDEnum["draygon"];
-        let ridleyRoom = snes["roomID"].old == roomIDEnum["lowerNorfairFarming"]
-            && snes["roomID"].current == roomIDEnum["ridley"];
-        let motherBrainRoom = snes["roomID"].old == roomIDEnum["rinkaShaft"]
-            && snes["roomID"].current == roomIDEnum["motherBrain"];
+        let kraidRoom = snes.moved("kraidEyeDoor", "kraid");
+        let phantoonRoom = snes.moved("basement", "phantoon");
+        let draygonRoom = snes.moved("precious", "draygon");
+        let ridleyRoom = snes.moved("lowerNorfairFarming", "ridley");
+        let motherBrainRoom = snes.moved("rinkaShaft", "motherBrain");
         bossRooms = kraidRoom || phantoonRoom || draygonRoom || ridleyRoom || motherBrainRoom;
     }
 
     // Elevator transitions between areas
     let mut elevatorTransitions = false;
     if settings.get("elevatorTransitions") {
-        let blueBrinstar = (snes["roomID"].old == roomIDEnum["elevatorToMorphBall"]
-            && snes["roomID"].current == roomIDEnum["morphBall"])
-            || (snes["roomID"].old == roomIDEnum["morphBall"]
-                && snes["roomID"].current == roomIDEnum["elevatorToMorphBall"]);
-        let greenBrinstar = (snes["roomID"].old == roomIDEnum["elevatorToGreenBrinstar"]
-            && snes["roomID"].current == roomIDEnum["greenBrinstarMainShaft"])
-            || (snes["roomID"].old == roomIDEnum["greenBrinstarMainShaft"]
-                && snes["roomID"].current == roomIDEnum["elevatorToGreenBrinstar"]);
-        let businessCenter = (snes["roomID"].old == roomIDEnum["warehouseEntrance"]
-            && snes["roomID"].current == roomIDEnum["businessCenter"])
-            || (snes["roomID"].old == roomIDEnum["businessCenter"]
-                && snes["roomID"].current == roomIDEnum["warehouseEntrance"]);
-        let caterpillar = (snes["roomID"].old == roomIDEnum["elevatorToCaterpillar"]
-            && snes["roomID"].current == roomIDEnum["caterpillar"])
-            || (snes["roomID"].old == roomIDEnum["caterpillar"]
-                && snes["roomID"].current == roomIDEnum["elevatorToCaterpillar"]);
-        let maridiaElevator = (snes["roomID"].old == roomIDEnum["elevatorToMaridia"]
-            && snes["roomID"].current == roomIDEnum["maridiaElevator"])
-            || (snes["roomID"].old == roomIDEnum["maridiaElevator"]
-                && snes["roomID"].current == roomIDEnum["elevatorToMaridia"]);
+        let blueBrinstar = (snes.moved("elevatorToMorphBall", "morphBall"))
+            || (snes.moved("morphBall", "elevatorToMorphBall"));
+        let greenBrinstar = (snes.moved("elevatorToGreenBrinstar", "greenBrinstarMainShaft"))
+            || (snes.moved("greenBrinstarMainShaft", "elevatorToGreenBrinstar"));
+        let businessCenter = (snes.moved("warehouseEntrance", "businessCenter"))
+            || (snes.moved("businessCenter", "warehouseEntrance"));
+        let caterpillar = (snes.moved("elevatorToCaterpillar", "caterpillar"))
+            || (snes.moved("caterpillar", "elevatorToCaterpillar"));
+        let maridiaElevator = (snes.moved("elevatorToMaridia", "maridiaElevator"))
+            || (snes.moved("maridiaElevator", "elevatorToMaridia"));
         elevatorTransitions =
             blueBrinstar || greenBrinstar || businessCenter || caterpillar || maridiaElevator;
     }
@@ -1312,211 +1098,109 @@ fn split(settings: &Settings, snes: &mut SNESState) -> bool {
     // Room transitions
     let ceresEscape = settings.get("ceresEscape")
         && snes["roomID"].current == roomIDEnum["ceresElevator"]
-        && snes["gameState"].old == gameStateEnum["normalGameplay"]
-        && snes["gameState"].current == gameStateEnum["startOfCeresCutscene"];
-    let wreckedShipEntrance = settings.get("wreckedShipEntrance")
-        && snes["roomID"].old == roomIDEnum["westOcean"]
-        && snes["roomID"].current == roomIDEnum["wreckedShipEntrance"];
-    let redTowerMiddleEntrance = settings.get("redTowerMiddleEntrance")
-        && snes["roomID"].old == roomIDEnum["noobBridge"]
-        && snes["roomID"].current == roomIDEnum["redTower"];
-    let redTowerBottomEntrance = settings.get("redTowerBottomEntrance")
-        && snes["roomID"].old == roomIDEnum["bat"]
-        && snes["roomID"].current == roomIDEnum["redTower"];
-    let kraidsLair = settings.get("kraidsLair")
-        && snes["roomID"].old == roomIDEnum["warehouseEntrance"]
-        && snes["roomID"].current == roomIDEnum["warehouseZeela"];
-    let risingTideEntrance = settings.get("risingTideEntrance")
-        && snes["roomID"].old == roomIDEnum["cathedral"]
-        && snes["roomID"].current == roomIDEnum["risingTide"];
-    let atticExit = settings.get("atticExit")
-        && snes["roomID"].old == roomIDEnum["attic"]
-        && snes["roomID"].current == roomIDEnum["westOcean"];
+        && snes.entered("gameState", gameStateEnum["startOfCeresCutscene"]);
+    let wreckedShipEntrance =
+        settings.get("wreckedShipEntrance") && snes.moved("westOcean", "wreckedShipEntrance");
+    let redTowerMiddleEntrance =
+        settings.get("redTowerMiddleEntrance") && snes.moved("noobBridge", "redTower");
+    let redTowerBottomEntrance =
+        settings.get("redTowerBottomEntrance") && snes.moved("bat", "redTower");
+    let kraidsLair =
+        settings.get("kraidsLair") && snes.moved("warehouseEntrance", "warehouseZeela");
+    let risingTideEntrance =
+        settings.get("risingTideEntrance") && snes.moved("cathedral", "risingTide");
+    let atticExit = settings.get("atticExit") && snes.moved("attic", "westOcean");
     let tubeBroken = settings.get("tubeBroken")
-        && snes["roomID"].current == roomIDEnum["glassTunnel"]
-        && (snes["eventFlags"].old & eventFlagEnum["tubeBroken"]) == 0
-        && (snes["eventFlags"].current & eventFlagEnum["tubeBroken"]) > 0;
-    let cacExit = settings.get("cacExit")
-        && snes["roomID"].old == roomIDEnum["westCactusAlley"]
-        && snes["roomID"].current == roomIDEnum["butterflyRoom"];
+        && snes.was_in_room("glassTunnel")
+        && snes.bit_set("eventFlags", eventFlagEnum["tubeBroken"]);
+    let cacExit = settings.get("cacExit") && snes.moved("westCactusAlley", "butterflyRoom");
     let toilet = settings.get("toilet")
-        && (snes["roomID"].old == roomIDEnum["plasmaSpark"]
-            && snes["roomID"].current == roomIDEnum["toiletBowl"]
-            || snes["roomID"].old == roomIDEnum["oasis"]
-                && snes["roomID"].current == roomIDEnum["toiletBowl"]);
+        && (snes.moved("plasmaSpark", "toiletBowl") || snes.moved("oasis", "toiletBowl"));
     let kronicBoost = settings.get("kronicBoost")
-        && (snes["roomID"].old == roomIDEnum["magdolliteTunnel"]
-            && snes["roomID"].current == roomIDEnum["kronicBoost"]
-            || snes["roomID"].old == roomIDEnum["spikyAcidSnakes"]
-                && snes["roomID"].current == roomIDEnum["kronicBoost"]
-            || snes["roomID"].old == roomIDEnum["volcano"]
-                && snes["roomID"].current == roomIDEnum["kronicBoost"]);
-    let lowerNorfairEntrance = settings.get("lowerNorfairEntrance")
-        && snes["roomID"].old == roomIDEnum["lowerNorfairElevator"]
-        && snes["roomID"].current == roomIDEnum["mainHall"];
-    let writg = settings.get("writg")
-        && snes["roomID"].old == roomIDEnum["pillars"]
-        && snes["roomID"].current == roomIDEnum["writg"];
+        && (snes.moved("magdolliteTunnel", "kronicBoost")
+            || snes.moved("spikyAcidSnakes", "kronicBoost")
+            || snes.moved("volcano", "kronicBoost"));
+    let lowerNorfairEntrance =
+        settings.get("lowerNorfairEntrance") && snes.moved("lowerNorfairElevator", "mainHall");
+    let writg = settings.get("writg") && snes.moved("pillars", "writg");
     let redKiShaft = settings.get("redKiShaft")
-        && (snes["roomID"].old == roomIDEnum["amphitheatre"]
-            && snes["roomID"].current == roomIDEnum["redKiShaft"]
-            || snes["roomID"].old == roomIDEnum["wasteland"]
-                && snes["roomID"].current == roomIDEnum["redKiShaft"]);
-    let metalPirates = settings.get("metalPirates")
-        && snes["roomID"].old == roomIDEnum["wasteland"]
-        && snes["roomID"].current == roomIDEnum["metalPirates"];
+        && (snes.moved("amphitheatre", "redKiShaft") || snes.moved("wasteland", "redKiShaft"));
+    let metalPirates = settings.get("metalPirates") && snes.moved("wasteland", "metalPirates");
     let lowerNorfairSpringMaze = settings.get("lowerNorfairSpringMaze")
-        && snes["roomID"].old == roomIDEnum["lowerNorfairFireflea"]
-        && snes["roomID"].current == roomIDEnum["lowerNorfairSpringMaze"];
-    let lowerNorfairExit = settings.get("lowerNorfairExit")
-        && snes["roomID"].old == roomIDEnum["threeMusketeers"]
-        && snes["roomID"].current == roomIDEnum["singleChamber"];
+        && snes.moved("lowerNorfairFireflea", "lowerNorfairSpringMaze");
+    let lowerNorfairExit =
+        settings.get("lowerNorfairExit") && snes.moved("threeMusketeers", "singleChamber");
     let allBossesFinished = (snes["brinstarBosses"].current & bossFlagEnum["kraid"]) > 0
         && (snes["wreckedShipBosses"].current & bossFlagEnum["phantoon"]) > 0
         && (snes["maridiaBosses"].current & bossFlagEnum["draygon"]) > 0
         && (snes["norfairBosses"].current & bossFlagEnum["ridley"]) > 0;
-    let goldenFour = settings.get("goldenFour")
-        && snes["roomID"].old == roomIDEnum["statuesHallway"]
-        && snes["roomID"].current == roomIDEnum["statues"]
-        && allBossesFinished;
-    let tourianEntrance = settings.get("tourianEntrance")
-        && snes["roomID"].old == roomIDEnum["statues"]
-        && snes["roomID"].current == roomIDEnum["tourianElevator"];
+    let goldenFour =
+        settings.get("goldenFour") && snes.moved("statuesHallway", "statues") && allBossesFinished;
+    let tourianEntrance =
+        settings.get("tourianEntrance") && snes.moved("statues", "tourianElevator");
     let metroids = settings.get("metroids")
-        && (snes["roomID"].old == roomIDEnum["metroidOne"]
-            && snes["roomID"].current == roomIDEnum["metroidTwo"]
-            || snes["roomID"].old == roomIDEnum["metroidTwo"]
-                && snes["roomID"].current == roomIDEnum["metroidThree"]
-            || snes["roomID"].old == roomIDEnum["metroidThree"]
-                && snes["roomID"].current == roomIDEnum["metroidFour"]
-            || snes["roomID"].old == roomIDEnum["metroidFour"]
-                && snes["roomID"].current == roomIDEnum["tourianHopper"]);
-    let babyMetroidRoom = settings.get("babyMetroidRoom")
-        && snes["roomID"].old == roomIDEnum["dustTorizo"]
-        && snes["roomID"].current == roomIDEnum["bigBoy"];
-    let escapeClimb = settings.get("escapeClimb")
-        && snes["roomID"].old == roomIDEnum["tourianEscape4"]
-        && snes["roomID"].current == roomIDEnum["climb"];
-    let roomTransitions = miniBossRooms
-        || bossRooms
-        || elevatorTransitions
-        || ceresEscape
-        || wreckedShipEntrance
-        || redTowerMiddleEntrance
-        || redTowerBottomEntrance
-        || kraidsLair
-        || risingTideEntrance
-        || atticExit
-        || tubeBroken
-        || cacExit
-        || toilet
-        || kronicBoost
-        || lowerNorfairEntrance
-        || writg
-        || redKiShaft
-        || metalPirates
-        || lowerNorfairSpringMaze
-        || lowerNorfairExit
-        || tourianEntrance
-        || goldenFour
-        || metroids
-        || babyMetroidRoom
-        || escapeClimb;
+        && (snes.moved("metroidOne", "metroidTwo")
+            || snes.moved("metroidTwo", "metroidThree")
+            || snes.moved("metroidThree", "metroidFour")
+            || snes.moved("metroidFour", "tourianHopper"));
+    let babyMetroidRoom = settings.get("babyMetroidRoom") && snes.moved("dustTorizo", "bigBoy");
+    let escapeClimb = settings.get("escapeClimb") && snes.moved("tourianEscape4", "climb");
 
     // Minibosses
     let ceresRidley = settings.get("ceresRidley")
-        && (snes["ceresBosses"].old & bossFlagEnum["ceresRidley"]) == 0
-        && (snes["ceresBosses"].current & bossFlagEnum["ceresRidley"]) > 0
-        && snes["roomID"].current == roomIDEnum["ceresRidley"];
+        && snes.bit_set("ceresBosses", bossFlagEnum["ceresRidley"])
+        && snes.was_in_room("ceresRidley");
     let bombTorizo = settings.get("bombTorizo")
-        && (snes["crateriaBosses"].old & bossFlagEnum["bombTorizo"]) == 0
-        && (snes["crateriaBosses"].current & bossFlagEnum["bombTorizo"]) > 0
-        && snes["roomID"].current == roomIDEnum["bombTorizo"];
+        && snes.bit_set("crateriaBosses", bossFlagEnum["bombTorizo"])
+        && snes.was_in_room("bombTorizo");
     let sporeSpawn = settings.get("sporeSpawn")
-        && (snes["brinstarBosses"].old & bossFlagEnum["sporeSpawn"]) == 0
-        && (snes["brinstarBosses"].current & bossFlagEnum["sporeSpawn"]) > 0
-        && snes["roomID"].current == roomIDEnum["sporeSpawn"];
+        && snes.bit_set("brinstarBosses", bossFlagEnum["sporeSpawn"])
+        && snes.was_in_room("sporeSpawn");
     let crocomire = settings.get("crocomire")
-        && (snes["norfairBosses"].old & bossFlagEnum["crocomire"]) == 0
-        && (snes["norfairBosses"].current & bossFlagEnum["crocomire"]) > 0
-        && snes["roomID"].current == roomIDEnum["crocomire"];
+        && snes.bit_set("norfairBosses", bossFlagEnum["crocomire"])
+        && snes.was_in_room("crocomire");
     let botwoon = settings.get("botwoon")
-        && (snes["maridiaBosses"].old & bossFlagEnum["botwoon"]) == 0
-        && (snes["maridiaBosses"].current & bossFlagEnum["botwoon"]) > 0
-        && snes["roomID"].current == roomIDEnum["botwoon"];
+        && snes.bit_set("maridiaBosses", bossFlagEnum["botwoon"])
+        && snes.was_in_room("botwoon");
     let goldenTorizo = settings.get("goldenTorizo")
-        && (snes["norfairBosses"].old & bossFlagEnum["goldenTorizo"]) == 0
-        && (snes["norfairBosses"].current & bossFlagEnum["goldenTorizo"]) > 0
-        && snes["roomID"].current == roomIDEnum["goldenTorizo"];
-    let minibossDefeat =
-        ceresRidley || bombTorizo || sporeSpawn || crocomire || botwoon || goldenTorizo;
+        && snes.bit_set("norfairBosses", bossFlagEnum["goldenTorizo"])
+        && snes.was_in_room("goldenTorizo");
 
     // Bosses
     let kraid = settings.get("kraid")
-        && (snes["brinstarBosses"].old & bossFlagEnum["kraid"]) == 0
-        && (snes["brinstarBosses"].current & bossFlagEnum["kraid"]) > 0
-        && snes["roomID"].current == roomIDEnum["kraid"];
-    if kraid {
-        println!("Split due to kraid defeat");
-    }
+        && snes.bit_set("brinstarBosses", bossFlagEnum["kraid"])
+        && snes.was_in_room("kraid");
     let phantoon = settings.get("phantoon")
-        && (snes["wreckedShipBosses"].old & bossFlagEnum["phantoon"]) == 0
-        && (snes["wreckedShipBosses"].current & bossFlagEnum["phantoon"]) > 0
-        && snes["roomID"].current == roomIDEnum["phantoon"];
-    if phantoon {
-        println!("Split due to phantoon defeat");
-    }
+        && snes.bit_set("wreckedShipBosses", bossFlagEnum["phantoon"])
+        && snes.was_in_room("phantoon");
     let draygon = settings.get("draygon")
-        && (snes["maridiaBosses"].old & bossFlagEnum["draygon"]) == 0
-        && (snes["maridiaBosses"].current & bossFlagEnum["draygon"]) > 0
-        && snes["roomID"].current == roomIDEnum["draygon"];
-    if draygon {
-        println!("Split due to draygon defeat");
-    }
+        && snes.bit_set("maridiaBosses", bossFlagEnum["draygon"])
+        && snes.was_in_room("draygon");
     let ridley = settings.get("ridley")
-        && (snes["norfairBosses"].old & bossFlagEnum["ridley"]) == 0
-        && (snes["norfairBosses"].current & bossFlagEnum["ridley"]) > 0
-        && snes["roomID"].current == roomIDEnum["ridley"];
-    if ridley {
-        println!("Split due to ridley defeat");
-    }
+        && snes.bit_set("norfairBosses", bossFlagEnum["ridley"])
+        && snes.was_in_room("ridley");
     // Mother Brain phases
     let inMotherBrainRoom = snes["roomID"].current == roomIDEnum["motherBrain"];
     let mb1 = settings.get("mb1")
         && inMotherBrainRoom
         && snes["gameState"].current == gameStateEnum["normalGameplay"]
-        && snes["motherBrainHP"].old == 0
-        && snes["motherBrainHP"].current == (motherBrainMaxHPEnum["phase2"]);
-    if mb1 {
-        println!("Split due to mb1 defeat");
-    }
+        && snes.entered("motherBrainHP", motherBrainMaxHPEnum["phase2"]);
     let mb2 = settings.get("mb2")
         && inMotherBrainRoom
         && snes["gameState"].current == gameStateEnum["normalGameplay"]
-        && snes["motherBrainHP"].old == 0
-        && snes["motherBrainHP"].current == (motherBrainMaxHPEnum["phase3"]);
-    if mb2 {
-        println!("Split due to mb2 defeat");
-    }
+        && snes.entered("motherBrainHP", motherBrainMaxHPEnum["phase3"]);
     let mb3 = settings.get("mb3")
         && inMotherBrainRoom
-        && (snes["tourianBosses"].old & bossFlagEnum["motherBrain"]) == 0
-        && (snes["tourianBosses"].current & bossFlagEnum["motherBrain"]) > 0;
-    if mb3 {
-        println!("Split due to mb3 defeat");
-    }
-    let bossDefeat = kraid || phantoon || draygon || ridley || mb1 || mb2 || mb3;
+        && snes.bit_set("tourianBosses", bossFlagEnum["motherBrain"]);
 
     // Run-ending splits
     let escape = settings.get("rtaFinish")
         && (snes["eventFlags"].current & eventFlagEnum["zebesAblaze"]) > 0
-        && snes["shipAI"].old != 0xaa4f
-        && snes["shipAI"].current == 0xaa4f;
+        && snes.entered("shipAI", 0xaa4f);
 
     let takeoff = settings.get("igtFinish")
         && snes["roomID"].current == roomIDEnum["landingSite"]
-        && snes["gameState"].old == gameStateEnum["preEndCutscene"]
-        && snes["gameState"].current == gameStateEnum["endCutscene"];
+        && snes.entered("gameState", gameStateEnum["endCutscene"]);
 
     let mut sporeSpawnRTAFinish = false;
     if settings.get("sporeSpawnRTAFinish") {
@@ -1526,8 +1210,8 @@ fn split(settings: &Settings, snes: &mut SNESState) -> bool {
                 snes.pickedUpSporeSpawnSuper = false;
             }
         } else {
-            snes.pickedUpSporeSpawnSuper = snes["roomID"].current == roomIDEnum["sporeSpawnSuper"]
-                && (snes["maxSupers"].old + 5) == (snes["maxSupers"].current)
+            snes.pickedUpSporeSpawnSuper = snes.was_in_room("sporeSpawnSuper")
+                && snes.gained("maxSupers", 5)
                 && (snes["brinstarBosses"].current & bossFlagEnum["sporeSpawn"]) > 0;
         }
     }
@@ -1541,109 +1225,241 @@ fn split(settings: &Settings, snes: &mut SNESState) -> bool {
             }
         } else {
             snes.pickedUpHundredthMissile =
-                snes["maxMissiles"].old == 95 && snes["maxMissiles"].current == 100;
+                snes.gained("maxMissiles", 5) && snes["maxMissiles"].current == 100;
         }
     }
 
-    let nonStandardCategoryFinish = sporeSpawnRTAFinish || hundredMissileRTAFinish;
-
-    if pickup {
-        println!("Split due to pickup");
-    }
-    if unlock {
-        println!("Split due to unlock");
-    }
-    if beam {
-        println!("Split due to beam upgrade");
-    }
-    if energyUpgrade {
-        println!("Split due to energy upgrade");
-    }
-    if roomTransitions {
-        println!("Split due to room transition");
-    }
-    if minibossDefeat {
-        println!("Split due to miniboss defeat");
-    }
-    // individual boss defeat conditions already covered above
-    if escape {
-        println!("Split due to escape");
-    }
-    if takeoff {
-        println!("Split due to takeoff");
-    }
-    if nonStandardCategoryFinish {
-        println!("Split due to non standard category finish");
-    }
-
-    pickup
-        || unlock
-        || beam
-        || energyUpgrade
-        || roomTransitions
-        || minibossDefeat
-        || bossDefeat
-        || escape
-        || takeoff
-        || nonStandardCategoryFinish
-}
-
-#[derive(Debug, Copy, Clone)]
-pub enum Width {
-    Byte,
-    Word,
+    // Report each condition by the setting that enables it
+    [
+        ("firstMissile", firstMissile),
+        ("allMissiles", allMissiles),
+        ("oceanBottomMissiles", oceanBottomMissiles),
+        ("oceanTopMissiles", oceanTopMissiles),
+        ("oceanMiddleMissiles", oceanMiddleMissiles),
+        ("moatMissiles", moatMissiles),
+        ("oldTourianMissiles", oldTourianMissiles),
+        ("gauntletRightMissiles", gauntletRightMissiles),
+        ("gauntletLeftMissiles", gauntletLeftMissiles),
+        ("dentalPlan", dentalPlan),
+        ("earlySuperBridgeMissiles", earlySuperBridgeMissiles),
+        ("greenBrinstarReserveMissiles", greenBrinstarReserveMissiles),
+        (
+            "greenBrinstarExtraReserveMissiles",
+            greenBrinstarExtraReserveMissiles,
+        ),
+        ("bigPinkTopMissiles", bigPinkTopMissiles),
+        ("chargeMissiles", chargeMissiles),
+        ("greenHillsMissiles", greenHillsMissiles),
+        ("blueBrinstarETankMissiles", blueBrinstarETankMissiles),
+        ("alphaMissiles", alphaMissiles),
+        ("billyMaysMissiles", billyMaysMissiles),
+        ("butWaitTheresMoreMissiles", butWaitTheresMoreMissiles),
+        ("redBrinstarMissiles", redBrinstarMissiles),
+        ("warehouseMissiles", warehouseMissiles),
+        ("cathedralMissiles", cathedralMissiles),
+        ("crumbleShaftMissiles", crumbleShaftMissiles),
+        ("crocomireEscapeMissiles", crocomireEscapeMissiles),
+        ("hiJumpMissiles", hiJumpMissiles),
+        ("postCrocomireMissiles", postCrocomireMissiles),
+        ("grappleMissiles", grappleMissiles),
+        ("norfairReserveMissiles", norfairReserveMissiles),
+        ("greenBubblesMissiles", greenBubblesMissiles),
+        ("bubbleMountainMissiles", bubbleMountainMissiles),
+        ("speedBoostMissiles", speedBoostMissiles),
+        ("waveMissiles", waveMissiles),
+        ("goldTorizoMissiles", goldTorizoMissiles),
+        ("mickeyMouseMissiles", mickeyMouseMissiles),
+        (
+            "lowerNorfairSpringMazeMissiles",
+            lowerNorfairSpringMazeMissiles,
+        ),
+        ("threeMusketeersMissiles", threeMusketeersMissiles),
+        ("wreckedShipMainShaftMissiles", wreckedShipMainShaftMissiles),
+        ("bowlingMissiles", bowlingMissiles),
+        ("atticMissiles", atticMissiles),
+        ("mainStreetMissiles", mainStreetMissiles),
+        ("mamaTurtleMissiles", mamaTurtleMissiles),
+        ("wateringHoleMissiles", wateringHoleMissiles),
+        ("beachMissiles", beachMissiles),
+        ("leftSandPitMissiles", leftSandPitMissiles),
+        ("rightSandPitMissiles", rightSandPitMissiles),
+        ("aqueductMissiles", aqueductMissiles),
+        ("preDraygonMissiles", preDraygonMissiles),
+        ("firstSuper", firstSuper),
+        ("allSupers", allSupers),
+        ("climbSupers", climbSupers),
+        ("sporeSpawnSupers", sporeSpawnSupers),
+        ("earlySupers", earlySupers),
+        ("etecoonSupers", etecoonSupers),
+        ("goldTorizoSupers", goldTorizoSupers),
+        ("wreckedShipLeftSupers", wreckedShipLeftSupers),
+        ("wreckedShipRightSupers", wreckedShipRightSupers),
+        ("crabSupers", crabSupers),
+        ("wateringHoleSupers", wateringHoleSupers),
+        ("aqueductSupers", aqueductSupers),
+        ("firstPowerBomb", firstPowerBomb),
+        ("allPowerBombs", allPowerBombs),
+        ("landingSiteBombs", landingSiteBombs),
+        ("etecoonBombs", etecoonBombs),
+        ("pinkBrinstarBombs", pinkBrinstarBombs),
+        ("blueBrinstarBombs", blueBrinstarBombs),
+        ("alphaBombs", alphaBombs),
+        ("betaBombs", betaBombs),
+        ("crocomireBombs", crocomireBombs),
+        ("lowerNorfairEscapeBombs", lowerNorfairEscapeBombs),
+        ("shameBombs", shameBombs),
+        ("rightSandPitBombs", rightSandPitBombs),
+        ("variaSuit", varia),
+        ("springBall", springBall),
+        ("morphBall", morphBall),
+        ("screwAttack", screwAttack),
+        ("gravSuit", gravSuit),
+        ("hiJump", hiJump),
+        ("spaceJump", spaceJump),
+        ("bomb", bomb),
+        ("speedBooster", speedBooster),
+        ("grapple", grapple),
+        ("xray", xray),
+        ("wave", wave),
+        ("ice", ice),
+        ("spazer", spazer),
+        ("plasma", plasma),
+        ("chargeBeam", chargeBeam),
+        ("firstETank", firstETank),
+        ("allETanks", allETanks),
+        ("gauntletETank", gauntletETank),
+        ("terminatorETank", terminatorETank),
+        ("ceilingETank", ceilingETank),
+        ("etecoonsETank", etecoonsETank),
+        ("waterwayETank", waterwayETank),
+        ("waveGateETank", waveGateETank),
+        ("kraidETank", kraidETank),
+        ("crocomireETank", crocomireETank),
+        ("hiJumpETank", hiJumpETank),
+        ("ridleyETank", ridleyETank),
+        ("firefleaETank", firefleaETank),
+        ("wreckedShipETank", wreckedShipETank),
+        ("tatoriETank", tatoriETank),
+        ("botwoonETank", botwoonETank),
+        ("reserveTanks", reserveTanks),
+        ("brinstarReserve", brinstarReserve),
+        ("norfairReserve", norfairReserve),
+        ("wreckedShipReserve", wreckedShipReserve),
+        ("maridiaReserve", maridiaReserve),
+        ("miniBossRooms", miniBossRooms),
+        ("bossRooms", bossRooms),
+        ("elevatorTransitions", elevatorTransitions),
+        ("ceresEscape", ceresEscape),
+        ("wreckedShipEntrance", wreckedShipEntrance),
+        ("redTowerMiddleEntrance", redTowerMiddleEntrance),
+        ("redTowerBottomEntrance", redTowerBottomEntrance),
+        ("kraidsLair", kraidsLair),
+        ("risingTideEntrance", risingTideEntrance),
+        ("atticExit", atticExit),
+        ("tubeBroken", tubeBroken),
+        ("cacExit", cacExit),
+        ("toilet", toilet),
+        ("kronicBoost", kronicBoost),
+        ("lowerNorfairEntrance", lowerNorfairEntrance),
+        ("writg", writg),
+        ("redKiShaft", redKiShaft),
+        ("metalPirates", metalPirates),
+        ("lowerNorfairSpringMaze", lowerNorfairSpringMaze),
+        ("lowerNorfairExit", lowerNorfairExit),
+        ("tourianEntrance", tourianEntrance),
+        ("goldenFour", goldenFour),
+        ("metroids", metroids),
+        ("babyMetroidRoom", babyMetroidRoom),
+        ("escapeClimb", escapeClimb),
+        ("ceresRidley", ceresRidley),
+        ("bombTorizo", bombTorizo),
+        ("sporeSpawn", sporeSpawn),
+        ("crocomire", crocomire),
+        ("botwoon", botwoon),
+        ("goldenTorizo", goldenTorizo),
+        ("kraid", kraid),
+        ("phantoon", phantoon),
+        ("draygon", draygon),
+        ("ridley", ridley),
+        ("mb1", mb1),
+        ("mb2", mb2),
+        ("mb3", mb3),
+        ("rtaFinish", escape),
+        ("igtFinish", takeoff),
+        ("sporeSpawnRTAFinish", sporeSpawnRTAFinish),
+        ("hundredMissileRTAFinish", hundredMissileRTAFinish),
+    ]
+    .into_iter()
+    .filter(|(_, fired)| *fired)
+    .map(|(name, _)| SplitReason::from(name))
+    .collect()
 }
 
+// How many polls back a room transition can start, so one or two polls
+// landing mid-transition don't hide it
+const ROOM_HISTORY: usize = 3;
+
+// Progress that's saved with the game, so that reloading a save loses any
+// of it picked up since. The counters only ever go up, and the rest are
+// bitfields.
+const PROGRESS_COUNTERS: &[&str] = &[
+    "maxEnergy",
+    "maxMissiles",
+    "maxSupers",
+    "maxPowerBombs",
+    "maxReserve",
+];
+const PROGRESS_FLAGS: &[&str] = &[
+    "unlockedEquips2",
+    "unlockedEquips",
+    "unlockedBeams",
+    "unlockedCharge",
+    "eventFlags",
+    "crateriaBosses",
+    "brinstarBosses",
+    "norfairBosses",
+    "wreckedShipBosses",
+    "maridiaBosses",
+    "tourianBosses",
+    "ceresBosses",
+    "crateriaItems",
+    "brinteriaItems",
+    "brinstarItems2",
+    "brinstarItems3",
+    "brinstarItems4",
+    "brinstarItems5",
+    "norfairItems1",
+    "norfairItems2",
+    "norfairItems3",
+    "norfairItems4",
+    "norfairItems5",
+    "wreckedShipItems",
+    "maridiaItems1",
+    "maridiaItems2",
+    "maridiaItems3",
+];
+
+/// The reasons returned by one poll's `split`, along with the progress
+/// that changed in that poll as `(watcher, before, after)`
 #[derive(Clone)]
-pub struct MemoryWatcher {
-    address: u32,
-    current: u32,
-    old: u32,
-    width: Width,
-}
-
-impl MemoryWatcher {
-    pub fn new(address: u32, width: Width) -> MemoryWatcher {
-        MemoryWatcher {
-            address,
-            current: 0,
-            old: 0,
-            width,
-        }
-    }
-
-    fn update_value(&mut self, memory: &[u8]) {
-        match self.width {
-            Width::Byte => {
-                self.old = self.current;
-                self.current = memory[self.address as usize] as u32;
-            }
-            Width::Word => {
-                let address = self.address as usize;
-                self.old = self.current;
-                let word: u16 = (memory[address + 1] as u16) << 8 | memory[address] as u16;
-                self.current = word as u32;
-            }
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone)]
-pub struct SNESSummary {
-    pub latency_average: f32,
-    pub latency_stddev: f32,
-    pub start: bool,
-    pub reset: bool,
-    pub split: bool,
+struct Fired {
+    reasons: Vec<SplitReason>,
+    progress: Vec<(&'static str, i64, i64)>,
 }
 
 #[allow(non_snake_case)]
 #[derive(Clone)]
 pub struct SNESState {
     vars: HashMap<&'static str, MemoryWatcher>,
+    // Derived from vars, so a new watcher is read without further ado
+    plan: Vec<(u32, usize)>,
     pickedUpHundredthMissile: bool,
     pickedUpSporeSpawnSuper: bool,
-    latency_samples: VecDeque<u128>,
+    // Everything that split since the game was loaded from the file select
+    fired: Vec<Fired>,
+    // Samus died or a save is being loaded, so progress may be lost once
+    // gameplay resumes
+    reloading: bool,
     data: Vec<u8>,
     // The MemoryWatchers are not in a good
     // state until they've been updated
@@ -1651,23 +1467,35 @@ pub struct SNESState {
     // fields. So the first time we update, we
     // need to do it twice.
     do_extra_update: bool,
+    // Set by the user, overriding everything else
+    forced_region: Option<Region>,
+    // From the in-game frame counter
+    detected_region: Option<Region>,
+    // From the cartridge header
+    header_region: Option<Region>,
 }
 
-const NUM_LATENCY_SAMPLES: usize = 10;
-
 impl SNESState {
     pub fn new() -> SNESState {
-        let data = vec![0; 0x10000];
-        SNESState {
+        let data = vec![0; WRAM_SIZE];
+        let mut state = SNESState {
             do_extra_update: true,
             data,
-            latency_samples: VecDeque::from([]),
+            plan: vec![],
             pickedUpHundredthMissile: false,
             pickedUpSporeSpawnSuper: false,
+            fired: vec![],
+            reloading: false,
+            forced_region: None,
+            detected_region: None,
+            header_region: None,
             vars: HashMap::from([
                 // Word
                 ("controller", MemoryWatcher::new(0x008B, Width::Word)),
-                ("roomID", MemoryWatcher::new(0x079B, Width::Word)),
+                (
+                    "roomID",
+                    MemoryWatcher::new(0x079B, Width::Word).with_history(ROOM_HISTORY),
+                ),
                 ("enemyHP", MemoryWatcher::new(0x0F8C, Width::Word)),
                 ("shipAI", MemoryWatcher::new(0x0FB2, Width::Word)),
                 ("motherBrainHP", MemoryWatcher::new(0x0FCC, Width::Word)),
@@ -1712,10 +1540,91 @@ impl SNESState {
                 ("maridiaItems2", MemoryWatcher::new(0xD882, Width::Byte)),
                 ("maridiaItems3", MemoryWatcher::new(0xD883, Width::Byte)),
             ]),
+        };
+        state.plan = plan_reads(state.vars.values());
+        state
+    }
+
+    // Conditions are written with these rather than comparing `old` and
+    // `current` exactly, so that two changes landing between polls, or a
+    // state that came and went between them, don't lose a split.
+
+    /// Some bit of `mask` went from clear to set, whatever other bits did
+    fn bit_set(&self, var: &str, mask: i64) -> bool {
+        (self[var].old & mask) == 0 && (self[var].current & mask) != 0
+    }
+
+    /// The value went up by a whole number of `step`s. Going up from room
+    /// 0 is a save being loaded rather than a pickup.
+    fn gained(&self, var: &str, step: i64) -> bool {
+        let gained = self[var].current - self[var].old;
+        self["roomID"].old != 0 && gained > 0 && gained % step == 0
+    }
+
+    /// `var` changed to `value`, from anything
+    fn entered(&self, var: &str, value: i64) -> bool {
+        self[var].old != value && self[var].current == value
+    }
+
+    /// Samus was in the room at either end of the poll, so something
+    /// picked up on the way out still counts
+    fn was_in_room(&self, room: &str) -> bool {
+        let room = roomIDEnum[room];
+        self["roomID"].old == room || self["roomID"].current == room
+    }
+
+    /// Samus just entered room `to` from room `from`, even if a poll
+    /// landed on something else in between, like a door transition
+    fn moved(&self, from: &str, to: &str) -> bool {
+        self.entered("roomID", roomIDEnum[to])
+            && self["roomID"].was_within(roomIDEnum[from], ROOM_HISTORY)
+    }
+
+    /// Whether any of the progress from a poll has been lost since
+    fn lost(&self, progress: &[(&str, i64, i64)]) -> bool {
+        progress.iter().any(|&(var, before, after)| {
+            let current = self[var].current;
+            if PROGRESS_COUNTERS.contains(&var) {
+                current < after
+            } else {
+                // Any bit gained in that poll being gone again is enough
+                (after & !before) & !current != 0
+            }
+        })
+    }
+
+    /// Which frame rate to use: the user's choice, then what the frame
+    /// counter showed, then the cartridge header
+    fn region(&self) -> Region {
+        self.forced_region
+            .or(self.detected_region)
+            .or(self.header_region)
+            .unwrap_or(Region::Ntsc)
+    }
+
+    /// The frame counter only reaches 50 on NTSC, and only wraps from 49 to
+    /// 0 on PAL. This beats the cartridge header, which patched ROMs don't
+    /// always keep accurate.
+    fn detect_region(&mut self) {
+        let frames = &self["igtFrames"];
+        let detected = if frames.current >= 50 {
+            Some(Region::Ntsc)
+        } else if frames.old == 49 && frames.current == 0 {
+            Some(Region::Pal)
+        } else {
+            None
+        };
+        // Memory can hold anything before a save is loaded, so the latest
+        // evidence wins
+        if let Some(region) = detected {
+            if self.detected_region != detected {
+                println!("Detected {:?} from the in-game frame counter", region);
+                self.detected_region = detected;
+            }
         }
     }
 
-    fn update(&mut self) {
+    fn update_watchers(&mut self) {
         for watcher in self.vars.iter_mut() {
             if self.do_extra_update {
                 watcher.1.update_value(&self.data);
@@ -1724,94 +1633,119 @@ impl SNESState {
             watcher.1.update_value(&self.data);
         }
     }
+}
 
-    pub fn fetch_all(
-        &mut self,
-        client: &mut crate::usb2snes::SyncClient,
-        settings: &Settings,
-    ) -> Result<SNESSummary, Box<dyn Error>> {
-        let start_time = Instant::now();
-        let snes_data = client.get_addresses(&[
-            (0xF5008B, 2),  // Controller 1 Input
-            (0xF5079B, 3),  // ROOM ID + ROOM # for region + Region Number
-            (0xF50998, 1),  // GAME STATE
-            (0xF509A4, 61), // ITEMS
-            (0xF50A28, 1),
-            (0xF50F8C, 66),
-            (0xF5D821, 14),
-            (0xF5D870, 20),
-        ])?;
-        // TODO: refactor this
-        for i in 0..2 {
-            self.data[0x008b + i] = snes_data[0][i];
-        }
-        for i in 0..3 {
-            self.data[0x079b + i] = snes_data[1][i];
-        }
-        self.data[0x0998] = snes_data[2][0];
-        for i in 0..61 {
-            self.data[0x09a4 + i] = snes_data[3][i];
-        }
-        self.data[0x0a28] = snes_data[4][0];
-        for i in 0..66 {
-            self.data[0x0f8c + i] = snes_data[5][i];
-        }
-        for i in 0..14 {
-            self.data[0xd821 + i] = snes_data[6][i];
-        }
-        for i in 0..20 {
-            self.data[0xd870 + i] = snes_data[7][i];
-        }
-        self.update();
-        let start = self.start();
-        let reset = self.reset();
-        let split = split(settings, self);
-        let elapsed = start_time.elapsed().as_millis();
-        if self.latency_samples.len() == NUM_LATENCY_SAMPLES {
-            self.latency_samples.pop_front();
-        }
-        self.latency_samples.push_back(elapsed);
-        let average_latency: f32 =
-            self.latency_samples.iter().sum::<u128>() as f32 / self.latency_samples.len() as f32;
-        let mut s = 0;
-        for x in self.latency_samples.iter() {
-            let y = *x as i128;
-            let avg = average_latency as i128;
-            let diff = y - avg;
-            s += diff * diff;
+impl AutoSplitter for SNESState {
+    fn read_plan(&self) -> Vec<(u32, usize)> {
+        self.plan.clone()
+    }
+
+    fn update(&mut self, snes_data: &[Vec<u8>]) {
+        store_reads(&self.plan, snes_data, &mut self.data);
+        self.update_watchers();
+        self.detect_region();
+        if self.entered("gameState", gameStateEnum["fileSelectMenus"])
+            || (self["roomID"].old != 0 && self["roomID"].current == 0)
+        {
+            // A new game, or a soft reset: nothing from before can be undone
+            self.fired.clear();
+            self.reloading = false;
+        } else if self.entered("gameState", gameStateEnum["samusOutOfHealth"])
+            || self.entered("gameState", gameStateEnum["loadingGameData"])
+        {
+            self.reloading = true;
         }
-        let stddev = (s as f32 / (self.latency_samples.len() as f32 - 1.0)).sqrt();
-        Ok(SNESSummary {
-            latency_average: average_latency,
-            latency_stddev: stddev,
-            start,
-            reset,
-            split,
-        })
     }
 
-    pub fn start(&self) -> bool {
-        let normal_start = self["gameState"].old == 2 && self["gameState"].current == 0x1f;
+    fn start(&self, settings: &Settings) -> bool {
+        let game_state = |name: &str| gameStateEnum[name];
+        let normal_start = settings.get("normalStart")
+            && self["gameState"].old == game_state("optionsMenu")
+            && self["gameState"].current == game_state("newGame");
         // Allow for a cutscene start, even though it's not normally used for speedrunning
-        let cutscene_ended = self["gameState"].old == 0x1E && self["gameState"].current == 0x1F;
+        let cutscene_ended = settings.get("cutsceneStart")
+            && self["gameState"].old == game_state("introCutscene")
+            && self["gameState"].current == game_state("newGame");
         // Some categories start from Zebes, such as Spore Spawn RTA
-        let zebes_start = self["gameState"].old == 5 && self["gameState"].current == 6;
+        let zebes_start = settings.get("zebesStart")
+            && self["gameState"].old == game_state("fileSelectMap")
+            && self["gameState"].current == game_state("loadingGameData");
         normal_start || cutscene_ended || zebes_start
     }
 
-    pub fn reset(&self) -> bool {
-        self["roomID"].old != 0 && self["roomID"].current == 0
+    fn reset(&self, settings: &Settings) -> bool {
+        let soft_reset =
+            settings.get("softReset") && self["roomID"].old != 0 && self["roomID"].current == 0;
+        let file_select = settings.get("fileSelectReset")
+            && self.entered("gameState", gameStateEnum["fileSelectMenus"]);
+        let death = settings.get("deathReset")
+            && self.entered("gameState", gameStateEnum["samusOutOfHealth"]);
+        soft_reset || file_select || death
     }
 
-    pub fn gametime_to_seconds(&self) -> TimeSpan {
-        let hours = Duration::hours(self.vars.get("igtHours").unwrap().current.into());
+    fn gametime_to_seconds(&self) -> Option<TimeSpan> {
+        let hours = Duration::hours(self.vars.get("igtHours").unwrap().current);
 
-        let minutes = Duration::minutes(self.vars.get("igtMinutes").unwrap().current.into());
+        let minutes = Duration::minutes(self.vars.get("igtMinutes").unwrap().current);
 
-        let seconds = Duration::seconds(self.vars.get("igtSeconds").unwrap().current.into());
+        let seconds = Duration::seconds(self.vars.get("igtSeconds").unwrap().current);
 
-        let gametime = hours + minutes + seconds;
-        TimeSpan::from_seconds(gametime.as_seconds_f64())
+        let frames = Duration::seconds_f64(
+            self.vars.get("igtFrames").unwrap().current as f64
+                / self.region().frames_per_second() as f64,
+        );
+
+        let gametime = hours + minutes + seconds + frames;
+        Some(TimeSpan::from_seconds(gametime.as_seconds_f64()))
+    }
+
+    fn set_region(&mut self, user: Option<Region>, header: Option<Region>) {
+        self.forced_region = user;
+        self.header_region = header;
+    }
+
+    fn split(&mut self, settings: &Settings) -> Vec<SplitReason> {
+        let reasons = split(settings, self);
+        if !reasons.is_empty() {
+            let progress = PROGRESS_COUNTERS
+                .iter()
+                .chain(PROGRESS_FLAGS)
+                .map(|&var| (var, self[var].old, self[var].current))
+                .filter(|&(_, before, after)| before != after)
+                .collect();
+            self.fired.push(Fired {
+                reasons: reasons.clone(),
+                progress,
+            });
+        }
+        reasons
+    }
+
+    /// Once gameplay resumes after a death or a reload, everything from
+    /// the first split whose progress was lost onwards is reverted, since
+    /// the rest came after it
+    fn reverted(&mut self, settings: &Settings) -> Vec<SplitReason> {
+        if !self.reloading || self["gameState"].current != gameStateEnum["normalGameplay"] {
+            return vec![];
+        }
+        self.reloading = false;
+        // Everything is kept, so that turning this on mid-run still undoes
+        // what was lost before
+        if !settings.get("undoOnReload") {
+            return vec![];
+        }
+        let Some(first) = self.fired.iter().position(|f| self.lost(&f.progress)) else {
+            return vec![];
+        };
+        self.fired
+            .split_off(first)
+            .into_iter()
+            .flat_map(|f| f.reasons)
+            .collect()
+    }
+
+    fn default_settings(&self) -> Settings {
+        default_settings()
     }
 }
 
@@ -1828,3 +1762,6 @@ impl Index<&str> for SNESState {
         self.vars.get(var).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1212 @@
+//! Synthetic memory sequences run through `SNESState`, checking exactly
+//! which split conditions fire for each. Memory is fed in through
+//! `read_plan` and `update` the same way the polling thread does it.
+
+use super::*;
+use crate::autosplitters::{take_reverted, WRAM_SIZE, WRAM_START};
+use std::collections::BTreeSet;
+
+struct Harness {
+    snes: SNESState,
+    memory: Vec<u8>,
+    settings: Settings,
+}
+
+impl Harness {
+    fn new(settings: Settings) -> Harness {
+        let mut harness = Harness {
+            snes: SNESState::new(),
+            memory: vec![0; WRAM_SIZE],
+            settings,
+        };
+        harness.poll();
+        harness
+    }
+
+    fn write(&mut self, var: &str, value: i64) {
+        let watcher = &self.snes[var];
+        let (address, size) = (watcher.address as usize, watcher.width.size());
+        self.memory[address..address + size].copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+
+    /// The split conditions for the current memory, in the order the
+    /// autosplitter reports them
+    fn poll_reasons(&mut self) -> Vec<SplitReason> {
+        let data: Vec<Vec<u8>> = self
+            .snes
+            .read_plan()
+            .iter()
+            .map(|&(address, size)| {
+                let offset = (address - WRAM_START) as usize;
+                self.memory[offset..offset + size].to_vec()
+            })
+            .collect();
+        self.snes.update(&data);
+        self.snes.split(&self.settings)
+    }
+
+    fn poll(&mut self) -> BTreeSet<String> {
+        self.poll_reasons()
+            .iter()
+            .map(|reason| reason.to_string())
+            .collect()
+    }
+
+    /// Write the values and poll until they're both old and current, so
+    /// that only what changes afterwards counts
+    fn settle(&mut self, values: &[(&str, i64)]) {
+        for &(var, value) in values {
+            self.write(var, value);
+        }
+        self.poll();
+        self.poll();
+    }
+
+    fn step(&mut self, values: &[(&str, i64)]) -> BTreeSet<String> {
+        for &(var, value) in values {
+            self.write(var, value);
+        }
+        self.poll()
+    }
+}
+
+/// The default settings with every setting turned on, so that anything
+/// that could fire on a change does
+fn everything() -> Settings {
+    let mut settings = default_settings();
+    for key in settings.keys() {
+        settings.set(&key, true);
+    }
+    settings
+}
+
+fn set(names: &[&str]) -> BTreeSet<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// The conditions that fire going from `before` to `after`, with every
+/// setting enabled
+fn splits(before: &[(&str, i64)], after: &[(&str, i64)]) -> BTreeSet<String> {
+    let mut harness = Harness::new(everything());
+    harness.settle(before);
+    harness.step(after)
+}
+
+fn room(name: &str) -> i64 {
+    roomIDEnum[name]
+}
+
+// (setting, room, item watcher, bit)
+const ITEM_PICKUPS: &[(&str, &str, &str, i64)] = &[
+    ("oceanBottomMissiles", "westOcean", "crateriaItems", 0x02),
+    ("oceanTopMissiles", "westOcean", "crateriaItems", 0x04),
+    ("oceanMiddleMissiles", "westOcean", "crateriaItems", 0x08),
+    ("moatMissiles", "crateriaMoat", "crateriaItems", 0x10),
+    ("oldTourianMissiles", "pitRoom", "crateriaItems", 0x40),
+    (
+        "gauntletRightMissiles",
+        "greenPirateShaft",
+        "brinteriaItems",
+        0x02,
+    ),
+    (
+        "gauntletLeftMissiles",
+        "greenPirateShaft",
+        "brinteriaItems",
+        0x04,
+    ),
+    ("dentalPlan", "theFinalMissile", "brinteriaItems", 0x10),
+    (
+        "earlySuperBridgeMissiles",
+        "earlySupers",
+        "brinteriaItems",
+        0x80,
+    ),
+    (
+        "greenBrinstarReserveMissiles",
+        "brinstarReserveRoom",
+        "brinstarItems2",
+        0x08,
+    ),
+    (
+        "greenBrinstarExtraReserveMissiles",
+        "brinstarReserveRoom",
+        "brinstarItems2",
+        0x04,
+    ),
+    ("bigPinkTopMissiles", "bigPink", "brinstarItems2", 0x20),
+    ("chargeMissiles", "bigPink", "brinstarItems2", 0x40),
+    ("greenHillsMissiles", "greenHills", "brinstarItems3", 0x02),
+    (
+        "blueBrinstarETankMissiles",
+        "blueBrinstarETankRoom",
+        "brinstarItems3",
+        0x10,
+    ),
+    ("alphaMissiles", "alphaMissileRoom", "brinstarItems4", 0x04),
+    ("billyMaysMissiles", "billyMays", "brinstarItems4", 0x10),
+    (
+        "butWaitTheresMoreMissiles",
+        "billyMays",
+        "brinstarItems4",
+        0x20,
+    ),
+    (
+        "redBrinstarMissiles",
+        "alphaPowerBombsRoom",
+        "brinstarItems5",
+        0x02,
+    ),
+    (
+        "warehouseMissiles",
+        "warehouseKiHunters",
+        "brinstarItems5",
+        0x10,
+    ),
+    ("cathedralMissiles", "cathedral", "norfairItems1", 0x02),
+    (
+        "crumbleShaftMissiles",
+        "crumbleShaft",
+        "norfairItems1",
+        0x08,
+    ),
+    (
+        "crocomireEscapeMissiles",
+        "crocomireEscape",
+        "norfairItems1",
+        0x40,
+    ),
+    ("hiJumpMissiles", "hiJumpShaft", "norfairItems1", 0x80),
+    ("postCrocomireMissiles", "cosineRoom", "norfairItems2", 0x04),
+    ("grappleMissiles", "preGrapple", "norfairItems2", 0x08),
+    (
+        "norfairReserveMissiles",
+        "norfairReserveRoom",
+        "norfairItems2",
+        0x40,
+    ),
+    (
+        "greenBubblesMissiles",
+        "greenBubblesRoom",
+        "norfairItems2",
+        0x80,
+    ),
+    (
+        "bubbleMountainMissiles",
+        "bubbleMountain",
+        "norfairItems3",
+        0x01,
+    ),
+    (
+        "speedBoostMissiles",
+        "speedBoostHall",
+        "norfairItems3",
+        0x02,
+    ),
+    ("waveMissiles", "doubleChamber", "norfairItems3", 0x08),
+    ("goldTorizoMissiles", "goldenTorizo", "norfairItems3", 0x40),
+    ("mickeyMouseMissiles", "mickeyMouse", "norfairItems4", 0x02),
+    (
+        "lowerNorfairSpringMazeMissiles",
+        "lowerNorfairSpringMaze",
+        "norfairItems4",
+        0x04,
+    ),
+    (
+        "threeMusketeersMissiles",
+        "threeMusketeers",
+        "norfairItems4",
+        0x20,
+    ),
+    (
+        "wreckedShipMainShaftMissiles",
+        "wreckedShipMainShaft",
+        "wreckedShipItems",
+        0x01,
+    ),
+    ("bowlingMissiles", "bowling", "wreckedShipItems", 0x04),
+    (
+        "atticMissiles",
+        "atticWorkerRobotRoom",
+        "wreckedShipItems",
+        0x08,
+    ),
+    ("mainStreetMissiles", "mainStreet", "maridiaItems1", 0x01),
+    ("mamaTurtleMissiles", "mamaTurtle", "maridiaItems1", 0x08),
+    (
+        "wateringHoleMissiles",
+        "wateringHole",
+        "maridiaItems1",
+        0x20,
+    ),
+    ("beachMissiles", "beach", "maridiaItems1", 0x40),
+    ("leftSandPitMissiles", "leftSandPit", "maridiaItems2", 0x01),
+    (
+        "rightSandPitMissiles",
+        "rightSandPit",
+        "maridiaItems2",
+        0x04,
+    ),
+    ("aqueductMissiles", "aqueduct", "maridiaItems2", 0x10),
+    ("preDraygonMissiles", "precious", "maridiaItems2", 0x80),
+    ("climbSupers", "crateriaSupersRoom", "brinteriaItems", 0x08),
+    (
+        "sporeSpawnSupers",
+        "sporeSpawnSuper",
+        "brinteriaItems",
+        0x40,
+    ),
+    ("earlySupers", "earlySupers", "brinstarItems2", 0x01),
+    ("etecoonSupers", "etecoonSuperRoom", "brinstarItems3", 0x80),
+    ("goldTorizoSupers", "goldenTorizo", "norfairItems3", 0x80),
+    (
+        "wreckedShipLeftSupers",
+        "wreckedShipLeftSuperRoom",
+        "wreckedShipItems",
+        0x20,
+    ),
+    (
+        "wreckedShipRightSupers",
+        "wreckedShipRightSuperRoom",
+        "wreckedShipItems",
+        0x40,
+    ),
+    ("crabSupers", "mainStreet", "maridiaItems1", 0x02),
+    ("wateringHoleSupers", "wateringHole", "maridiaItems1", 0x10),
+    ("aqueductSupers", "aqueduct", "maridiaItems2", 0x20),
+    (
+        "landingSiteBombs",
+        "crateriaPowerBombRoom",
+        "crateriaItems",
+        0x01,
+    ),
+    (
+        "etecoonBombs",
+        "greenBrinstarMainShaft",
+        "brinteriaItems",
+        0x20,
+    ),
+    (
+        "pinkBrinstarBombs",
+        "pinkBrinstarPowerBombRoom",
+        "brinstarItems3",
+        0x01,
+    ),
+    ("blueBrinstarBombs", "morphBall", "brinstarItems3", 0x08),
+    ("alphaBombs", "alphaPowerBombsRoom", "brinstarItems5", 0x01),
+    ("betaBombs", "betaPowerBombRoom", "brinstarItems4", 0x80),
+    (
+        "crocomireBombs",
+        "postCrocomirePowerBombRoom",
+        "norfairItems2",
+        0x02,
+    ),
+    (
+        "lowerNorfairEscapeBombs",
+        "lowerNorfairEscapePowerBombRoom",
+        "norfairItems4",
+        0x08,
+    ),
+    ("shameBombs", "wasteland", "norfairItems4", 0x10),
+    ("rightSandPitBombs", "rightSandPit", "maridiaItems2", 0x08),
+    ("gauntletETank", "gauntletETankRoom", "crateriaItems", 0x20),
+    ("terminatorETank", "terminator", "brinteriaItems", 0x01),
+    (
+        "ceilingETank",
+        "blueBrinstarETankRoom",
+        "brinstarItems3",
+        0x20,
+    ),
+    ("etecoonsETank", "etecoonETankRoom", "brinstarItems3", 0x40),
+    ("waterwayETank", "waterway", "brinstarItems4", 0x02),
+    ("waveGateETank", "hopperETankRoom", "brinstarItems4", 0x08),
+    ("kraidETank", "warehouseETankRoom", "brinstarItems5", 0x08),
+    ("crocomireETank", "crocomire", "norfairItems1", 0x10),
+    ("hiJumpETank", "hiJumpShaft", "norfairItems2", 0x01),
+    ("ridleyETank", "ridleyETankRoom", "norfairItems4", 0x40),
+    (
+        "firefleaETank",
+        "lowerNorfairFireflea",
+        "norfairItems5",
+        0x01,
+    ),
+    (
+        "wreckedShipETank",
+        "wreckedShipETankRoom",
+        "wreckedShipItems",
+        0x10,
+    ),
+    ("tatoriETank", "mamaTurtle", "maridiaItems1", 0x04),
+    ("botwoonETank", "botwoonETankRoom", "maridiaItems3", 0x01),
+    (
+        "brinstarReserve",
+        "brinstarReserveRoom",
+        "brinstarItems2",
+        0x02,
+    ),
+    (
+        "norfairReserve",
+        "norfairReserveRoom",
+        "norfairItems2",
+        0x20,
+    ),
+    ("wreckedShipReserve", "bowling", "wreckedShipItems", 0x02),
+    ("maridiaReserve", "leftSandPit", "maridiaItems2", 0x02),
+];
+
+// (setting, room, unlock watcher, bit)
+const UNLOCKS: &[(&str, &str, &str, i64)] = &[
+    ("variaSuit", "varia", "unlockedEquips2", 0x01),
+    ("springBall", "springBall", "unlockedEquips2", 0x02),
+    ("morphBall", "morphBall", "unlockedEquips2", 0x04),
+    ("screwAttack", "screwAttack", "unlockedEquips2", 0x08),
+    ("gravSuit", "gravity", "unlockedEquips2", 0x20),
+    ("hiJump", "hiJump", "unlockedEquips", 0x01),
+    ("spaceJump", "spaceJump", "unlockedEquips", 0x02),
+    ("bomb", "bombTorizo", "unlockedEquips", 0x10),
+    ("speedBooster", "speedBooster", "unlockedEquips", 0x20),
+    ("grapple", "grapple", "unlockedEquips", 0x40),
+    ("xray", "xRay", "unlockedEquips", 0x80),
+    ("wave", "waveBeam", "unlockedBeams", 0x01),
+    ("ice", "iceBeam", "unlockedBeams", 0x02),
+    ("spazer", "spazer", "unlockedBeams", 0x04),
+    ("plasma", "plasmaBeam", "unlockedBeams", 0x08),
+    ("chargeBeam", "bigPink", "unlockedCharge", 0x10),
+];
+
+// (setting, room, boss watcher, bit)
+const BOSSES: &[(&str, &str, &str, i64)] = &[
+    ("ceresRidley", "ceresRidley", "ceresBosses", 0x01),
+    ("bombTorizo", "bombTorizo", "crateriaBosses", 0x04),
+    ("sporeSpawn", "sporeSpawn", "brinstarBosses", 0x02),
+    ("crocomire", "crocomire", "norfairBosses", 0x02),
+    ("botwoon", "botwoon", "maridiaBosses", 0x02),
+    ("goldenTorizo", "goldenTorizo", "norfairBosses", 0x04),
+    ("kraid", "kraid", "brinstarBosses", 0x01),
+    ("phantoon", "phantoon", "wreckedShipBosses", 0x01),
+    ("draygon", "draygon", "maridiaBosses", 0x01),
+    ("ridley", "ridley", "norfairBosses", 0x01),
+];
+
+// (setting, from room, to room)
+const TRANSITIONS: &[(&str, &str, &str)] = &[
+    ("miniBossRooms", "flatRoom", "ceresRidley"),
+    ("miniBossRooms", "sporeSpawnKeyhunter", "sporeSpawn"),
+    ("miniBossRooms", "crocomireSpeedway", "crocomire"),
+    ("miniBossRooms", "botwoonHallway", "botwoon"),
+    ("miniBossRooms", "acidStatue", "goldenTorizo"),
+    ("miniBossRooms", "screwAttack", "goldenTorizo"),
+    ("bossRooms", "kraidEyeDoor", "kraid"),
+    ("bossRooms", "basement", "phantoon"),
+    ("bossRooms", "precious", "draygon"),
+    ("bossRooms", "lowerNorfairFarming", "ridley"),
+    ("bossRooms", "rinkaShaft", "motherBrain"),
+    ("elevatorTransitions", "elevatorToMorphBall", "morphBall"),
+    ("elevatorTransitions", "morphBall", "elevatorToMorphBall"),
+    (
+        "elevatorTransitions",
+        "elevatorToGreenBrinstar",
+        "greenBrinstarMainShaft",
+    ),
+    (
+        "elevatorTransitions",
+        "greenBrinstarMainShaft",
+        "elevatorToGreenBrinstar",
+    ),
+    ("elevatorTransitions", "warehouseEntrance", "businessCenter"),
+    ("elevatorTransitions", "businessCenter", "warehouseEntrance"),
+    (
+        "elevatorTransitions",
+        "elevatorToCaterpillar",
+        "caterpillar",
+    ),
+    (
+        "elevatorTransitions",
+        "caterpillar",
+        "elevatorToCaterpillar",
+    ),
+    (
+        "elevatorTransitions",
+        "elevatorToMaridia",
+        "maridiaElevator",
+    ),
+    (
+        "elevatorTransitions",
+        "maridiaElevator",
+        "elevatorToMaridia",
+    ),
+    ("wreckedShipEntrance", "westOcean", "wreckedShipEntrance"),
+    ("redTowerMiddleEntrance", "noobBridge", "redTower"),
+    ("redTowerBottomEntrance", "bat", "redTower"),
+    ("kraidsLair", "warehouseEntrance", "warehouseZeela"),
+    ("risingTideEntrance", "cathedral", "risingTide"),
+    ("atticExit", "attic", "westOcean"),
+    ("cacExit", "westCactusAlley", "butterflyRoom"),
+    ("toilet", "plasmaSpark", "toiletBowl"),
+    ("toilet", "oasis", "toiletBowl"),
+    ("kronicBoost", "magdolliteTunnel", "kronicBoost"),
+    ("kronicBoost", "spikyAcidSnakes", "kronicBoost"),
+    ("kronicBoost", "volcano", "kronicBoost"),
+    ("lowerNorfairEntrance", "lowerNorfairElevator", "mainHall"),
+    ("writg", "pillars", "writg"),
+    ("redKiShaft", "amphitheatre", "redKiShaft"),
+    ("redKiShaft", "wasteland", "redKiShaft"),
+    ("metalPirates", "wasteland", "metalPirates"),
+    (
+        "lowerNorfairSpringMaze",
+        "lowerNorfairFireflea",
+        "lowerNorfairSpringMaze",
+    ),
+    ("lowerNorfairExit", "threeMusketeers", "singleChamber"),
+    ("tourianEntrance", "statues", "tourianElevator"),
+    ("metroids", "metroidOne", "metroidTwo"),
+    ("metroids", "metroidTwo", "metroidThree"),
+    ("metroids", "metroidThree", "metroidFour"),
+    ("metroids", "metroidFour", "tourianHopper"),
+    ("babyMetroidRoom", "dustTorizo", "bigBoy"),
+    ("escapeClimb", "tourianEscape4", "climb"),
+];
+
+#[test]
+fn item_pickups() {
+    for &(setting, name, var, bit) in ITEM_PICKUPS {
+        assert_eq!(
+            splits(&[("roomID", room(name)), (var, 0)], &[(var, bit)]),
+            set(&[setting]),
+            "{}",
+            setting
+        );
+    }
+}
+
+#[test]
+fn item_pickups_need_their_room() {
+    for &(setting, _, var, bit) in ITEM_PICKUPS {
+        assert_eq!(
+            splits(&[("roomID", room("landingSite")), (var, 0)], &[(var, bit)]),
+            set(&[]),
+            "{}",
+            setting
+        );
+    }
+}
+
+#[test]
+fn item_pickups_only_count_their_own_bit() {
+    // Picking up the Gauntlet's right missiles with the left ones already
+    // collected
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("greenPirateShaft")),
+                ("brinteriaItems", 0x04)
+            ],
+            &[("brinteriaItems", 0x06)]
+        ),
+        set(&["gauntletRightMissiles"])
+    );
+}
+
+#[test]
+fn pickups_between_polls_all_count() {
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("greenPirateShaft")),
+                ("brinteriaItems", 0),
+                ("maxMissiles", 10)
+            ],
+            &[("brinteriaItems", 0x06), ("maxMissiles", 20)]
+        ),
+        set(&[
+            "gauntletRightMissiles",
+            "gauntletLeftMissiles",
+            "allMissiles"
+        ])
+    );
+    assert_eq!(
+        splits(
+            &[("roomID", room("landingSite")), ("maxEnergy", 99)],
+            &[("maxEnergy", 299)]
+        ),
+        set(&["firstETank", "allETanks"])
+    );
+}
+
+#[test]
+fn pickups_on_the_way_out_of_a_room_count() {
+    assert_eq!(
+        splits(
+            &[("roomID", room("westOcean")), ("crateriaItems", 0)],
+            &[
+                ("roomID", room("wreckedShipEntrance")),
+                ("crateriaItems", 0x02)
+            ]
+        ),
+        set(&["oceanBottomMissiles", "wreckedShipEntrance"])
+    );
+}
+
+#[test]
+fn short_lived_states_dont_hide_transitions() {
+    // Whatever the game state was just before the cutscene
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("landingSite")),
+                ("gameState", gameStateEnum["normalGameplay"])
+            ],
+            &[("gameState", gameStateEnum["endCutscene"])]
+        ),
+        set(&["igtFinish"])
+    );
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("motherBrain")),
+                ("gameState", gameStateEnum["normalGameplay"]),
+                ("motherBrainHP", 100)
+            ],
+            &[("motherBrainHP", motherBrainMaxHPEnum["phase2"])]
+        ),
+        set(&["mb1"])
+    );
+}
+
+#[test]
+fn loading_a_save_isnt_a_pickup() {
+    assert_eq!(
+        splits(
+            &[("roomID", 0), ("maxMissiles", 0), ("maxEnergy", 99)],
+            &[
+                ("roomID", room("landingSite")),
+                ("maxMissiles", 45),
+                ("maxEnergy", 599)
+            ]
+        ),
+        set(&[])
+    );
+}
+
+#[test]
+fn unlocks() {
+    for &(setting, name, var, bit) in UNLOCKS {
+        assert_eq!(
+            splits(&[("roomID", room(name)), (var, 0)], &[(var, bit)]),
+            set(&[setting]),
+            "{}",
+            setting
+        );
+        // Other unlocks already being there makes no difference
+        assert_eq!(
+            splits(
+                &[("roomID", room(name)), (var, !bit & 0xFF)],
+                &[(var, 0xFF)]
+            ),
+            set(&[setting]),
+            "{} with everything else unlocked",
+            setting
+        );
+        assert_eq!(
+            splits(&[("roomID", room("landingSite")), (var, 0)], &[(var, bit)]),
+            set(&[]),
+            "{} outside of its room",
+            setting
+        );
+    }
+}
+
+// (counter, before, after, settings that split)
+const AMMO: &[(&str, i64, i64, &[&str])] = &[
+    ("maxMissiles", 0, 5, &["firstMissile", "allMissiles"]),
+    ("maxMissiles", 5, 10, &["allMissiles"]),
+    ("maxSupers", 0, 5, &["firstSuper", "allSupers"]),
+    ("maxSupers", 5, 10, &["allSupers"]),
+    ("maxPowerBombs", 0, 5, &["firstPowerBomb", "allPowerBombs"]),
+    ("maxPowerBombs", 5, 10, &["allPowerBombs"]),
+    ("maxEnergy", 99, 199, &["firstETank", "allETanks"]),
+    ("maxEnergy", 199, 299, &["allETanks"]),
+    ("maxEnergy", 1399, 1499, &["allETanks"]),
+    ("maxReserve", 0, 100, &["reserveTanks"]),
+    // Losing ammo to a reload isn't a pickup
+    ("maxMissiles", 10, 5, &[]),
+];
+
+#[test]
+fn ammo_and_tank_counts() {
+    for &(var, before, after, fired) in AMMO {
+        assert_eq!(
+            splits(
+                &[("roomID", room("landingSite")), (var, before)],
+                &[(var, after)]
+            ),
+            set(fired),
+            "{} from {} to {}",
+            var,
+            before,
+            after
+        );
+    }
+}
+
+#[test]
+fn room_transitions() {
+    for &(setting, from, to) in TRANSITIONS {
+        assert_eq!(
+            splits(&[("roomID", room(from))], &[("roomID", room(to))]),
+            set(&[setting]),
+            "{} from {} to {}",
+            setting,
+            from,
+            to
+        );
+    }
+}
+
+#[test]
+fn room_transitions_survive_a_poll_in_between() {
+    // Not a room, like a poll that caught the door halfway
+    let between = 0x1234;
+    for &(setting, from, to) in TRANSITIONS {
+        let mut harness = Harness::new(everything());
+        harness.settle(&[("roomID", room(from))]);
+        harness.step(&[("roomID", between)]);
+        assert_eq!(
+            harness.step(&[("roomID", room(to))]),
+            set(&[setting]),
+            "{} from {} to {}",
+            setting,
+            from,
+            to
+        );
+    }
+}
+
+#[test]
+fn room_transitions_dont_reach_back_forever() {
+    let mut harness = Harness::new(everything());
+    harness.settle(&[("roomID", room("kraidEyeDoor"))]);
+    for _ in 0..ROOM_HISTORY {
+        harness.step(&[("roomID", room("warehouseZeela"))]);
+    }
+    assert_eq!(harness.step(&[("roomID", room("kraid"))]), set(&[]));
+}
+
+#[test]
+fn golden_four_needs_every_boss() {
+    let bosses = [
+        ("brinstarBosses", 0x01),
+        ("wreckedShipBosses", 0x01),
+        ("maridiaBosses", 0x01),
+        ("norfairBosses", 0x01),
+    ];
+    let mut before = vec![("roomID", room("statuesHallway"))];
+    before.extend(bosses);
+    assert_eq!(
+        splits(&before, &[("roomID", room("statues"))]),
+        set(&["goldenFour"])
+    );
+    for missing in bosses {
+        let before: Vec<_> = before
+            .iter()
+            .map(|&(var, value)| (var, if var == missing.0 { 0 } else { value }))
+            .collect();
+        assert_eq!(
+            splits(&before, &[("roomID", room("statues"))]),
+            set(&[]),
+            "without {}",
+            missing.0
+        );
+    }
+}
+
+#[test]
+fn ceres_escape_needs_the_elevator() {
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("flatRoom")),
+                ("gameState", gameStateEnum["normalGameplay"])
+            ],
+            &[("gameState", gameStateEnum["startOfCeresCutscene"])]
+        ),
+        set(&[])
+    );
+}
+
+#[test]
+fn bosses() {
+    for &(setting, name, var, bit) in BOSSES {
+        assert_eq!(
+            splits(&[("roomID", room(name)), (var, 0)], &[(var, bit)]),
+            set(&[setting]),
+            "{}",
+            setting
+        );
+        // The flags get set again when a save is loaded, which happens
+        // somewhere else
+        assert_eq!(
+            splits(&[("roomID", room("landingSite")), (var, 0)], &[(var, bit)]),
+            set(&[]),
+            "{} outside of its room",
+            setting
+        );
+    }
+}
+
+type Value = (&'static str, i64);
+type Values = Vec<Value>;
+
+/// (setting, before, after) for the splits that are one of a kind
+fn events() -> Vec<(&'static str, Values, Values)> {
+    let mother_brain = vec![
+        ("roomID", room("motherBrain")),
+        ("gameState", gameStateEnum["normalGameplay"]),
+        ("motherBrainHP", 0),
+    ];
+    vec![
+        (
+            "goldenFour",
+            vec![
+                ("roomID", room("statuesHallway")),
+                ("brinstarBosses", 0x01),
+                ("wreckedShipBosses", 0x01),
+                ("maridiaBosses", 0x01),
+                ("norfairBosses", 0x01),
+            ],
+            vec![("roomID", room("statues"))],
+        ),
+        (
+            "ceresEscape",
+            vec![
+                ("roomID", room("ceresElevator")),
+                ("gameState", gameStateEnum["normalGameplay"]),
+            ],
+            vec![("gameState", gameStateEnum["startOfCeresCutscene"])],
+        ),
+        (
+            "tubeBroken",
+            vec![("roomID", room("glassTunnel")), ("eventFlags", 0x40)],
+            vec![("eventFlags", 0x48)],
+        ),
+        (
+            "mb1",
+            mother_brain.clone(),
+            vec![("motherBrainHP", motherBrainMaxHPEnum["phase2"])],
+        ),
+        (
+            "mb2",
+            mother_brain.clone(),
+            vec![("motherBrainHP", motherBrainMaxHPEnum["phase3"])],
+        ),
+        ("mb3", mother_brain, vec![("tourianBosses", 0x02)]),
+        (
+            "rtaFinish",
+            vec![("eventFlags", 0x40), ("shipAI", 0)],
+            vec![("shipAI", 0xaa4f)],
+        ),
+        (
+            "igtFinish",
+            vec![
+                ("roomID", room("landingSite")),
+                ("gameState", gameStateEnum["preEndCutscene"]),
+            ],
+            vec![("gameState", gameStateEnum["endCutscene"])],
+        ),
+    ]
+}
+
+#[test]
+fn one_of_a_kind_events() {
+    for (setting, before, after) in events() {
+        assert_eq!(splits(&before, &after), set(&[setting]), "{}", setting);
+    }
+}
+
+#[test]
+fn mother_brain_phases_need_a_phase_change() {
+    // Taking damage isn't a phase change
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("motherBrain")),
+                ("gameState", gameStateEnum["normalGameplay"]),
+                ("motherBrainHP", 18000)
+            ],
+            &[("motherBrainHP", 17000)]
+        ),
+        set(&[])
+    );
+    // Phase changes during a door transition don't count
+    assert_eq!(
+        splits(
+            &[
+                ("roomID", room("motherBrain")),
+                ("gameState", gameStateEnum["doorTransition"]),
+                ("motherBrainHP", 0)
+            ],
+            &[("motherBrainHP", motherBrainMaxHPEnum["phase2"])]
+        ),
+        set(&[])
+    );
+}
+
+#[test]
+fn rta_finish_needs_the_planet_ablaze() {
+    // The ship AI gets there before the planet is ablaze, too
+    assert_eq!(
+        splits(&[("eventFlags", 0), ("shipAI", 0)], &[("shipAI", 0xaa4f)]),
+        set(&[])
+    );
+}
+
+/// (setting, before, the pickup, what the pickup splits) for the finishes
+/// that wait for the game to unfreeze after a pickup
+fn pickup_finishes() -> Vec<(&'static str, Values, Value, &'static [&'static str])> {
+    vec![
+        (
+            "sporeSpawnRTAFinish",
+            vec![
+                ("roomID", room("sporeSpawnSuper")),
+                ("brinstarBosses", 0x02),
+                ("maxSupers", 0),
+            ],
+            ("maxSupers", 5),
+            &["firstSuper", "allSupers"],
+        ),
+        (
+            "hundredMissileRTAFinish",
+            vec![("roomID", room("landingSite")), ("maxMissiles", 95)],
+            ("maxMissiles", 100),
+            &["allMissiles"],
+        ),
+    ]
+}
+
+#[test]
+fn pickup_finishes_wait_for_the_game_to_unfreeze() {
+    for (setting, before, pickup, fired) in pickup_finishes() {
+        let mut harness = Harness::new(everything());
+        harness.settle(&before);
+        assert_eq!(harness.step(&[pickup]), set(fired), "{}", setting);
+        // The text box is up and the game is frozen
+        assert_eq!(harness.step(&[]), set(&[]), "{}", setting);
+        assert_eq!(
+            harness.step(&[("igtFrames", 1)]),
+            set(&[setting]),
+            "{}",
+            setting
+        );
+        assert_eq!(harness.step(&[("igtFrames", 2)]), set(&[]), "{}", setting);
+    }
+}
+
+/// Whether the run starts and resets going from `before` to `after`
+fn start_and_reset(
+    settings: Settings,
+    before: &[(&str, i64)],
+    after: &[(&str, i64)],
+) -> (bool, bool) {
+    let mut harness = Harness::new(settings);
+    harness.settle(before);
+    harness.step(after);
+    (
+        harness.snes.start(&harness.settings),
+        harness.snes.reset(&harness.settings),
+    )
+}
+
+// (setting, game state before, game state after)
+const STARTS: &[(&str, i64, i64)] = &[
+    ("normalStart", 0x02, 0x1F),
+    ("cutsceneStart", 0x1E, 0x1F),
+    ("zebesStart", 0x05, 0x06),
+];
+
+#[test]
+fn starts() {
+    for &(setting, before, after) in STARTS {
+        let before = [("gameState", before)];
+        let after = [("gameState", after)];
+        assert_eq!(
+            start_and_reset(default_settings(), &before, &after),
+            (true, false),
+            "{}",
+            setting
+        );
+        let mut settings = default_settings();
+        settings.set(setting, false);
+        assert_eq!(
+            start_and_reset(settings, &before, &after),
+            (false, false),
+            "{} turned off",
+            setting
+        );
+    }
+    let mut settings = default_settings();
+    settings.set("startConditions", false);
+    assert_eq!(
+        start_and_reset(settings, &[("gameState", 0x02)], &[("gameState", 0x1F)]),
+        (false, false)
+    );
+}
+
+/// (setting, whether it's on by default, before, after)
+fn resets() -> Vec<(&'static str, bool, Values, Values)> {
+    vec![
+        (
+            "softReset",
+            true,
+            vec![("roomID", room("landingSite"))],
+            vec![("roomID", 0)],
+        ),
+        (
+            "fileSelectReset",
+            false,
+            vec![("roomID", room("landingSite")), ("gameState", 0x19)],
+            vec![("gameState", 0x04)],
+        ),
+        (
+            "deathReset",
+            false,
+            vec![("roomID", room("landingSite")), ("gameState", 0x08)],
+            vec![("gameState", 0x13)],
+        ),
+    ]
+}
+
+#[test]
+fn resets_follow_their_settings() {
+    for (setting, default, before, after) in resets() {
+        assert_eq!(
+            start_and_reset(default_settings(), &before, &after),
+            (false, default),
+            "{}",
+            setting
+        );
+        let mut settings = default_settings();
+        settings.set(setting, !default);
+        assert_eq!(
+            start_and_reset(settings, &before, &after),
+            (false, !default),
+            "{} turned {}",
+            setting,
+            if default { "off" } else { "on" }
+        );
+    }
+}
+
+#[test]
+fn settings_saved_before_a_setting_existed_get_its_default() {
+    let mut saved = default_settings();
+    saved.data.remove("zebesStart");
+    saved.data.remove("deathReset");
+    saved.set("firstMissile", true);
+    saved.add_missing(&default_settings());
+    assert!(saved.get("zebesStart"));
+    assert!(!saved.get("deathReset"));
+    assert!(saved.get("firstMissile"));
+}
+
+// The setting that turns on everything `undoing` is for
+const UNDO: &str = "undoOnReload";
+
+fn undoing() -> Harness {
+    let mut settings = default_settings();
+    settings.set(UNDO, true);
+    settings.set("specificMissiles", true);
+    settings.set("crateriaMissiles", true);
+    settings.set("oceanBottomMissiles", true);
+    settings.set("oceanTopMissiles", true);
+    settings.set("moatMissiles", true);
+    let mut harness = Harness::new(settings);
+    harness.settle(&[
+        ("roomID", room("westOcean")),
+        ("gameState", gameStateEnum["normalGameplay"]),
+    ]);
+    harness
+}
+
+/// Die, then continue from the save with `items` as the Crateria item bits
+fn die_and_reload(harness: &mut Harness, items: i64) -> Vec<SplitReason> {
+    harness.step(&[("gameState", gameStateEnum["samusOutOfHealth"])]);
+    assert!(harness.snes.reverted(&harness.settings).is_empty());
+    harness.step(&[
+        ("gameState", gameStateEnum["loadingGameData"]),
+        ("crateriaItems", items),
+    ]);
+    assert!(harness.snes.reverted(&harness.settings).is_empty());
+    harness.step(&[("gameState", gameStateEnum["normalGameplay"])]);
+    harness.snes.reverted(&harness.settings)
+}
+
+#[test]
+fn reloading_reverts_lost_pickups() {
+    let mut harness = undoing();
+    assert_eq!(
+        harness.step(&[("crateriaItems", 0x02)]),
+        set(&["oceanBottomMissiles"])
+    );
+    let reverted = die_and_reload(&mut harness, 0);
+    assert_eq!(reverted, vec![SplitReason::from("oceanBottomMissiles")]);
+    // Picking it up again splits again, and can be reverted again
+    assert_eq!(
+        harness.step(&[("crateriaItems", 0x02)]),
+        set(&["oceanBottomMissiles"])
+    );
+    assert_eq!(die_and_reload(&mut harness, 0).len(), 1);
+}
+
+#[test]
+fn reloading_keeps_saved_pickups() {
+    let mut harness = undoing();
+    harness.step(&[("crateriaItems", 0x02)]);
+    assert!(die_and_reload(&mut harness, 0x02).is_empty());
+}
+
+#[test]
+fn reloading_reverts_everything_after_a_lost_pickup() {
+    let mut harness = undoing();
+    harness.step(&[("crateriaItems", 0x02)]);
+    harness.step(&[("roomID", room("crateriaMoat"))]);
+    harness.step(&[("crateriaItems", 0x12)]);
+    let reverted = die_and_reload(&mut harness, 0x02);
+    assert_eq!(reverted, vec![SplitReason::from("moatMissiles")]);
+}
+
+#[test]
+fn reloading_doesnt_revert_unless_asked_to() {
+    let mut harness = undoing();
+    harness.settings.set(UNDO, false);
+    harness.step(&[("crateriaItems", 0x02)]);
+    assert!(die_and_reload(&mut harness, 0).is_empty());
+}
+
+#[test]
+fn reloading_reverts_a_poll_that_lost_any_of_its_pickups() {
+    let mut harness = undoing();
+    assert_eq!(
+        harness.step(&[("crateriaItems", 0x06)]),
+        set(&["oceanBottomMissiles", "oceanTopMissiles"])
+    );
+    // The save has one of the two, and a poll's splits go together
+    assert_eq!(die_and_reload(&mut harness, 0x02).len(), 2);
+}
+
+#[test]
+fn reloading_reverts_what_was_lost_before_undoing_was_turned_on() {
+    let mut harness = undoing();
+    harness.settings.set(UNDO, false);
+    harness.step(&[("crateriaItems", 0x02)]);
+    assert!(die_and_reload(&mut harness, 0).is_empty());
+    harness.settings.set(UNDO, true);
+    let reverted = die_and_reload(&mut harness, 0);
+    assert_eq!(reverted, vec![SplitReason::from("oceanBottomMissiles")]);
+}
+
+#[test]
+fn reverted_reasons_undo_the_latest_splits() {
+    let split = |reasons: &[&str]| -> Vec<SplitReason> {
+        reasons.iter().map(|r| SplitReason::from(*r)).collect()
+    };
+    let mut reverted = split(&["allMissiles", "moatMissiles", "allMissiles"]);
+    assert!(take_reverted(
+        &mut reverted,
+        &split(&["allMissiles", "moatMissiles"])
+    ));
+    assert!(take_reverted(&mut reverted, &split(&["allMissiles"])));
+    assert!(!take_reverted(&mut reverted, &split(&["allMissiles"])));
+    assert!(reverted.is_empty());
+    let mut reverted = split(&["moatMissiles"]);
+    assert!(!take_reverted(
+        &mut reverted,
+        &split(&["moatMissiles", "bombTorizo"])
+    ));
+    assert_eq!(reverted, split(&["moatMissiles"]));
+}
+
+#[test]
+fn disabled_settings_dont_fire() {
+    let mut settings = default_settings();
+    for key in settings.keys() {
+        settings.set(&key, false);
+    }
+    let mut harness = Harness::new(settings);
+    harness.settle(&[("roomID", room("ridley")), ("maxMissiles", 0)]);
+    assert_eq!(
+        harness.step(&[("norfairBosses", 0x01), ("maxMissiles", 5)]),
+        set(&[])
+    );
+}
+
+#[test]
+fn disabled_parents_disable_their_children() {
+    let mut settings = everything();
+    settings.set("bosses", false);
+    let mut harness = Harness::new(settings);
+    harness.settle(&[("roomID", room("ridley"))]);
+    assert_eq!(harness.step(&[("norfairBosses", 0x01)]), set(&[]));
+}
+
+#[test]
+fn nothing_fires_without_changes() {
+    let mut harness = Harness::new(everything());
+    harness.settle(&[
+        ("roomID", room("motherBrain")),
+        ("gameState", gameStateEnum["normalGameplay"]),
+        ("maxMissiles", 5),
+        ("eventFlags", 0x40),
+        ("shipAI", 0xaa4f),
+    ]);
+    for _ in 0..3 {
+        assert_eq!(harness.step(&[]), set(&[]));
+    }
+}
+
+/// Every setting that isn't just a group of other settings has to be
+/// exercised by one of the tests above
+#[test]
+fn every_setting_is_covered() {
+    let settings = default_settings();
+    let parents: BTreeSet<&str> = settings
+        .data
+        .values()
+        .filter_map(|(_, parent)| parent.as_deref())
+        .collect();
+    let covered: BTreeSet<&str> = ITEM_PICKUPS
+        .iter()
+        .chain(UNLOCKS)
+        .chain(BOSSES)
+        .map(|(setting, ..)| *setting)
+        .chain(TRANSITIONS.iter().map(|(setting, ..)| *setting))
+        .chain(AMMO.iter().flat_map(|(.., fired)| fired.iter().copied()))
+        .chain(events().into_iter().map(|(setting, ..)| setting))
+        .chain(pickup_finishes().into_iter().map(|(setting, ..)| setting))
+        .chain(STARTS.iter().map(|(setting, ..)| *setting))
+        .chain(resets().into_iter().map(|(setting, ..)| setting))
+        .chain([UNDO])
+        .collect();
+    let missing: Vec<String> = settings
+        .keys()
+        .into_iter()
+        .filter(|key| !parents.contains(key.as_str()) && !covered.contains(key.as_str()))
+        .collect();
+    assert!(missing.is_empty(), "No test for {:?}", missing);
+}
+
+#[test]
+fn read_plan_covers_every_watcher_in_one_request() {
+    let snes = SNESState::new();
+    let plan = snes.read_plan();
+    assert!(plan.len() <= crate::usb2snes::MAX_OPERANDS, "{:x?}", plan);
+    for (name, watcher) in snes.vars.iter() {
+        let start = WRAM_START + watcher.address;
+        let end = start + watcher.width.size() as u32;
+        assert!(
+            plan.iter()
+                .any(|&(address, size)| address <= start && end <= address + size as u32),
+            "{} isn't read",
+            name
+        );
+    }
+}
@@ -0,0 +1,527 @@
+//! Autosplitters compiled to WebAssembly, written against the same
+//! interface as LiveSplit's auto splitting runtime.
+//!
+//! The module sees the SNES as a process: `process_attach` succeeds once we
+//! are connected, and `process_read` takes usb2snes addresses. WRAM can also
+//! be read at its bus address in banks $7E and $7F.
+//!
+//! usb2snes is far too slow to answer each read as the module makes it.
+//! Instead, reads are answered from the memory fetched at the start of the
+//! poll, and the ranges the module asked for become the read plan for the
+//! next poll. A read of a range that wasn't fetched fails, which modules
+//! already have to handle, and succeeds from the next poll on.
+//!
+//! Each call into the module gets a budget of fuel, so a module stuck in a
+//! loop traps instead of hanging the polling thread. A module that runs
+//! out is stopped like one that traps any other way.
+//!
+//! Settings registered with `user_settings_add_bool` and
+//! `user_settings_add_title` show up in the settings editor. The module
+//! reads their values when it registers them, so changes take effect the
+//! next time the autosplitter is created (on connect or timer reset).
+
+use super::{plan_ranges, AutoSplitter, Settings, SplitReason, WRAM_START};
+use anyhow::anyhow;
+use livesplit_core::{SharedTimer, TimeSpan, TimerPhase};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Module, Store, Trap, TypedFunc};
+
+// The only process the module can attach to
+const SNES_PROCESS: u64 = 1;
+
+// Roughly how many instructions one call into the module may run: far more
+// than a poll's worth of work, but gone in well under a second
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+/// A compiled module along with the settings it registers
+pub struct WasmModule {
+    name: String,
+    engine: Engine,
+    module: Module,
+    settings: Settings,
+    timer: SharedTimer,
+}
+
+impl WasmModule {
+    pub fn load(path: &Path, timer: SharedTimer) -> Result<WasmModule, Box<dyn Error>> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)?;
+        let name = path
+            .file_stem()
+            .map_or("WebAssembly autosplitter".to_owned(), |s| {
+                s.to_string_lossy().into_owned()
+            });
+        let mut wasm = WasmModule {
+            name,
+            engine,
+            module,
+            settings: Settings::new(),
+            timer,
+        };
+        // Run one update without the SNES or the timer, which is where
+        // modules register their settings.
+        let mut instance = wasm.instantiate(Host::new(None, Settings::new()))?;
+        if let Err(e) = instance.update() {
+            println!("{} failed while registering settings: {}", wasm.name, e);
+        }
+        wasm.settings = instance.store.into_data().registered;
+        wasm.settings.modified_after_creation = false;
+        Ok(wasm)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn default_settings(&self) -> Settings {
+        self.settings.clone()
+    }
+
+    fn instantiate(&self, host: Host) -> Result<Instance, Box<dyn Error>> {
+        let mut linker = Linker::new(&self.engine);
+        link(&mut linker)?;
+        // Anything else we don't provide (WASI, process enumeration, ...)
+        // traps if the module actually calls it.
+        linker.define_unknown_imports_as_traps(&self.module)?;
+        let mut store = Store::new(&self.engine, host);
+        let instance = linker.instantiate(&mut store, &self.module)?;
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            store.set_fuel(FUEL_PER_CALL)?;
+            initialize.call(&mut store, ())?;
+        }
+        let update = instance.get_typed_func::<(), ()>(&mut store, "update")?;
+        Ok(Instance { store, update })
+    }
+}
+
+struct Instance {
+    store: Store<Host>,
+    update: TypedFunc<(), ()>,
+}
+
+impl Instance {
+    fn update(&mut self) -> wasmtime::Result<()> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        self.update
+            .call(&mut self.store, ())
+            .map_err(|e| match e.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => anyhow!("update ran for too long"),
+                _ => e,
+            })
+    }
+}
+
+/// What the host functions can see while the module runs
+struct Host {
+    /// `None` while registering settings, where nothing is connected
+    timer: Option<SharedTimer>,
+    /// The values the module reads back when it registers a setting
+    settings: Settings,
+    registered: Settings,
+    /// The (heading level, key) of the titles enclosing the next setting
+    headings: Vec<(u32, Arc<str>)>,
+    memory: Vec<(u32, Vec<u8>)>,
+    requested: Vec<(u32, usize)>,
+    start: bool,
+    reset: bool,
+    /// How many times the module split during this update
+    splits: usize,
+}
+
+impl Host {
+    fn new(timer: Option<SharedTimer>, settings: Settings) -> Host {
+        Host {
+            timer,
+            settings,
+            registered: Settings::new(),
+            headings: vec![],
+            memory: vec![],
+            requested: vec![],
+            start: false,
+            reset: false,
+            splits: 0,
+        }
+    }
+
+    fn attached(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    fn read(&mut self, process: u64, address: u64, buf: &mut [u8]) -> bool {
+        if process != SNES_PROCESS || !self.attached() {
+            return false;
+        }
+        let address = match usb2snes_address(address) {
+            Some(address) => address,
+            None => return false,
+        };
+        let range = (address, buf.len());
+        if !self.requested.contains(&range) {
+            self.requested.push(range);
+        }
+        let fetched = self.memory.iter().find(|(start, data)| {
+            *start <= address && (address - start) as usize + buf.len() <= data.len()
+        });
+        match fetched {
+            Some((start, data)) => {
+                let offset = (address - start) as usize;
+                buf.copy_from_slice(&data[offset..offset + buf.len()]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn phase(&self) -> u32 {
+        let timer = match &self.timer {
+            Some(timer) => timer,
+            None => return 0,
+        };
+        // TODO: fix this unwrap
+        match timer.read().unwrap().current_phase() {
+            TimerPhase::NotRunning => 0,
+            TimerPhase::Running => 1,
+            TimerPhase::Paused => 2,
+            TimerPhase::Ended => 3,
+        }
+    }
+
+    /// Skipping, undoing, and game time go straight to the timer. Starting,
+    /// splitting, and resetting are reported through the `AutoSplitter`
+    /// trait so the polling thread treats them like any other game.
+    fn with_timer(&self, action: impl FnOnce(&mut livesplit_core::Timer)) {
+        if let Some(timer) = &self.timer {
+            // TODO: fix this unwrap
+            action(&mut timer.write().unwrap());
+        }
+    }
+
+    fn add_setting(&mut self, key: &str, description: &str, value: bool) {
+        match self.headings.last() {
+            Some((_, parent)) => self.registered.insert_with_parent(key, value, parent),
+            None => self.registered.insert(key, value),
+        }
+        if !description.is_empty() && description != key {
+            self.registered.describe(key, description);
+        }
+    }
+
+    /// The user's value for a setting, or the module's default if the user
+    /// hasn't configured it
+    fn setting(&self, key: &str, default: bool) -> bool {
+        if self.settings.contains(key) {
+            self.settings.get(key)
+        } else {
+            default
+        }
+    }
+}
+
+/// Map an address the module reads to a usb2snes address
+fn usb2snes_address(address: u64) -> Option<u32> {
+    match address {
+        0x7E0000..=0x7FFFFF => Some(WRAM_START + (address - 0x7E0000) as u32),
+        0..=0xFFFFFF => Some(address as u32),
+        _ => None,
+    }
+}
+
+fn memory(caller: &mut Caller<'_, Host>) -> anyhow::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| anyhow!("module does not export its memory"))
+}
+
+fn read_str(caller: &mut Caller<'_, Host>, ptr: u32, len: u32) -> anyhow::Result<String> {
+    let memory = memory(caller)?;
+    let bytes = memory
+        .data(&*caller)
+        .get(ptr as usize..ptr as usize + len as usize)
+        .ok_or_else(|| anyhow!("string is outside of the module's memory"))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The host functions of LiveSplit's auto splitting runtime that make sense
+/// for a SNES
+fn link(linker: &mut Linker<Host>) -> anyhow::Result<()> {
+    linker.func_wrap("env", "timer_get_state", |caller: Caller<'_, Host>| {
+        caller.data().phase()
+    })?;
+    linker.func_wrap("env", "timer_start", |mut caller: Caller<'_, Host>| {
+        caller.data_mut().start = true;
+    })?;
+    linker.func_wrap("env", "timer_split", |mut caller: Caller<'_, Host>| {
+        caller.data_mut().splits += 1;
+    })?;
+    linker.func_wrap("env", "timer_reset", |mut caller: Caller<'_, Host>| {
+        caller.data_mut().reset = true;
+    })?;
+    linker.func_wrap("env", "timer_skip_split", |caller: Caller<'_, Host>| {
+        caller.data().with_timer(|timer| {
+            timer.skip_split().ok();
+        });
+    })?;
+    linker.func_wrap("env", "timer_undo_split", |caller: Caller<'_, Host>| {
+        caller.data().with_timer(|timer| {
+            timer.undo_split().ok();
+        });
+    })?;
+    linker.func_wrap(
+        "env",
+        "timer_set_game_time",
+        |caller: Caller<'_, Host>, secs: i64, nanos: i32| {
+            let time = TimeSpan::from_seconds(secs as f64 + nanos as f64 / 1_000_000_000.0);
+            caller.data().with_timer(|timer| {
+                timer.set_game_time(time).ok();
+            });
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "timer_pause_game_time",
+        |caller: Caller<'_, Host>| {
+            caller.data().with_timer(|timer| {
+                timer.pause_game_time().ok();
+            });
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "timer_resume_game_time",
+        |caller: Caller<'_, Host>| {
+            caller.data().with_timer(|timer| {
+                timer.resume_game_time().ok();
+            });
+        },
+    )?;
+    // There is nowhere to show custom variables
+    linker.func_wrap(
+        "env",
+        "timer_set_variable",
+        |_: Caller<'_, Host>, _: u32, _: u32, _: u32, _: u32| {},
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "process_attach",
+        |caller: Caller<'_, Host>, _name_ptr: u32, _name_len: u32| {
+            if caller.data().attached() {
+                SNES_PROCESS
+            } else {
+                0
+            }
+        },
+    )?;
+    linker.func_wrap("env", "process_detach", |_: Caller<'_, Host>, _: u64| {})?;
+    linker.func_wrap(
+        "env",
+        "process_is_open",
+        |caller: Caller<'_, Host>, process: u64| {
+            (process == SNES_PROCESS && caller.data().attached()) as u32
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "process_read",
+        |mut caller: Caller<'_, Host>, process: u64, address: u64, buf_ptr: u32, buf_len: u32| {
+            let memory = memory(&mut caller)?;
+            let (data, host) = memory.data_and_store_mut(&mut caller);
+            let buf = data
+                .get_mut(buf_ptr as usize..buf_ptr as usize + buf_len as usize)
+                .ok_or_else(|| anyhow!("read buffer is outside of the module's memory"))?;
+            anyhow::Ok(host.read(process, address, buf) as u32)
+        },
+    )?;
+    // The SNES has no modules, so these always fail
+    linker.func_wrap(
+        "env",
+        "process_get_module_address",
+        |_: Caller<'_, Host>, _: u64, _: u32, _: u32| 0u64,
+    )?;
+    linker.func_wrap(
+        "env",
+        "process_get_module_size",
+        |_: Caller<'_, Host>, _: u64, _: u32, _: u32| 0u64,
+    )?;
+
+    // The polling rate comes from the app config instead
+    linker.func_wrap(
+        "env",
+        "runtime_set_tick_rate",
+        |_: Caller<'_, Host>, _: f64| {},
+    )?;
+    linker.func_wrap(
+        "env",
+        "runtime_print_message",
+        |mut caller: Caller<'_, Host>, ptr: u32, len: u32| {
+            println!("{}", read_str(&mut caller, ptr, len)?);
+            anyhow::Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "env",
+        "user_settings_add_bool",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: u32,
+         key_len: u32,
+         description_ptr: u32,
+         description_len: u32,
+         default: u32| {
+            let key = read_str(&mut caller, key_ptr, key_len)?;
+            let description = read_str(&mut caller, description_ptr, description_len)?;
+            let host = caller.data_mut();
+            host.add_setting(&key, &description, default != 0);
+            anyhow::Ok(host.setting(&key, default != 0) as u32)
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "user_settings_add_title",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: u32,
+         key_len: u32,
+         description_ptr: u32,
+         description_len: u32,
+         heading_level: u32| {
+            let key = read_str(&mut caller, key_ptr, key_len)?;
+            let description = read_str(&mut caller, description_ptr, description_len)?;
+            let host = caller.data_mut();
+            while host
+                .headings
+                .last()
+                .is_some_and(|(level, _)| *level >= heading_level)
+            {
+                host.headings.pop();
+            }
+            host.add_setting(&key, &description, true);
+            host.headings.push((heading_level, key.into()));
+            anyhow::Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "user_settings_set_tooltip",
+        |mut caller: Caller<'_, Host>,
+         key_ptr: u32,
+         key_len: u32,
+         tooltip_ptr: u32,
+         tooltip_len: u32| {
+            let key = read_str(&mut caller, key_ptr, key_len)?;
+            let tooltip = read_str(&mut caller, tooltip_ptr, tooltip_len)?;
+            caller.data_mut().registered.describe(&key, &tooltip);
+            anyhow::Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+/// An autosplitter running a `WasmModule`. The module is instantiated on
+/// the first poll, with the settings from the settings editor.
+pub struct WasmAutoSplitter {
+    module: Arc<WasmModule>,
+    instance: Option<Instance>,
+    // Set when the module can't be instantiated or traps, so we don't
+    // keep retrying (and printing the same error) on every poll
+    failed: bool,
+    plan: Vec<(u32, usize)>,
+    memory: Vec<(u32, Vec<u8>)>,
+    start: bool,
+    reset: bool,
+}
+
+impl WasmAutoSplitter {
+    pub fn new(module: Arc<WasmModule>) -> WasmAutoSplitter {
+        WasmAutoSplitter {
+            module,
+            instance: None,
+            failed: false,
+            plan: vec![],
+            memory: vec![],
+            start: false,
+            reset: false,
+        }
+    }
+
+    fn stop(&mut self, e: &dyn std::fmt::Display) {
+        println!("{} stopped: {}", self.module.name, e);
+        self.instance = None;
+        self.failed = true;
+        self.plan.clear();
+    }
+}
+
+impl AutoSplitter for WasmAutoSplitter {
+    fn read_plan(&self) -> Vec<(u32, usize)> {
+        self.plan.clone()
+    }
+
+    fn update(&mut self, snes_data: &[Vec<u8>]) {
+        self.memory = self
+            .plan
+            .iter()
+            .map(|&(address, _)| address)
+            .zip(snes_data.iter().cloned())
+            .collect();
+    }
+
+    fn start(&self, _settings: &Settings) -> bool {
+        self.start
+    }
+
+    fn reset(&self, _settings: &Settings) -> bool {
+        self.reset
+    }
+
+    /// Runs the module's `update`, which is where it decides to start,
+    /// reset, or split. Modules don't say why they split, so each split is
+    /// reported under the module's name.
+    fn split(&mut self, settings: &Settings) -> Vec<SplitReason> {
+        self.start = false;
+        self.reset = false;
+        if self.instance.is_none() && !self.failed {
+            let host = Host::new(Some(self.module.timer.clone()), settings.clone());
+            match self.module.instantiate(host) {
+                Ok(instance) => self.instance = Some(instance),
+                Err(e) => self.stop(&e),
+            }
+        }
+        let instance = match self.instance.as_mut() {
+            Some(instance) => instance,
+            None => return vec![],
+        };
+        {
+            let host = instance.store.data_mut();
+            host.memory = std::mem::take(&mut self.memory);
+            host.start = false;
+            host.reset = false;
+            host.splits = 0;
+        }
+        if let Err(e) = instance.update() {
+            self.stop(&e);
+            return vec![];
+        }
+        let host = instance.store.data_mut();
+        // Merged like any other autosplitter's reads, however scattered
+        // the module's are
+        self.plan = plan_ranges(std::mem::take(&mut host.requested));
+        self.start = host.start;
+        self.reset = host.reset;
+        vec![self.module.name().into(); host.splits]
+    }
+
+    /// Modules set (and pause) the game time through the timer functions
+    /// themselves
+    fn gametime_to_seconds(&self) -> Option<TimeSpan> {
+        None
+    }
+
+    fn default_settings(&self) -> Settings {
+        self.module.default_settings()
+    }
+}
@@ -1,19 +1,60 @@
 use clap::Parser;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::autosplitters::{Game, Region};
+use crate::evaluate::EvaluateArgs;
 use crate::hotkey::*;
+use crate::usb2snes::Scheme;
 
 #[derive(Deserialize, Serialize, Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct AppConfig {
+    #[clap(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
     #[clap(name = "load-splits", short = 's', long, value_parser)]
     pub recent_splits: Option<String>,
     #[clap(name = "load-layout", short = 'l', long, value_parser)]
     pub recent_layout: Option<String>,
     #[clap(name = "load-autosplitter", short = 'a', long, value_parser)]
     pub recent_autosplitter: Option<String>,
+    #[clap(name = "load-definition", short = 'd', long, value_parser)]
+    pub recent_definition: Option<String>,
+    /// Start a new run from a route template: a built in one (hundo, kpdr),
+    /// one in the routes directory by file name, or a template file
+    #[clap(name = "route", long, value_parser)]
+    #[serde(skip)]
+    pub route: Option<String>,
+    #[clap(name = "game", long, value_parser)]
+    pub game: Option<Game>,
+    #[clap(name = "detect-game", long, value_parser)]
+    pub detect_game: Option<YesOrNo>,
+    /// Count game time frames at this region's rate instead of detecting it
+    #[clap(name = "region", long, value_parser)]
+    pub region: Option<Region>,
+    /// The host running QUsb2Snes or SNI
+    #[clap(name = "usb2snes-host", long, value_parser)]
+    pub usb2snes_host: Option<String>,
+    /// The port QUsb2Snes or SNI is listening on. Without one, 23074 and
+    /// then the legacy 8080 are tried.
+    #[clap(name = "usb2snes-port", long, value_parser)]
+    pub usb2snes_port: Option<u16>,
+    /// Use wss:// to connect to a server behind TLS
+    #[clap(name = "usb2snes-scheme", long, value_parser)]
+    pub usb2snes_scheme: Option<Scheme>,
+    /// The usb2snes device to use, when there's more than one. The first
+    /// device found is used while this one isn't there.
+    #[clap(name = "device", long, value_parser)]
+    pub device: Option<String>,
     #[clap(name = "use-autosplitter", long, action)]
     pub use_autosplitter: Option<YesOrNo>,
+    /// Save everything read from the SNES, to replay later
+    #[clap(name = "record-sessions", long, value_parser)]
+    pub record_sessions: Option<YesOrNo>,
+    /// Replay a recorded session instead of connecting to usb2snes
+    #[clap(name = "replay", long, value_parser)]
+    #[serde(skip)]
+    pub replay: Option<String>,
     #[clap(name = "polling-rate", long, short = 'p', value_parser)]
     pub polling_rate: Option<f32>,
     #[clap(name = "frame-rate", long, short = 'f', value_parser)]
@@ -22,6 +63,9 @@ pub struct AppConfig {
     pub reset_timer_on_game_reset: Option<YesOrNo>,
     #[clap(name = "reset-game-on-timer-reset", long, value_parser)]
     pub reset_game_on_timer_reset: Option<YesOrNo>,
+    /// Show the autosplitter's connection status and latency over the layout
+    #[clap(name = "show-status", long, value_parser)]
+    pub show_status: Option<YesOrNo>,
     #[clap(name = "global-hotkeys", long, short = 'g', value_parser)]
     pub global_hotkeys: Option<YesOrNo>,
     #[clap(skip)]
@@ -40,6 +84,14 @@ pub struct AppConfig {
     pub hot_key_comparison_prev: Option<HotKey>,
 }
 
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Check splits and autosplitter settings against a session recording,
+    /// without opening a window. Exits with an error unless every segment
+    /// gets split.
+    Evaluate(EvaluateArgs),
+}
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub enum YesOrNo {
     #[default]
@@ -54,9 +106,12 @@ impl AppConfig {
     fn new() -> Self {
         let modifiers = ::egui::Modifiers::default();
         AppConfig {
+            command: None,
             recent_splits: None,
             recent_layout: None,
             recent_autosplitter: None,
+            recent_definition: None,
+            route: None,
             hot_key_start: Some(HotKey {
                 key: egui::Key::Num1,
                 modifiers,
@@ -85,12 +140,22 @@ impl AppConfig {
                 key: egui::Key::Num4,
                 modifiers,
             }),
+            game: Some(Game::default()),
+            detect_game: Some(YesOrNo::Yes),
+            region: None,
+            record_sessions: Some(YesOrNo::No),
+            replay: None,
+            usb2snes_host: Some("localhost".to_owned()),
+            usb2snes_port: None,
+            usb2snes_scheme: Some(Scheme::Ws),
+            device: None,
             use_autosplitter: Some(YesOrNo::Yes),
             frame_rate: Some(DEFAULT_FRAME_RATE),
             polling_rate: Some(DEFAULT_POLLING_RATE),
             reset_timer_on_game_reset: Some(YesOrNo::No),
             reset_game_on_timer_reset: Some(YesOrNo::No),
             global_hotkeys: Some(YesOrNo::Yes),
+            show_status: Some(YesOrNo::No),
         }
     }
 }
@@ -0,0 +1,229 @@
+//! Run an autosplitter over a session recording without the GUI, to check
+//! that a set of splits and autosplitter settings split where they should.
+
+use crate::autosplitters::declarative::Definition;
+use crate::autosplitters::recording::Replay;
+use crate::autosplitters::{
+    fetch_all, take_reverted, AutoSplitterChoice, Game, LatencySamples, Region, Settings,
+    SplitEvent, SplitReason,
+};
+use livesplit_core::TimeSpan;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct EvaluateArgs {
+    /// A session recording (.rec)
+    pub recording: PathBuf,
+    /// Autosplitter settings saved from annelid (.asc)
+    #[clap(long = "autosplitter", short = 'a')]
+    pub autosplitter: PathBuf,
+    /// The splits to check against (.lss)
+    #[clap(long = "splits", short = 's')]
+    pub splits: PathBuf,
+    /// An autosplitter definition to use instead of a built in game
+    #[clap(long = "definition", short = 'd')]
+    pub definition: Option<PathBuf>,
+    #[clap(long = "game")]
+    pub game: Option<Game>,
+    /// Count game time frames at this region's rate instead of detecting it
+    #[clap(long = "region")]
+    pub region: Option<Region>,
+    /// Start over when the game resets, like the app's setting of the same name
+    #[clap(long = "reset-timer-on-game-reset")]
+    pub reset_timer_on_game_reset: bool,
+}
+
+/// What a split past the last segment is listed as
+const EXTRA_SPLIT: &str = "(extra)";
+
+pub struct Split {
+    pub segment: String,
+    /// Real time since the run started, as recorded
+    pub real_time: Duration,
+    pub game_time: Option<TimeSpan>,
+    pub reasons: Vec<SplitReason>,
+}
+
+/// Replay the recording and collect the splits of the last attempt in it,
+/// the way the polling thread would have made them. Returns the segment
+/// names along with the splits.
+pub fn evaluate(args: &EvaluateArgs) -> Result<(Vec<String>, Vec<Split>), Box<dyn Error>> {
+    let choice = match &args.definition {
+        Some(path) if path.extension().is_some_and(|ext| ext == "wasm") => {
+            Err("WebAssembly autosplitters drive the timer themselves and can't be evaluated")?
+        }
+        Some(path) => AutoSplitterChoice::Definition(Arc::new(Definition::load(path)?)),
+        None => AutoSplitterChoice::Game(args.game.unwrap_or_default()),
+    };
+    let mut settings: Settings = serde_json::from_reader(std::io::BufReader::new(
+        std::fs::File::open(&args.autosplitter)?,
+    ))?;
+    settings.add_missing(&choice.default_settings());
+    let segments: Vec<String> = {
+        use livesplit_core::run::parser::composite;
+        let contents = std::fs::read(&args.splits)?;
+        let run = composite::parse(&contents, args.splits.parent())?.run;
+        run.segments().iter().map(|s| s.name().to_owned()).collect()
+    };
+
+    let mut replay = Replay::load(&args.recording)?;
+    let mut autosplitter = choice.autosplitter();
+    autosplitter.set_region(args.region, None);
+    let mut latency = LatencySamples::new();
+    let mut started = None;
+    let mut splits: Vec<Split> = vec![];
+    while !replay.is_finished() {
+        let summary = match fetch_all(autosplitter.as_mut(), &mut replay, &settings, &mut latency) {
+            Ok(summary) => summary,
+            Err(_) if replay.is_finished() => break,
+            Err(e) => Err(e)?,
+        };
+        let now = replay.elapsed();
+        if summary.start && started.is_none() {
+            started = Some(now);
+            splits.clear();
+        }
+        if summary.reset && args.reset_timer_on_game_reset && started.is_some() {
+            println!("Reset after {} splits", splits.len());
+            started = None;
+        }
+        let Some(start) = started else {
+            continue;
+        };
+        let mut reverted = summary.reverted;
+        while let Some(last) = splits.last() {
+            if !take_reverted(&mut reverted, &last.reasons) {
+                break;
+            }
+            println!("Undoing the split of {}", last.segment);
+            splits.pop();
+        }
+        let (fired, _) = settings.assign_splits(Some(splits.len()), summary.split_events);
+        add_splits(
+            &mut splits,
+            &segments,
+            fired,
+            now - start,
+            autosplitter.gametime_to_seconds(),
+        );
+    }
+    Ok((segments, splits))
+}
+
+/// Add a split for each event, named after the segment it splits. Splits
+/// past the last segment are kept, so that they can be reported.
+fn add_splits(
+    splits: &mut Vec<Split>,
+    segments: &[String],
+    fired: Vec<SplitEvent>,
+    real_time: Duration,
+    game_time: Option<TimeSpan>,
+) {
+    for reasons in fired {
+        splits.push(Split {
+            segment: segments
+                .get(splits.len())
+                .cloned()
+                .unwrap_or_else(|| EXTRA_SPLIT.to_owned()),
+            real_time,
+            game_time,
+            reasons,
+        });
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let hundredths = (seconds * 100.0).round() as u64;
+    let (hours, minutes) = (hundredths / 360000, hundredths / 6000 % 60);
+    let seconds = hundredths as f64 / 100.0 % 60.0;
+    if hours > 0 {
+        format!("{}:{:02}:{:05.2}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:05.2}", minutes, seconds)
+    }
+}
+
+/// Print the split table, followed by any splits past the last segment.
+/// Returns whether there was exactly one split for each segment.
+pub fn print_table(segments: &[String], splits: &[Split]) -> bool {
+    let width = segments
+        .iter()
+        .map(|s| s.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Segment".len())
+        .max(EXTRA_SPLIT.len());
+    println!(
+        "{:width$}  {:>10}  {:>10}  Condition",
+        "Segment", "RTA", "IGT"
+    );
+    let print_split = |split: &Split| {
+        println!(
+            "{:width$}  {:>10}  {:>10}  {}",
+            split.segment,
+            format_time(split.real_time.as_secs_f64()),
+            split
+                .game_time
+                .map_or("-".to_owned(), |t| format_time(t.total_seconds())),
+            split.reasons.join(", ")
+        )
+    };
+    for (i, segment) in segments.iter().enumerate() {
+        match splits.get(i) {
+            Some(split) => print_split(split),
+            None => println!("{:width$}  {:>10}  {:>10}", segment, "-", "-"),
+        }
+    }
+    splits.iter().skip(segments.len()).for_each(print_split);
+    println!("{} splits for {} segments", splits.len(), segments.len());
+    splits.len() == segments.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(names: &[&str]) -> Vec<String> {
+        names.iter().map(|&name| name.to_owned()).collect()
+    }
+
+    fn fire(splits: &mut Vec<Split>, segments: &[String], reasons: &[&str]) {
+        let fired = reasons
+            .iter()
+            .map(|&reason| vec![SplitReason::from(reason)])
+            .collect();
+        add_splits(splits, segments, fired, Duration::from_secs(1), None);
+    }
+
+    #[test]
+    fn every_segment_split_once_passes() {
+        let segments = segments(&["Kraid", "Ridley"]);
+        let mut splits = vec![];
+        fire(&mut splits, &segments, &["kraid", "ridley"]);
+        assert!(print_table(&segments, &splits));
+    }
+
+    #[test]
+    fn too_few_splits_fail() {
+        let segments = segments(&["Kraid", "Ridley"]);
+        let mut splits = vec![];
+        assert!(!print_table(&segments, &splits));
+        fire(&mut splits, &segments, &["kraid"]);
+        assert!(!print_table(&segments, &splits));
+    }
+
+    #[test]
+    fn too_many_splits_are_kept_and_fail() {
+        let segments = segments(&["Kraid", "Ridley"]);
+        let mut splits = vec![];
+        fire(&mut splits, &segments, &["kraid"]);
+        fire(&mut splits, &segments, &["ridley", "motherBrain"]);
+        let names: Vec<&str> = splits.iter().map(|s| s.segment.as_str()).collect();
+        assert_eq!(names, ["Kraid", "Ridley", EXTRA_SPLIT]);
+        assert_eq!(&*splits[2].reasons[0], "motherBrain");
+        assert!(!print_table(&segments, &splits));
+    }
+}
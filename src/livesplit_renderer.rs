@@ -1,9 +1,20 @@
-use crate::autosplitters::supermetroid::{SNESState, Settings};
+use crate::autosplitters::cartridge::CartridgeHeader;
+use crate::autosplitters::declarative::Definition;
+use crate::autosplitters::recording::{Recorder, RecordingWriter, Replay};
+use crate::autosplitters::wasm::WasmModule;
+use crate::autosplitters::MemorySource;
+use crate::autosplitters::{
+    fetch_all, select_game, take_reverted, AutoSplitterChoice, Game, LatencySamples, Region,
+    Settings, SplitReason,
+};
+use crate::routes::Route;
+use crate::usb2snes::{ConnectionState, Flag, Infos, SyncClient, USB2SnesError};
 use anyhow::{anyhow, Result};
 use eframe::egui;
-use livesplit_core::{Layout, SharedTimer, Timer};
+use livesplit_core::{Layout, SharedTimer, TimeSpan, Timer, TimerPhase};
 use livesplit_hotkey::Hook;
 use parking_lot::RwLock;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::Arc;
 use thread_priority::{set_current_thread_priority, ThreadBuilder, ThreadPriority};
 
@@ -12,8 +23,83 @@ use crate::hotkey::*;
 use crate::utils::*;
 use crate::widget::glow_canvas::*;
 
+// How many of the latest polls the achieved polling rate is measured over
+const POLL_RATE_WINDOW: usize = 20;
+
+// How long to wait between attempts to connect to usb2snes. The first
+// retry is quick, so a bumped cable recovers without a noticeable gap.
+const MIN_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+const MAX_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Messages from the UI to the SNES polling thread
 pub enum ThreadEvent {
+    /// The timer was reset, so start over with a fresh autosplitter state
     TimerReset,
+    /// Drop the connection and connect again, picking up a newly chosen
+    /// autosplitter
+    Reconnect,
+    /// Disconnect and exit the thread
+    Stop,
+}
+
+/// A split made by the autosplitter this session, kept so that an
+/// unexpected split can be traced back to the setting that caused it
+#[derive(Clone)]
+pub struct SplitLogEntry {
+    /// The segment that was split, if the timer was running
+    pub segment: Option<String>,
+    /// That segment's index
+    pub index: Option<usize>,
+    pub reasons: Vec<SplitReason>,
+}
+
+impl std::fmt::Display for SplitLogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.segment {
+            Some(segment) => write!(f, "{}: {}", segment, self.reasons.join(", ")),
+            None => write!(f, "{}", self.reasons.join(", ")),
+        }
+    }
+}
+
+/// What the SNES polling thread is up to, so a runner can see the
+/// autosplitter is working before starting a run
+#[derive(Clone, Default)]
+pub struct AutosplitterStatus {
+    pub state: ConnectionState,
+    /// The usb2snes device in use
+    pub device: Option<String>,
+    /// What's being autosplit, once it's been picked
+    pub game: Option<String>,
+    /// The polls actually made per second, which can fall short of the
+    /// polling rate asked for on a slow connection
+    pub polling_rate: f32,
+    /// Average and standard deviation of recent polls, in milliseconds
+    pub latency: (f32, f32),
+    pub last_error: Option<String>,
+}
+
+impl AutosplitterStatus {
+    /// A line for each thing that's known, for showing to the user
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![match &self.device {
+            Some(device) => format!("{} ({})", self.state, device),
+            None => self.state.to_string(),
+        }];
+        if let Some(game) = &self.game {
+            lines.push(format!("Game: {}", game));
+        }
+        if self.state == ConnectionState::Polling {
+            lines.push(format!(
+                "{:.1} polls/s, latency {:.0} ± {:.0} ms",
+                self.polling_rate, self.latency.0, self.latency.1
+            ));
+        }
+        if let Some(error) = &self.last_error {
+            lines.push(format!("Last error: {}", error));
+        }
+        lines
+    }
 }
 
 pub struct LiveSplitCoreRenderer {
@@ -23,10 +109,20 @@ pub struct LiveSplitCoreRenderer {
     image_cache: livesplit_core::settings::ImageCache,
     timer: SharedTimer,
     show_settings_editor: bool,
+    show_split_log: bool,
+    show_segment_bindings: bool,
+    split_log: Arc<RwLock<Vec<SplitLogEntry>>>,
+    // What usb2snes listed on the last connection
+    devices: Arc<RwLock<Vec<String>>>,
+    status: Arc<RwLock<AutosplitterStatus>>,
     settings: Arc<RwLock<Settings>>,
+    autosplitter_choice: Arc<RwLock<AutoSplitterChoice>>,
     can_exit: bool,
     is_exiting: bool,
-    thread_chan: std::sync::mpsc::SyncSender<ThreadEvent>,
+    thread_chan: std::sync::mpsc::Sender<ThreadEvent>,
+    // Shared so that a restarted polling thread can pick it up again
+    thread_receiver: Arc<parking_lot::Mutex<std::sync::mpsc::Receiver<ThreadEvent>>>,
+    autosplitter_thread: Option<std::thread::JoinHandle<()>>,
     project_dirs: directories::ProjectDirs,
     pub app_config: std::sync::Arc<std::sync::RwLock<AppConfig>>,
     app_config_processed: bool,
@@ -48,7 +144,7 @@ fn show_children(
         if !children.is_empty() {
             egui::collapsing_header::CollapsingState::load_with_default_open(ctx, id, false)
                 .show_header(ui, |ui| {
-                    ui.checkbox(settings.lookup_mut(key), key);
+                    show_setting(settings, ui, key);
                 })
                 .body(|ui| {
                     ui.indent(id, |ui| {
@@ -62,21 +158,29 @@ fn show_children(
                 });
         } else {
             ui.scope(|ui| {
-                ui.checkbox(settings.lookup_mut(key), key);
+                show_setting(settings, ui, key);
             });
         }
     });
 }
 
+fn show_setting(settings: &mut Settings, ui: &mut egui::Ui, key: &str) {
+    let description = settings.description(key).map(|d| d.to_owned());
+    let response = ui.checkbox(settings.lookup_mut(key), key);
+    if let Some(description) = description {
+        response.on_hover_text(description);
+    }
+}
+
 impl LiveSplitCoreRenderer {
     pub fn new(
         timer: SharedTimer,
         layout: Layout,
         settings: Arc<RwLock<Settings>>,
-        chan: std::sync::mpsc::SyncSender<ThreadEvent>,
         project_dirs: directories::ProjectDirs,
         cli_config: AppConfig,
     ) -> Self {
+        let (thread_chan, thread_receiver) = std::sync::mpsc::channel();
         LiveSplitCoreRenderer {
             timer,
             layout,
@@ -84,10 +188,18 @@ impl LiveSplitCoreRenderer {
             image_cache: livesplit_core::settings::ImageCache::new(),
             layout_state: None,
             show_settings_editor: false,
+            show_split_log: false,
+            show_segment_bindings: false,
+            split_log: Arc::new(RwLock::new(vec![])),
+            devices: Arc::new(RwLock::new(vec![])),
+            status: Arc::new(RwLock::new(AutosplitterStatus::default())),
             settings,
+            autosplitter_choice: Arc::new(RwLock::new(AutoSplitterChoice::Game(Game::default()))),
             can_exit: false,
             is_exiting: false,
-            thread_chan: chan,
+            thread_chan,
+            thread_receiver: Arc::new(parking_lot::Mutex::new(thread_receiver)),
+            autosplitter_thread: None,
             project_dirs,
             app_config: std::sync::Arc::new(std::sync::RwLock::new(cli_config)),
             app_config_processed: false,
@@ -186,6 +298,35 @@ impl LiveSplitCoreRenderer {
             if cli_config.recent_autosplitter.is_some() {
                 new_app_config.recent_autosplitter = cli_config.recent_autosplitter;
             }
+            if cli_config.recent_definition.is_some() {
+                new_app_config.recent_definition = cli_config.recent_definition;
+            }
+            new_app_config.route = cli_config.route;
+            if cli_config.game.is_some() {
+                new_app_config.game = cli_config.game;
+            }
+            if cli_config.detect_game.is_some() {
+                new_app_config.detect_game = cli_config.detect_game;
+            }
+            if cli_config.region.is_some() {
+                new_app_config.region = cli_config.region;
+            }
+            if cli_config.record_sessions.is_some() {
+                new_app_config.record_sessions = cli_config.record_sessions;
+            }
+            new_app_config.replay = cli_config.replay;
+            if cli_config.usb2snes_host.is_some() {
+                new_app_config.usb2snes_host = cli_config.usb2snes_host;
+            }
+            if cli_config.usb2snes_port.is_some() {
+                new_app_config.usb2snes_port = cli_config.usb2snes_port;
+            }
+            if cli_config.usb2snes_scheme.is_some() {
+                new_app_config.usb2snes_scheme = cli_config.usb2snes_scheme;
+            }
+            if cli_config.device.is_some() {
+                new_app_config.device = cli_config.device;
+            }
             if cli_config.use_autosplitter.is_some() {
                 new_app_config.use_autosplitter = cli_config.use_autosplitter;
             }
@@ -204,6 +345,9 @@ impl LiveSplitCoreRenderer {
             if cli_config.global_hotkeys.is_some() {
                 new_app_config.global_hotkeys = cli_config.global_hotkeys;
             }
+            if cli_config.show_status.is_some() {
+                new_app_config.show_status = cli_config.show_status;
+            }
             *self.app_config.write().unwrap() = new_app_config;
             Ok(())
         });
@@ -239,6 +383,12 @@ impl LiveSplitCoreRenderer {
                     format!("Failed to load autosplitter config \"{}\"", autosplitter)
                 })?;
             }
+            if let Some(route) = config.route {
+                let found = crate::routes::find(&route, &self.routes_dir())
+                    .map_err(|e| anyhow!("{}", e))
+                    .with_context(|| format!("Failed to load route \"{}\"", route))?;
+                self.new_run_from_route(found)?;
+            }
             Ok(())
         });
         self.load_errors = queue;
@@ -301,10 +451,419 @@ impl LiveSplitCoreRenderer {
     }
 
     pub fn load_autosplitter(&mut self, f: &std::fs::File) -> Result<()> {
-        *self.settings.write() = serde_json::from_reader(std::io::BufReader::new(f))?;
+        let mut settings: Settings = serde_json::from_reader(std::io::BufReader::new(f))?;
+        settings.add_missing(&self.autosplitter_choice.read().default_settings());
+        *self.settings.write() = settings;
+        Ok(())
+    }
+
+    /// Switch the autosplitter to a different game. The settings tree is
+    /// replaced with that game's defaults, and the polling thread
+    /// reconnects to pick up the new game.
+    pub fn select_game(&mut self, game: Game) {
+        {
+            let mut config = self.app_config.write().unwrap();
+            config.game = Some(game);
+            config.recent_definition = None;
+        }
+        self.set_autosplitter_choice(AutoSplitterChoice::Game(game));
+    }
+
+    /// Where session recordings are written
+    pub fn recordings_dir(&self) -> std::path::PathBuf {
+        self.project_dirs.data_local_dir().join("recordings")
+    }
+
+    /// Where route template files are looked for
+    pub fn routes_dir(&self) -> std::path::PathBuf {
+        self.project_dirs.preference_dir().join("routes")
+    }
+
+    /// Replace the splits and the autosplitter settings with a route's
+    pub fn new_run_from_route(&mut self, route: Route) -> Result<()> {
+        self.select_game(route.game);
+        *self.settings.write() = route.settings;
+        // TODO: fix this unwrap
+        self.timer
+            .write()
+            .unwrap()
+            .set_run(route.run)
+            .map_err(|_| anyhow!("The route has no segments"))?;
+        let mut config = self.app_config.write().unwrap();
+        // Neither of these describe the current run anymore
+        config.recent_splits = None;
+        config.recent_autosplitter = None;
+        Ok(())
+    }
+
+    /// Like `select_game`, but for a definition file or WebAssembly module
+    pub fn load_definition(&mut self, path: &std::path::Path) -> Result<()> {
+        let choice = if path.extension().is_some_and(|ext| ext == "wasm") {
+            let module =
+                WasmModule::load(path, self.timer.clone()).map_err(|e| anyhow!("{}", e))?;
+            AutoSplitterChoice::Wasm(Arc::new(module))
+        } else {
+            let definition = Definition::load(path).map_err(|e| anyhow!("{}", e))?;
+            AutoSplitterChoice::Definition(Arc::new(definition))
+        };
+        self.set_autosplitter_choice(choice);
+        self.app_config.write().unwrap().recent_definition =
+            Some(path.to_str().expect("utf8").to_owned());
+        Ok(())
+    }
+
+    fn set_autosplitter_choice(&mut self, choice: AutoSplitterChoice) {
+        *self.settings.write() = choice.default_settings();
+        *self.autosplitter_choice.write() = choice;
+        if self.autosplitter_running() {
+            self.thread_chan.send(ThreadEvent::Reconnect).unwrap_or(());
+        }
+    }
+
+    /// Whether the SNES polling thread is running
+    pub fn autosplitter_running(&self) -> bool {
+        self.autosplitter_thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Start the SNES polling thread, unless it's already running
+    pub fn start_autosplitter(&mut self) -> Result<()> {
+        if self.autosplitter_running() {
+            return Ok(());
+        }
+        // Anything sent while no thread was listening is out of date
+        while self.thread_receiver.lock().try_recv().is_ok() {}
+        let polling_rate = self
+            .app_config
+            .read()
+            .unwrap()
+            .polling_rate
+            .unwrap_or(DEFAULT_POLLING_RATE);
+        let poll_interval = std::time::Duration::from_millis((1000.0 / polling_rate) as u64);
+        // The timer, settings, and app_config are all behind
+        // something equivalent to Arc<RwLock<_>> so it's safe
+        // to clone them and pass the clone between threads.
+        let timer = self.timer.clone();
+        let settings = self.settings.clone();
+        let split_log = self.split_log.clone();
+        let autosplitter_choice = self.autosplitter_choice.clone();
+        let app_config = self.app_config.clone();
+        let recordings_dir = self.recordings_dir();
+        let replay = app_config.read().unwrap().replay.clone();
+        let thread_receiver = self.thread_receiver.clone();
+        let devices = self.devices.clone();
+        let status = self.status.clone();
+        let mut retry_delay = MIN_RETRY_DELAY;
+        // Whether the cartridge header can be read. Once the device says it
+        // can't, the configured game is used until asked to reconnect.
+        // Connection trouble while reading it reconnects like any other read.
+        let mut header_readable = true;
+        // This thread deals with polling the SNES at a fixed rate.
+        let thread = ThreadBuilder::default()
+            .name("SNES Polling Thread".to_owned())
+            // We could change this thread priority, but we probably
+            // should leave it at the default to make sure we get timely
+            // polling of SNES state
+            .spawn(move |_| loop {
+                let mut stop = false;
+                status.write().state = ConnectionState::Connecting;
+                let result = (|| -> std::result::Result<(), Box<dyn std::error::Error>> {
+                    let mut client: Box<dyn MemorySource> = match &replay {
+                        Some(path) => {
+                            let mut replay = Replay::load(std::path::Path::new(path))?;
+                            replay.set_realtime(true);
+                            println!("Replaying {}", path);
+                            Box::new(replay)
+                        }
+                        None => {
+                            let config = app_config.read().unwrap().clone();
+                            let (client, device, infos) = connect_to_device(&config, &devices)?;
+                            if header_readable && infos.has(&Flag::NoRomRead) {
+                                println!(
+                                    "{} can't read ROM, so the game won't be detected",
+                                    device
+                                );
+                                header_readable = false;
+                            }
+                            status.write().device = Some(device);
+                            let writer = if app_config.read().unwrap().record_sessions
+                                == Some(YesOrNo::Yes)
+                            {
+                                std::fs::create_dir_all(&recordings_dir)?;
+                                let now = std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)?;
+                                let path =
+                                    recordings_dir.join(format!("session-{}.rec", now.as_secs()));
+                                println!("Recording to {}", path.display());
+                                Some(RecordingWriter::create(&path)?)
+                            } else {
+                                None
+                            };
+                            Box::new(Recorder::new(client, writer))
+                        }
+                    };
+                    status.write().state = ConnectionState::Attached;
+                    let configured = autosplitter_choice.read().clone();
+                    let detect_game = app_config.read().unwrap().detect_game != Some(YesOrNo::No);
+                    let mut header = None;
+                    let mut game = Some(configured.clone());
+                    if detect_game && header_readable {
+                        match CartridgeHeader::read(client.as_mut()) {
+                            Ok(h) => {
+                                println!("Cartridge: {}", h);
+                                game = select_game(&h, &configured);
+                                header = Some(h);
+                            }
+                            Err(e) if is_unsupported(e.as_ref()) => {
+                                println!("Couldn't read the cartridge header: {}", e);
+                                header_readable = false;
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    if let Some(game) = &game {
+                        println!("Autosplitting {}", game.name());
+                    }
+                    status.write().game = game.as_ref().map(|g| g.name().to_owned());
+                    let mut snes = game.as_ref().map(|g| g.autosplitter());
+                    let mut latency_samples = LatencySamples::new();
+                    let mut poll_times = std::collections::VecDeque::new();
+                    // Re-read the cartridge header about once a second so that
+                    // booting a different game mid-session is noticed.
+                    let header_check_interval = polling_rate.ceil().max(1.0) as u32;
+                    let mut polls_since_header_check = 0;
+                    loop {
+                        if detect_game && header_readable {
+                            polls_since_header_check += 1;
+                            if polls_since_header_check >= header_check_interval {
+                                polls_since_header_check = 0;
+                                match CartridgeHeader::read(client.as_mut()) {
+                                    Ok(h) if header.as_ref() != Some(&h) => {
+                                        println!("Cartridge changed: {}", h);
+                                        game = select_game(&h, &configured);
+                                        snes = game.as_ref().map(|g| g.autosplitter());
+                                        status.write().game =
+                                            game.as_ref().map(|g| g.name().to_owned());
+                                        header = Some(h);
+                                    }
+                                    Ok(_) => {}
+                                    // Keep splitting whatever was detected last
+                                    Err(e) if is_unsupported(e.as_ref()) => {
+                                        println!("Couldn't read the cartridge header: {}", e);
+                                        header_readable = false;
+                                    }
+                                    Err(e) => return Err(e),
+                                }
+                            }
+                        }
+                        if let Some(snes) = snes.as_mut() {
+                            snes.set_region(
+                                app_config.read().unwrap().region,
+                                header.as_ref().map(|h| h.video_region()),
+                            );
+                            let summary = fetch_all(
+                                snes.as_mut(),
+                                client.as_mut(),
+                                &settings.read(),
+                                &mut latency_samples,
+                            )?;
+                            let now = std::time::Instant::now();
+                            if poll_times.len() == POLL_RATE_WINDOW {
+                                poll_times.pop_front();
+                            }
+                            poll_times.push_back(now);
+                            {
+                                let mut status = status.write();
+                                status.state = ConnectionState::Polling;
+                                status.latency = (summary.latency_average, summary.latency_stddev);
+                                if let Some(&first) = poll_times.front() {
+                                    let elapsed = (now - first).as_secs_f32();
+                                    if elapsed > 0.0 {
+                                        status.polling_rate =
+                                            (poll_times.len() - 1) as f32 / elapsed;
+                                    }
+                                }
+                            }
+                            // Back to retrying quickly, now that it's working
+                            retry_delay = MIN_RETRY_DELAY;
+                            if summary.start {
+                                // TODO: fix this unwrap
+                                timer.write().unwrap().start().ok();
+                            }
+                            if let Some(game_time) = snes.gametime_to_seconds() {
+                                // TODO: fix this unwrap
+                                sync_game_time(&mut timer.write().unwrap(), game_time);
+                            }
+                            if summary.reset
+                                && app_config.read().unwrap().reset_timer_on_game_reset
+                                    == Some(YesOrNo::Yes)
+                            {
+                                // TODO: fix this unwrap
+                                timer.write().unwrap().reset(true).ok();
+                            }
+                            if !summary.reverted.is_empty() {
+                                // TODO: fix this unwrap
+                                let mut timer = timer.write().unwrap();
+                                let mut reverted = summary.reverted;
+                                let mut split_log = split_log.write();
+                                // Only the latest split can be undone, so
+                                // stop at the first one that wasn't ours
+                                while let Some(entry) = split_log.last() {
+                                    let last =
+                                        timer.current_split_index().and_then(|i| i.checked_sub(1));
+                                    if entry.index.is_none()
+                                        || entry.index != last
+                                        || !take_reverted(&mut reverted, &entry.reasons)
+                                    {
+                                        break;
+                                    }
+                                    println!("Undoing split due to {}", entry);
+                                    timer.undo_split().ok();
+                                    split_log.pop();
+                                }
+                            }
+                            if !summary.split_events.is_empty() {
+                                // TODO: fix this unwrap
+                                let mut timer = timer.write().unwrap();
+                                let index = timer.current_split_index();
+                                let (splits, unbound) =
+                                    settings.read().assign_splits(index, summary.split_events);
+                                // Everything that fired in this poll splits at
+                                // the same moment, back to back
+                                let mut splits = splits.into_iter();
+                                for reasons in splits.by_ref() {
+                                    let index = timer.current_split_index();
+                                    let segment = index
+                                        .and_then(|i| timer.run().segments().get(i))
+                                        .map(|s| s.name().to_owned());
+                                    let entry = SplitLogEntry {
+                                        segment,
+                                        index,
+                                        reasons,
+                                    };
+                                    // The timer may not be running, or be on
+                                    // its last segment already
+                                    match timer.split() {
+                                        Ok(_) => {
+                                            println!("Split due to {}", entry);
+                                            split_log.write().push(entry);
+                                        }
+                                        Err(e) => {
+                                            println!("Not splitting on {}: {:?}", entry, e);
+                                            break;
+                                        }
+                                    }
+                                }
+                                // Once the timer stops taking splits, the rest
+                                // of this poll's have nowhere to go
+                                for reasons in splits {
+                                    println!(
+                                        "Not splitting on {}: an earlier split failed",
+                                        reasons.join(", ")
+                                    );
+                                }
+                                for reasons in unbound {
+                                    println!(
+                                        "Not splitting {} on {}: not bound to it",
+                                        timer
+                                            .current_split_index()
+                                            .and_then(|i| timer.run().segments().get(i))
+                                            .map(|s| s.name().to_owned())
+                                            .unwrap_or_default(),
+                                        reasons.join(", ")
+                                    );
+                                }
+                            }
+                        }
+                        // Wait for the next poll, or for the UI to want something
+                        match thread_receiver.lock().recv_timeout(poll_interval) {
+                            // If the timer gets reset, we need to make a fresh snes state
+                            Ok(ThreadEvent::TimerReset) => {
+                                snes = game.as_ref().map(|g| g.autosplitter());
+                                //Reset the snes
+                                if app_config.read().unwrap().reset_game_on_timer_reset
+                                    == Some(YesOrNo::Yes)
+                                {
+                                    // A device that can't reset is no reason to drop it
+                                    match client.reset() {
+                                        Err(e)
+                                            if matches!(
+                                                e.downcast_ref(),
+                                                Some(USB2SnesError::Unsupported { .. })
+                                            ) =>
+                                        {
+                                            println!("{}", e)
+                                        }
+                                        result => result?,
+                                    }
+                                }
+                            }
+                            Ok(ThreadEvent::Reconnect) => {
+                                println!("Reconnecting");
+                                header_readable = true;
+                                return Ok(());
+                            }
+                            Ok(ThreadEvent::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                                stop = true;
+                                return Ok(());
+                            }
+                            Err(RecvTimeoutError::Timeout) => {}
+                        }
+                    }
+                })();
+                {
+                    let mut status = status.write();
+                    let last_error = match result {
+                        Ok(()) => status.last_error.take(),
+                        Err(e) => {
+                            println!("{}", e);
+                            Some(e.to_string())
+                        }
+                    };
+                    *status = AutosplitterStatus {
+                        last_error,
+                        ..AutosplitterStatus::default()
+                    };
+                }
+                if stop {
+                    println!("Autosplitter stopped");
+                    break;
+                }
+                if replay.is_some() {
+                    println!("Replay finished");
+                    break;
+                }
+                // Wait before trying to connect again, longer each time it
+                // fails, unless asked to reconnect now
+                match thread_receiver.lock().recv_timeout(retry_delay) {
+                    Ok(ThreadEvent::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                        println!("Autosplitter stopped");
+                        break;
+                    }
+                    Ok(ThreadEvent::Reconnect) => {
+                        retry_delay = MIN_RETRY_DELAY;
+                        header_readable = true;
+                    }
+                    Ok(ThreadEvent::TimerReset) | Err(RecvTimeoutError::Timeout) => {
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                    }
+                }
+            })?;
+        self.autosplitter_thread = Some(thread);
         Ok(())
     }
 
+    /// Stop the SNES polling thread and wait for it to disconnect
+    pub fn stop_autosplitter(&mut self) {
+        if let Some(thread) = self.autosplitter_thread.take() {
+            self.thread_chan.send(ThreadEvent::Stop).unwrap_or(());
+            thread.join().unwrap_or(());
+            // Nobody was listening if it had already finished
+            while self.thread_receiver.lock().try_recv().is_ok() {}
+        }
+    }
+
     pub fn save_splits_dialog(&mut self, default_dir: &str) {
         // TODO: fix this unwrap
         let mut fname = self.timer.read().unwrap().run().extended_file_name(false);
@@ -497,6 +1056,43 @@ impl LiveSplitCoreRenderer {
         );
     }
 
+    pub fn open_definition_dialog(&mut self, default_dir: &str) {
+        let default_path_buf = std::path::Path::new(default_dir).to_path_buf();
+        let dir = self
+            .app_config
+            .read()
+            .unwrap()
+            .recent_definition
+            .as_ref()
+            .map_or(default_path_buf.clone(), |p| {
+                let path = std::path::Path::new(&p);
+                path.parent().map_or(default_path_buf, |p| p.to_path_buf())
+            })
+            .into_os_string()
+            .into_string()
+            .expect("utf8");
+        self.open_dialog(&dir, ("Autosplitter Definition", "toml"), |me, _f, path| {
+            me.load_definition(&path)
+        });
+    }
+
+    pub fn open_route_dialog(&mut self) {
+        let dir = self.routes_dir();
+        let dir = if dir.is_dir() {
+            dir
+        } else {
+            self.project_dirs.preference_dir().to_path_buf()
+        };
+        self.open_dialog(
+            dir.to_str().expect("utf8"),
+            ("Route Template", "toml"),
+            |me, _f, path| {
+                let route = Route::load(&path).map_err(|e| anyhow!("{}", e))?;
+                me.new_run_from_route(route)
+            },
+        );
+    }
+
     pub fn open_dialog(
         &mut self,
         default_dir: &str,
@@ -561,7 +1157,7 @@ impl LiveSplitCoreRenderer {
                 // TODO: fix this unwrap
                 timer.write().unwrap().reset(true).ok();
                 if app_config_.read().unwrap().use_autosplitter == Some(YesOrNo::Yes) {
-                    thread_chan.try_send(ThreadEvent::TimerReset).unwrap_or(());
+                    thread_chan.send(ThreadEvent::TimerReset).unwrap_or(());
                 }
             })?;
             if let Some(alt_key) = to_livesplit_keycode_alternative(&hot_key.key) {
@@ -575,7 +1171,7 @@ impl LiveSplitCoreRenderer {
                     // TODO: fix this unwrap
                     timer.write().unwrap().reset(true).ok();
                     if app_config.read().unwrap().use_autosplitter == Some(YesOrNo::Yes) {
-                        thread_chan.try_send(ThreadEvent::TimerReset).unwrap_or(());
+                        thread_chan.send(ThreadEvent::TimerReset).unwrap_or(());
                     }
                 })?;
             }
@@ -777,6 +1373,22 @@ impl eframe::App for LiveSplitCoreRenderer {
                         self.save_splits_dialog(&document_dir);
                     }
                 });
+                ui.menu_button("New Run from Template", |ui| {
+                    for template in crate::routes::templates(&self.routes_dir()) {
+                        if ui.button(template.name()).clicked() {
+                            ui.close_menu();
+                            messagebox_on_error(|| {
+                                let route = template.build().map_err(|e| anyhow!("{}", e))?;
+                                self.new_run_from_route(route)
+                            });
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("From File...").clicked() {
+                        ui.close_menu();
+                        self.open_route_dialog();
+                    }
+                });
                 ui.menu_button("Run Control", |ui| {
                     if ui.button("Start").clicked() {
                         // TODO: fix this unwrap
@@ -816,18 +1428,132 @@ impl eframe::App for LiveSplitCoreRenderer {
                         // TODO: fix this unwrap
                         self.timer.write().unwrap().reset(true).ok();
                         if self.app_config.read().unwrap().use_autosplitter == Some(YesOrNo::Yes) {
-                            self.thread_chan
-                                .try_send(ThreadEvent::TimerReset)
-                                .unwrap_or(());
+                            self.thread_chan.send(ThreadEvent::TimerReset).unwrap_or(());
                         }
                         ui.close_menu()
                     }
                 });
                 ui.menu_button("Autosplitter", |ui| {
+                    let mut enabled = self.autosplitter_running();
+                    if ui
+                        .checkbox(&mut enabled, "Enabled")
+                        .on_hover_text("Poll the SNES and start, split, and reset automatically")
+                        .changed()
+                    {
+                        self.app_config.write().unwrap().use_autosplitter =
+                            Some(if enabled { YesOrNo::Yes } else { YesOrNo::No });
+                        if enabled {
+                            messagebox_on_error(|| self.start_autosplitter());
+                        } else {
+                            self.stop_autosplitter();
+                        }
+                    }
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Reconnect"))
+                        .clicked()
+                    {
+                        self.thread_chan.send(ThreadEvent::Reconnect).unwrap_or(());
+                        ui.close_menu();
+                    }
+                    let mut show_status =
+                        self.app_config.read().unwrap().show_status == Some(YesOrNo::Yes);
+                    if ui.checkbox(&mut show_status, "Show Status").changed() {
+                        self.app_config.write().unwrap().show_status = Some(if show_status {
+                            YesOrNo::Yes
+                        } else {
+                            YesOrNo::No
+                        });
+                    }
+                    ui.menu_button("Device", |ui| {
+                        let devices = self.devices.read().clone();
+                        if devices.is_empty() {
+                            ui.label("No devices found");
+                        }
+                        let attached = self.status.read().device.clone();
+                        for device in devices {
+                            if ui
+                                .radio(attached.as_ref() == Some(&device), &device)
+                                .clicked()
+                            {
+                                ui.close_menu();
+                                let reconnect = attached.as_ref() != Some(&device);
+                                self.app_config.write().unwrap().device = Some(device);
+                                if reconnect {
+                                    self.thread_chan.send(ThreadEvent::Reconnect).unwrap_or(());
+                                }
+                            }
+                        }
+                    });
+                    ui.separator();
+                    ui.menu_button("Game", |ui| {
+                        let current = match &*self.autosplitter_choice.read() {
+                            AutoSplitterChoice::Game(game) => Some(*game),
+                            AutoSplitterChoice::Definition(_) | AutoSplitterChoice::Wasm(_) => None,
+                        };
+                        for game in <Game as clap::ValueEnum>::value_variants() {
+                            if ui.radio(current == Some(*game), game.name()).clicked() {
+                                ui.close_menu();
+                                if current != Some(*game) {
+                                    self.select_game(*game);
+                                }
+                            }
+                        }
+                        match &*self.autosplitter_choice.read() {
+                            AutoSplitterChoice::Game(_) => {}
+                            AutoSplitterChoice::Definition(definition) => {
+                                ui.separator();
+                                ui.label(format!("Definition: {}", definition.name()));
+                            }
+                            AutoSplitterChoice::Wasm(module) => {
+                                ui.separator();
+                                ui.label(format!("WebAssembly: {}", module.name()));
+                            }
+                        }
+                    });
+                    ui.menu_button("Region", |ui| {
+                        let current = self.app_config.read().unwrap().region;
+                        let choices = [
+                            (None, "Detect"),
+                            (Some(Region::Ntsc), "NTSC (60 fps)"),
+                            (Some(Region::Pal), "PAL (50 fps)"),
+                        ];
+                        for (region, label) in choices {
+                            if ui.radio(current == region, label).clicked() {
+                                ui.close_menu();
+                                self.app_config.write().unwrap().region = region;
+                            }
+                        }
+                    });
+                    if ui.button("Load Definition").clicked() {
+                        ui.close_menu();
+                        self.open_definition_dialog(&document_dir);
+                    }
                     if ui.button("Configure").clicked() {
                         self.show_settings_editor = true;
                         ui.close_menu();
                     }
+                    if ui.button("Segment Bindings").clicked() {
+                        self.show_segment_bindings = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Split Log").clicked() {
+                        self.show_split_log = true;
+                        ui.close_menu();
+                    }
+                    let mut record =
+                        self.app_config.read().unwrap().record_sessions == Some(YesOrNo::Yes);
+                    if ui
+                        .checkbox(&mut record, "Record Sessions")
+                        .on_hover_text(format!(
+                            "Starting with the next connection, save everything read from the \
+                             SNES to {}",
+                            self.recordings_dir().display()
+                        ))
+                        .changed()
+                    {
+                        self.app_config.write().unwrap().record_sessions =
+                            Some(if record { YesOrNo::Yes } else { YesOrNo::No });
+                    }
                     if ui.button("Load Configuration").clicked() {
                         ui.close_menu();
                         self.open_autosplitter_dialog(&document_dir);
@@ -842,6 +1568,16 @@ impl eframe::App for LiveSplitCoreRenderer {
                     "Comparison: {}",
                     self.timer.read().unwrap().current_comparison()
                 )));
+                if let Some(entry) = self.split_log.read().last() {
+                    ui.add(egui::widgets::Label::new(format!(
+                        "Last split: {}",
+                        entry.reasons.join(", ")
+                    )));
+                }
+                ui.separator();
+                for line in self.status.read().lines() {
+                    ui.label(line);
+                }
                 ui.separator();
                 if ui.button("Quit").clicked() {
                     ctx.send_viewport_cmd(egui::viewport::ViewportCommand::Close)
@@ -859,6 +1595,100 @@ impl eframe::App for LiveSplitCoreRenderer {
                 let mut roots = settings.roots();
                 show_children(&mut settings, ui, ctx, &mut roots);
             });
+        egui::containers::Window::new("Segment Bindings")
+            .open(&mut self.show_segment_bindings)
+            .resizable(true)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let segments: Vec<String> = {
+                    // TODO: fix this unwrap
+                    let timer = self.timer.read().unwrap();
+                    timer
+                        .run()
+                        .segments()
+                        .iter()
+                        .map(|s| s.name().to_owned())
+                        .collect()
+                };
+                let mut settings = self.settings.write();
+                let mut ordered = settings.ordered();
+                if ui
+                    .checkbox(
+                        &mut ordered,
+                        "Only split on the current segment's conditions",
+                    )
+                    .changed()
+                {
+                    settings.set_ordered(ordered);
+                }
+                let keys = settings.keys();
+                egui::Grid::new("segment bindings")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, segment) in segments.iter().enumerate() {
+                            ui.label(segment);
+                            ui.horizontal_wrapped(|ui| {
+                                let mut unbind = None;
+                                for key in settings.bindings(i) {
+                                    if ui
+                                        .button(format!("{} ✕", key))
+                                        .on_hover_text("Unbind")
+                                        .clicked()
+                                    {
+                                        unbind = Some(key.clone());
+                                    }
+                                }
+                                if let Some(key) = unbind {
+                                    settings.unbind(i, &key);
+                                }
+                                let mut bind = None;
+                                egui::ComboBox::from_id_salt(("bind", i))
+                                    .selected_text("Bind…")
+                                    .show_ui(ui, |ui| {
+                                        for key in keys.iter() {
+                                            if ui.selectable_label(false, key).clicked() {
+                                                bind = Some(key);
+                                            }
+                                        }
+                                    });
+                                if let Some(key) = bind {
+                                    settings.bind(i, key);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        if self.app_config.read().unwrap().show_status == Some(YesOrNo::Yes) {
+            egui::Area::new("status".into())
+                .anchor(egui::Align2::LEFT_BOTTOM, [4.0, -4.0])
+                .interactable(false)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        for line in self.status.read().lines() {
+                            ui.small(line);
+                        }
+                    });
+                });
+        }
+        egui::containers::Window::new("Split Log")
+            .open(&mut self.show_split_log)
+            .resizable(true)
+            .collapsible(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let mut split_log = self.split_log.write();
+                if split_log.is_empty() {
+                    ui.label("The autosplitter hasn't split yet this session.");
+                }
+                for entry in split_log.iter() {
+                    ui.label(entry.to_string());
+                }
+                if ui.button("Clear").clicked() {
+                    split_log.clear();
+                }
+            });
         ctx.input(|i| {
             let scroll_delta = i.raw_scroll_delta;
             if scroll_delta.y > 0.0 {
@@ -882,9 +1712,7 @@ impl eframe::App for LiveSplitCoreRenderer {
                             // TODO: fix this unwrap
                             self.timer.write().unwrap().reset(true).ok();
                             if config.use_autosplitter == Some(YesOrNo::Yes) {
-                                self.thread_chan
-                                    .try_send(ThreadEvent::TimerReset)
-                                    .unwrap_or(());
+                                self.thread_chan.send(ThreadEvent::TimerReset).unwrap_or(());
                             }
                         }
                     }
@@ -926,14 +1754,89 @@ impl eframe::App for LiveSplitCoreRenderer {
     }
 }
 
-pub fn app_init(
-    app: &mut LiveSplitCoreRenderer,
-    sync_receiver: std::sync::mpsc::Receiver<ThreadEvent>,
-    cc: &eframe::CreationContext,
-) {
+/// Connect to the configured usb2snes server and attach to the preferred
+/// device, or to the first one it has when that one isn't there. Every
+/// device usb2snes lists is put in `devices`, for picking another one.
+/// Returns the device's name and what it says about itself along with
+/// the client.
+fn connect_to_device(
+    config: &AppConfig,
+    devices: &RwLock<Vec<String>>,
+) -> std::result::Result<(SyncClient, String, Infos), Box<dyn std::error::Error>> {
+    let preferred = config.device.as_deref();
+    let mut client = SyncClient::connect_to(
+        config.usb2snes_scheme.unwrap_or_default(),
+        config.usb2snes_host.as_deref().unwrap_or("localhost"),
+        config.usb2snes_port,
+    )?;
+    client.set_name("annelid")?;
+    println!("Server version is {:?}", client.app_version()?);
+    let found: Vec<String> = client
+        .list_device()?
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    *devices.write() = found.clone();
+    let device = found
+        .iter()
+        .find(|d| Some(d.as_str()) == preferred)
+        .or(found.first())
+        .ok_or("No devices present")?
+        .clone();
+    if let Some(preferred) = preferred.filter(|p| *p != device) {
+        println!("{} isn't present, falling back to {}", preferred, device);
+    }
+    println!("Using device: {}", device);
+    client.attach(&device)?;
+    println!("Connected.");
+    let infos = client.info()?;
+    println!("{:#?}", infos);
+    Ok((client, device, infos))
+}
+
+/// Whether the device refused a read outright, as opposed to the
+/// connection going wrong on the way
+fn is_unsupported(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<USB2SnesError>(),
+        Some(USB2SnesError::Unsupported { .. })
+    )
+}
+
+/// Make the timer's game time follow the game's own timer, the way an ASL
+/// script does when `isLoading` always returns true: game time is paused so
+/// it never runs on its own between polls, and set outright on each one.
+fn sync_game_time(timer: &mut Timer, game_time: TimeSpan) {
+    match timer.current_phase() {
+        TimerPhase::Running | TimerPhase::Paused => {}
+        TimerPhase::NotRunning | TimerPhase::Ended => return,
+    }
+    // Checked on every poll, since a reset unpauses game time
+    if !timer.is_game_time_initialized() {
+        timer.initialize_game_time().ok();
+    }
+    if !timer.is_game_time_paused() {
+        timer.pause_game_time().ok();
+    }
+    timer.set_game_time(game_time).ok();
+}
+
+pub fn app_init(app: &mut LiveSplitCoreRenderer, cc: &eframe::CreationContext) {
     let context = cc.egui_ctx.clone();
     context.set_visuals(egui::Visuals::dark());
     app.load_app_config();
+    let game = app.app_config.read().unwrap().game.unwrap_or_default();
+    app.set_autosplitter_choice(AutoSplitterChoice::Game(game));
+    let definition = app.app_config.read().unwrap().recent_definition.clone();
+    if let Some(definition) = definition {
+        messagebox_on_error(|| {
+            use anyhow::Context;
+            app.load_definition(std::path::Path::new(&definition))
+                .with_context(|| {
+                    format!("Failed to load autosplitter definition \"{}\"", definition)
+                })
+        });
+    }
     if app.app_config.read().unwrap().global_hotkeys == Some(YesOrNo::Yes) {
         messagebox_on_error(|| app.enable_global_hotkeys());
     }
@@ -943,12 +1846,6 @@ pub fn app_init(
         .unwrap()
         .frame_rate
         .unwrap_or(DEFAULT_FRAME_RATE);
-    let polling_rate = app
-        .app_config
-        .read()
-        .unwrap()
-        .polling_rate
-        .unwrap_or(DEFAULT_POLLING_RATE);
     // This thread is essentially just a refresh rate timer
     // it ensures that the gui thread is redrawn at the requested frame_rate,
     // possibly more often.
@@ -964,82 +1861,7 @@ pub fn app_init(
         // TODO: fix this unwrap
         .unwrap();
 
-    // The timer, settings, and app_config are all behind
-    // something equivalent to Arc<RwLock<_>> so it's safe
-    // to clone them and pass the clone between threads.
-    let timer = app.timer.clone();
-    let settings = app.settings.clone();
-    let app_config = app.app_config.clone();
-    // This thread deals with polling the SNES at a fixed rate.
-    if app_config.read().unwrap().use_autosplitter == Some(YesOrNo::Yes) {
-        let _snes_polling_thread = ThreadBuilder::default()
-            .name("SNES Polling Thread".to_owned())
-            // We could change this thread priority, but we probably
-            // should leave it at the default to make sure we get timely
-            // polling of SNES state
-            .spawn(move |_| loop {
-                let latency = Arc::new(RwLock::new((0.0, 0.0)));
-                print_on_error(|| -> std::result::Result<(), Box<dyn std::error::Error>> {
-                    let mut client = crate::usb2snes::SyncClient::connect()?;
-                    client.set_name("annelid")?;
-                    println!("Server version is {:?}", client.app_version()?);
-                    let mut devices = client.list_device()?.to_vec();
-                    if devices.len() != 1 {
-                        if devices.is_empty() {
-                            Err("No devices present")?;
-                        } else {
-                            Err(format!("You need to select a device: {:#?}", devices))?;
-                        }
-                    }
-                    let device = devices.pop().ok_or("Device list was empty")?;
-                    println!("Using device: {}", device);
-                    client.attach(&device)?;
-                    println!("Connected.");
-                    println!("{:#?}", client.info()?);
-                    let mut snes = SNESState::new();
-                    loop {
-                        let summary = snes.fetch_all(&mut client, &settings.read())?;
-                        if summary.start {
-                            // TODO: fix this unwrap
-                            timer.write().unwrap().start().ok();
-                        }
-                        if summary.reset
-                            && app_config.read().unwrap().reset_timer_on_game_reset
-                                == Some(YesOrNo::Yes)
-                        {
-                            // TODO: fix this unwrap
-                            timer.write().unwrap().reset(true).ok();
-                        }
-                        if summary.split {
-                            timer
-                                .write()
-                                .unwrap()
-                                .set_game_time(snes.gametime_to_seconds())
-                                .ok();
-                            // TODO: fix this unwrap
-                            timer.write().unwrap().split().ok();
-                        }
-                        {
-                            *latency.write() = (summary.latency_average, summary.latency_stddev);
-                        }
-                        // If the timer gets reset, we need to make a fresh snes state
-                        if let Ok(ThreadEvent::TimerReset) = sync_receiver.try_recv() {
-                            snes = SNESState::new();
-                            //Reset the snes
-                            if app_config.read().unwrap().reset_game_on_timer_reset
-                                == Some(YesOrNo::Yes)
-                            {
-                                client.reset()?;
-                            }
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(
-                            (1000.0 / polling_rate) as u64,
-                        ));
-                    }
-                });
-                std::thread::sleep(std::time::Duration::from_millis(1000));
-            })
-            //TODO: fix this unwrap
-            .unwrap();
+    if app.app_config.read().unwrap().use_autosplitter == Some(YesOrNo::Yes) {
+        messagebox_on_error(|| app.start_autosplitter());
     }
 }
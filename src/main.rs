@@ -3,6 +3,7 @@
 extern crate lazy_static;
 pub mod autosplitters;
 pub mod config;
+pub mod evaluate;
 pub mod hotkey;
 pub mod livesplit_renderer;
 pub mod routes;
@@ -10,7 +11,6 @@ pub mod usb2snes;
 pub mod utils;
 pub mod widget;
 
-use autosplitters::supermetroid::Settings;
 use clap::Parser;
 use eframe::egui;
 use livesplit_core::layout::{ComponentSettings, LayoutSettings};
@@ -55,7 +55,14 @@ fn customize_timer(timer: &mut livesplit_core::component::timer::Settings) {
 
 fn main() -> std::result::Result<(), Box<dyn Error>> {
     let cli_config = AppConfig::parse();
-    let settings = Settings::new();
+    if let Some(Command::Evaluate(args)) = &cli_config.command {
+        let (segments, splits) = evaluate::evaluate(args)?;
+        if !evaluate::print_table(&segments, &splits) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let settings = cli_config.game.unwrap_or_default().default_settings();
     let settings = Arc::new(RwLock::new(settings));
     let mut run = Run::default();
     run.push_segment(Segment::new(""));
@@ -74,9 +81,6 @@ fn main() -> std::result::Result<(), Box<dyn Error>> {
     //customize_layout(&mut layout_settings);
     let layout = Layout::from_settings(layout_settings);
 
-    use std::sync::mpsc::sync_channel;
-    let (sync_sender, sync_receiver) = sync_channel(1);
-
     let project_dirs = directories::ProjectDirs::from("", "", "annelid")
         .ok_or("Unable to computer configuration directory")?;
     println!("project_dirs = {:#?}", project_dirs);
@@ -84,20 +88,13 @@ fn main() -> std::result::Result<(), Box<dyn Error>> {
     let preference_dir = project_dirs.preference_dir();
     std::fs::create_dir_all(preference_dir)?;
 
-    let mut app = LiveSplitCoreRenderer::new(
-        timer,
-        layout,
-        settings,
-        sync_sender,
-        project_dirs,
-        cli_config,
-    );
+    let mut app = LiveSplitCoreRenderer::new(timer, layout, settings, project_dirs, cli_config);
 
     eframe::run_native(
         "Annelid",
         options,
         Box::new(move |cc| {
-            livesplit_renderer::app_init(&mut app, sync_receiver, cc);
+            livesplit_renderer::app_init(&mut app, cc);
             Ok(Box::new(app))
         }),
     )?;
@@ -1 +1,204 @@
+//! Route templates: the splits for a category together with the
+//! autosplitter settings and segment bindings that split them.
+//!
+//! Besides the templates built in to annelid, templates can be written as
+//! TOML files. For example:
+//!
+//! ```toml
+//! category = "Reverse Boss Order"
+//! # Optional, defaults to Super Metroid
+//! game = "SuperMetroid"
+//! # Settings to change from the game's defaults
+//! enable = ["miniBosses", "ceresRidley", "kraid", "phantoon", "draygon"]
+//! disable = []
+//! # Only split on each segment's own settings (the default)
+//! ordered = true
+//!
+//! [[segments]]
+//! name = "Ceres Ridley"
+//! split = ["ceresRidley"]
+//!
+//! [[segments]]
+//! name = "Ridley"
+//! split = ["ridley"]
+//! ```
+//!
+//! Template files are picked up from the `routes` directory next to
+//! annelid's `settings.toml`, or can be opened directly.
+
 pub mod supermetroid;
+
+use crate::autosplitters::{Game, Settings};
+use livesplit_core::{Run, Segment};
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// A run ready to go: splits, and settings that autosplit them
+pub struct Route {
+    pub game: Game,
+    pub settings: Settings,
+    pub run: Run,
+}
+
+impl Route {
+    fn from_parts(game: Game, (settings, mut run): (Settings, Run)) -> Route {
+        // Nothing has been saved yet
+        run.mark_as_modified();
+        Route {
+            game,
+            settings,
+            run,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Route, Box<dyn Error>> {
+        let file: RouteFile = toml::from_str(&std::fs::read_to_string(path)?)?;
+        file.into_route()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteFile {
+    category: String,
+    #[serde(default)]
+    game: Game,
+    #[serde(default)]
+    enable: Vec<String>,
+    #[serde(default)]
+    disable: Vec<String>,
+    #[serde(default = "default_ordered")]
+    ordered: bool,
+    segments: Vec<SegmentFile>,
+}
+
+fn default_ordered() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SegmentFile {
+    name: String,
+    #[serde(default)]
+    split: Vec<String>,
+}
+
+impl RouteFile {
+    fn into_route(self) -> Result<Route, Box<dyn Error>> {
+        if self.segments.is_empty() {
+            Err("A route needs at least one segment")?;
+        }
+        let mut settings = self.game.default_settings();
+        let keys = settings.keys();
+        let check = |key: &String| -> Result<(), Box<dyn Error>> {
+            if keys.contains(key) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "\"{}\" is not a {} autosplitter setting",
+                    key,
+                    self.game.name()
+                )
+                .into())
+            }
+        };
+        for key in self.enable.iter() {
+            check(key)?;
+            *settings.lookup_mut(key) = true;
+        }
+        for key in self.disable.iter() {
+            check(key)?;
+            *settings.lookup_mut(key) = false;
+        }
+        settings.set_ordered(self.ordered);
+        let mut run = Run::new();
+        run.set_game_name(self.game.name());
+        run.set_category_name(self.category.as_str());
+        for (i, segment) in self.segments.iter().enumerate() {
+            for key in segment.split.iter() {
+                check(key)?;
+                settings.bind(i, key);
+            }
+            run.push_segment(Segment::new(segment.name.as_str()));
+        }
+        Ok(Route::from_parts(self.game, (settings, run)))
+    }
+}
+
+/// A route template, which can be listed without building the route
+pub enum Template {
+    Builtin {
+        id: &'static str,
+        name: &'static str,
+        build: fn() -> Route,
+    },
+    File(PathBuf),
+}
+
+impl Template {
+    /// What to pass to `--route` for this template
+    pub fn id(&self) -> String {
+        match self {
+            Template::Builtin { id, .. } => id.to_string(),
+            Template::File(path) => path
+                .file_stem()
+                .map_or(String::new(), |s| s.to_string_lossy().into_owned()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Template::Builtin { name, .. } => name.to_string(),
+            Template::File(_) => self.id(),
+        }
+    }
+
+    pub fn build(&self) -> Result<Route, Box<dyn Error>> {
+        match self {
+            Template::Builtin { build, .. } => Ok(build()),
+            Template::File(path) => Route::load(path),
+        }
+    }
+}
+
+fn builtin() -> Vec<Template> {
+    vec![
+        Template::Builtin {
+            id: "hundo",
+            name: "Super Metroid 100%",
+            build: || Route::from_parts(Game::SuperMetroid, supermetroid::hundo()),
+        },
+        Template::Builtin {
+            id: "kpdr",
+            name: "Super Metroid KPDR",
+            build: || Route::from_parts(Game::SuperMetroid, supermetroid::anypercent()),
+        },
+    ]
+}
+
+/// The built in templates, followed by the template files in `dir`
+pub fn templates(dir: &Path) -> Vec<Template> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    let mut templates = builtin();
+    templates.extend(files.into_iter().map(Template::File));
+    templates
+}
+
+/// Build the route for a template id (see `Template::id`), or else treat
+/// `id_or_path` as the path of a template file
+pub fn find(id_or_path: &str, dir: &Path) -> Result<Route, Box<dyn Error>> {
+    match templates(dir).iter().find(|t| t.id() == id_or_path) {
+        Some(template) => template.build(),
+        None => Route::load(Path::new(id_or_path)),
+    }
+}
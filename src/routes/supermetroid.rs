@@ -1,172 +1,368 @@
-use crate::autosplitters::supermetroid::Settings;
+use crate::autosplitters::supermetroid::{default_settings, split_on_anypercent, split_on_hundo};
+use crate::autosplitters::Settings;
 use livesplit_core::{Run, Segment};
 
+/// Add a segment that only splits on the given settings (in ordered mode)
+fn push_segment(run: &mut Run, settings: &mut Settings, name: &str, keys: &[&str]) {
+    for key in keys {
+        settings.bind(run.len(), key);
+    }
+    run.push_segment(Segment::new(name));
+}
+
 pub fn hundo() -> (Settings, livesplit_core::Run) {
-    let mut settings = Settings::new();
-    settings.split_on_hundo();
+    let mut settings = default_settings();
+    split_on_hundo(&mut settings);
+    settings.set_ordered(true);
     let mut run = Run::new();
     run.set_game_name("Super Metroid");
     run.set_category_name("100%");
-    run.push_segment(Segment::new("ceresRidley"));
-    run.push_segment(Segment::new("ceresEscape"));
-    run.push_segment(Segment::new("morphBall"));
-    run.push_segment(Segment::new("firstMissile"));
-    run.push_segment(Segment::new("bomb"));
-    run.push_segment(Segment::new("bomb torizo"));
-    run.push_segment(Segment::new("terminator tank"));
-    run.push_segment(Segment::new("early supers"));
-    run.push_segment(Segment::new("missile behind reserve"));
-    run.push_segment(Segment::new("missile behind behind"));
-    run.push_segment(Segment::new("brin reserve"));
-    run.push_segment(Segment::new("missile quick fall"));
-    run.push_segment(Segment::new("charge beam"));
-    run.push_segment(Segment::new("spazer"));
-    run.push_segment(Segment::new("kraid"));
-    run.push_segment(Segment::new("varia"));
-    run.push_segment(Segment::new("beetom tank"));
-    run.push_segment(Segment::new("hjb tank"));
-    run.push_segment(Segment::new("boots"));
-    run.push_segment(Segment::new("missile boots"));
-    run.push_segment(Segment::new("missile cathedral"));
-    run.push_segment(Segment::new("missile speedbooster"));
-    run.push_segment(Segment::new("speedbooster"));
-    run.push_segment(Segment::new("missile wave"));
-    run.push_segment(Segment::new("wave"));
-    run.push_segment(Segment::new("croc tank"));
-    run.push_segment(Segment::new("crocomire"));
-    run.push_segment(Segment::new("croc pb"));
-    run.push_segment(Segment::new("grapple"));
-    run.push_segment(Segment::new("missile grapple"));
-    run.push_segment(Segment::new("missile swag dboost"));
-    run.push_segment(Segment::new("missile croc escape"));
-    run.push_segment(Segment::new("missile alpha"));
-    run.push_segment(Segment::new("alpha pb"));
-    run.push_segment(Segment::new("beta pb"));
-    run.push_segment(Segment::new("missile moat"));
-    run.push_segment(Segment::new("missile spooky"));
-    run.push_segment(Segment::new("phantoon"));
-    run.push_segment(Segment::new("right super"));
-    run.push_segment(Segment::new("left super"));
-    run.push_segment(Segment::new("ws tank"));
-    run.push_segment(Segment::new("missile attic"));
-    run.push_segment(Segment::new("missile sky"));
-    run.push_segment(Segment::new("missile tunnel"));
-    run.push_segment(Segment::new("missile bowling"));
-    run.push_segment(Segment::new("ws reserve"));
-    run.push_segment(Segment::new("gravity"));
-    run.push_segment(Segment::new("missile mermaid"));
-    run.push_segment(Segment::new("criteria pb"));
-    run.push_segment(Segment::new("gauntlet tank"));
-    run.push_segment(Segment::new("gauntlet missile1"));
-    run.push_segment(Segment::new("gauntlet missile2"));
-    run.push_segment(Segment::new("etecoons tank"));
-    run.push_segment(Segment::new("etecoons supers"));
-    run.push_segment(Segment::new("etecoons pb"));
-    run.push_segment(Segment::new("missile pink brin top"));
-    run.push_segment(Segment::new("mission impossible pb"));
-    run.push_segment(Segment::new("wave gate tank"));
-    run.push_segment(Segment::new("spospo super"));
-    run.push_segment(Segment::new("missile pink brin bottom"));
-    run.push_segment(Segment::new("waterways tank"));
-    run.push_segment(Segment::new("missile greenhills"));
-    run.push_segment(Segment::new("maridia tube"));
-    run.push_segment(Segment::new("missile mainstreet"));
-    run.push_segment(Segment::new("momma turtle tank"));
-    run.push_segment(Segment::new("missile momma turtle"));
-    run.push_segment(Segment::new("crab supers"));
-    run.push_segment(Segment::new("missile beach"));
-    run.push_segment(Segment::new("wateringhole supers"));
-    run.push_segment(Segment::new("missile wateringhole"));
-    run.push_segment(Segment::new("botwoon"));
-    run.push_segment(Segment::new("botwoon tank"));
-    run.push_segment(Segment::new("missile precious room"));
-    run.push_segment(Segment::new("draygon"));
-    run.push_segment(Segment::new("spacejump"));
-    run.push_segment(Segment::new("missile right sandpit"));
-    run.push_segment(Segment::new("right sandpit pb"));
-    run.push_segment(Segment::new("springball"));
-    run.push_segment(Segment::new("plasma"));
-    run.push_segment(Segment::new("missile aqueduct"));
-    run.push_segment(Segment::new("aqueduct super"));
-    run.push_segment(Segment::new("maridia reserve"));
-    run.push_segment(Segment::new("missile left sandpit"));
-    run.push_segment(Segment::new("missile wharehouse"));
-    run.push_segment(Segment::new("ice"));
-    run.push_segment(Segment::new("missile crumble shaft"));
-    run.push_segment(Segment::new("missile gt"));
-    run.push_segment(Segment::new("gt super"));
-    run.push_segment(Segment::new("gt"));
-    run.push_segment(Segment::new("screwattack"));
-    run.push_segment(Segment::new("missile mickeymouse"));
-    run.push_segment(Segment::new("pbs of shame"));
-    run.push_segment(Segment::new("ridley"));
-    run.push_segment(Segment::new("ridley tank"));
-    run.push_segment(Segment::new("firefleas tank"));
-    run.push_segment(Segment::new("missile hota"));
-    run.push_segment(Segment::new("jail pb"));
-    run.push_segment(Segment::new("missile ffz"));
-    run.push_segment(Segment::new("missile norfair reserve"));
-    run.push_segment(Segment::new("missile norfair reserve"));
-    run.push_segment(Segment::new("norfair reserve"));
-    run.push_segment(Segment::new("missile bubble mountain"));
-    run.push_segment(Segment::new("xr_tech"));
-    run.push_segment(Segment::new("retro pb"));
-    run.push_segment(Segment::new("brin ceiling tank"));
-    run.push_segment(Segment::new("missile billy mays 1"));
-    run.push_segment(Segment::new("missile billy mays 2"));
-    run.push_segment(Segment::new("missile retro brin"));
-    run.push_segment(Segment::new("missile old mb"));
-    run.push_segment(Segment::new("climb supers"));
-    run.push_segment(Segment::new("missile dental plan"));
-    run.push_segment(Segment::new("g4 room"));
-    run.push_segment(Segment::new("the baby!"));
-    run.push_segment(Segment::new("mb1"));
-    run.push_segment(Segment::new("mb2"));
-    run.push_segment(Segment::new("mb3"));
-    run.push_segment(Segment::new(".done"));
+    push_segment(&mut run, &mut settings, "ceresRidley", &["ceresRidley"]);
+    push_segment(&mut run, &mut settings, "ceresEscape", &["ceresEscape"]);
+    push_segment(&mut run, &mut settings, "morphBall", &["morphBall"]);
+    push_segment(&mut run, &mut settings, "firstMissile", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "bomb", &["bomb"]);
+    push_segment(&mut run, &mut settings, "bomb torizo", &["bombTorizo"]);
+    push_segment(&mut run, &mut settings, "terminator tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "early supers", &["allSupers"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile behind reserve",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile behind behind",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "brin reserve", &["reserveTanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile quick fall",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "charge beam", &["chargeBeam"]);
+    push_segment(&mut run, &mut settings, "spazer", &["spazer"]);
+    push_segment(&mut run, &mut settings, "kraid", &["kraid"]);
+    push_segment(&mut run, &mut settings, "varia", &["variaSuit"]);
+    push_segment(&mut run, &mut settings, "beetom tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "hjb tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "boots", &["hiJump"]);
+    push_segment(&mut run, &mut settings, "missile boots", &["allMissiles"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile cathedral",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile speedbooster",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "speedbooster", &["speedBooster"]);
+    push_segment(&mut run, &mut settings, "missile wave", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "wave", &["wave"]);
+    push_segment(&mut run, &mut settings, "croc tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "crocomire", &["crocomire"]);
+    push_segment(&mut run, &mut settings, "croc pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "grapple", &["grapple"]);
+    push_segment(&mut run, &mut settings, "missile grapple", &["allMissiles"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile swag dboost",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile croc escape",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "missile alpha", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "alpha pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "beta pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "missile moat", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "missile spooky", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "phantoon", &["phantoon"]);
+    push_segment(&mut run, &mut settings, "right super", &["allSupers"]);
+    push_segment(&mut run, &mut settings, "left super", &["allSupers"]);
+    push_segment(&mut run, &mut settings, "ws tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "missile attic", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "missile sky", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "missile tunnel", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "missile bowling", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "ws reserve", &["reserveTanks"]);
+    push_segment(&mut run, &mut settings, "gravity", &["gravSuit"]);
+    push_segment(&mut run, &mut settings, "missile mermaid", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "criteria pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "gauntlet tank", &["allETanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "gauntlet missile1",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "gauntlet missile2",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "etecoons tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "etecoons supers", &["allSupers"]);
+    push_segment(&mut run, &mut settings, "etecoons pb", &["allPowerBombs"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile pink brin top",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "mission impossible pb",
+        &["allPowerBombs"],
+    );
+    push_segment(&mut run, &mut settings, "wave gate tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "spospo super", &["allSupers"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile pink brin bottom",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "waterways tank", &["allETanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile greenhills",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "maridia tube", &["tubeBroken"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile mainstreet",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "momma turtle tank", &["allETanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile momma turtle",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "crab supers", &["allSupers"]);
+    push_segment(&mut run, &mut settings, "missile beach", &["allMissiles"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "wateringhole supers",
+        &["allSupers"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile wateringhole",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "botwoon", &["botwoon"]);
+    push_segment(&mut run, &mut settings, "botwoon tank", &["allETanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile precious room",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "draygon", &["draygon"]);
+    push_segment(&mut run, &mut settings, "spacejump", &["spaceJump"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile right sandpit",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "right sandpit pb",
+        &["allPowerBombs"],
+    );
+    push_segment(&mut run, &mut settings, "springball", &["springBall"]);
+    push_segment(&mut run, &mut settings, "plasma", &["plasma"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile aqueduct",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "aqueduct super", &["allSupers"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "maridia reserve",
+        &["reserveTanks"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile left sandpit",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile wharehouse",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "ice", &["ice"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile crumble shaft",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "missile gt", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "gt super", &["allSupers"]);
+    push_segment(&mut run, &mut settings, "gt", &["goldenTorizo"]);
+    push_segment(&mut run, &mut settings, "screwattack", &["screwAttack"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile mickeymouse",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "pbs of shame", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "ridley", &["ridley"]);
+    push_segment(&mut run, &mut settings, "ridley tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "firefleas tank", &["allETanks"]);
+    push_segment(&mut run, &mut settings, "missile hota", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "jail pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "missile ffz", &["allMissiles"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile norfair reserve",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile norfair reserve",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "norfair reserve",
+        &["reserveTanks"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile bubble mountain",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "xr_tech", &["xray"]);
+    push_segment(&mut run, &mut settings, "retro pb", &["allPowerBombs"]);
+    push_segment(&mut run, &mut settings, "brin ceiling tank", &["allETanks"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile billy mays 1",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile billy mays 2",
+        &["allMissiles"],
+    );
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile retro brin",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "missile old mb", &["allMissiles"]);
+    push_segment(&mut run, &mut settings, "climb supers", &["allSupers"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "missile dental plan",
+        &["allMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "g4 room", &["goldenFour"]);
+    push_segment(&mut run, &mut settings, "the baby!", &["babyMetroidRoom"]);
+    push_segment(&mut run, &mut settings, "mb1", &["mb1"]);
+    push_segment(&mut run, &mut settings, "mb2", &["mb2"]);
+    push_segment(&mut run, &mut settings, "mb3", &["mb3"]);
+    push_segment(&mut run, &mut settings, ".done", &["rtaFinish"]);
     (settings, run)
 }
 
 pub fn anypercent() -> (Settings, livesplit_core::Run) {
-    let mut settings = Settings::new();
-    settings.split_on_anypercent();
+    let mut settings = default_settings();
+    split_on_anypercent(&mut settings);
+    settings.set_ordered(true);
     let mut run = Run::new();
     run.set_game_name("Super Metroid");
     run.set_category_name("KPDR");
-    run.push_segment(Segment::new("ceresRidley"));
-    run.push_segment(Segment::new("ceresEscape"));
-    run.push_segment(Segment::new("morphBall"));
-    run.push_segment(Segment::new("firstMissile"));
-    run.push_segment(Segment::new("bomb"));
-    run.push_segment(Segment::new("bomb torizo"));
-    run.push_segment(Segment::new("terminator tank"));
-    run.push_segment(Segment::new("early supers"));
-    run.push_segment(Segment::new("charge missile"));
-    run.push_segment(Segment::new("charge beam"));
-    run.push_segment(Segment::new("kraid"));
-    run.push_segment(Segment::new("varia"));
-    run.push_segment(Segment::new("hjb tank"));
-    run.push_segment(Segment::new("boots"));
-    run.push_segment(Segment::new("speedbooster"));
-    run.push_segment(Segment::new("missile wave"));
-    run.push_segment(Segment::new("wave"));
-    run.push_segment(Segment::new("alpha pb"));
-    run.push_segment(Segment::new("phantoon"));
-    run.push_segment(Segment::new("left super"));
-    run.push_segment(Segment::new("gravity"));
-    run.push_segment(Segment::new("maridia tube"));
-    run.push_segment(Segment::new("botwoon"));
-    run.push_segment(Segment::new("botwoon tank"));
-    run.push_segment(Segment::new("draygon"));
-    run.push_segment(Segment::new("spacejump"));
-    run.push_segment(Segment::new("plasma"));
-    run.push_segment(Segment::new("ice"));
-    run.push_segment(Segment::new("ridley"));
-    run.push_segment(Segment::new("g4 room"));
-    run.push_segment(Segment::new("the baby!"));
-    run.push_segment(Segment::new("mb1"));
-    run.push_segment(Segment::new("mb2"));
-    run.push_segment(Segment::new("mb3"));
-    run.push_segment(Segment::new(".done"));
+    push_segment(&mut run, &mut settings, "ceresRidley", &["ceresRidley"]);
+    push_segment(&mut run, &mut settings, "ceresEscape", &["ceresEscape"]);
+    push_segment(&mut run, &mut settings, "morphBall", &["morphBall"]);
+    push_segment(&mut run, &mut settings, "firstMissile", &["firstMissile"]);
+    push_segment(&mut run, &mut settings, "bomb", &["bomb"]);
+    push_segment(&mut run, &mut settings, "bomb torizo", &["bombTorizo"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "terminator tank",
+        &["terminatorETank"],
+    );
+    push_segment(&mut run, &mut settings, "early supers", &["firstSuper"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "charge missile",
+        &["chargeMissiles"],
+    );
+    push_segment(&mut run, &mut settings, "charge beam", &["chargeBeam"]);
+    push_segment(&mut run, &mut settings, "kraid", &["kraid"]);
+    push_segment(&mut run, &mut settings, "varia", &["variaSuit"]);
+    push_segment(&mut run, &mut settings, "hjb tank", &["hiJumpETank"]);
+    push_segment(&mut run, &mut settings, "boots", &["hiJump"]);
+    push_segment(&mut run, &mut settings, "speedbooster", &["speedBooster"]);
+    push_segment(&mut run, &mut settings, "missile wave", &["waveMissiles"]);
+    push_segment(&mut run, &mut settings, "wave", &["wave"]);
+    push_segment(&mut run, &mut settings, "alpha pb", &["firstPowerBomb"]);
+    push_segment(&mut run, &mut settings, "phantoon", &["phantoon"]);
+    push_segment(
+        &mut run,
+        &mut settings,
+        "left super",
+        &["wreckedShipLeftSupers"],
+    );
+    push_segment(&mut run, &mut settings, "gravity", &["gravSuit"]);
+    push_segment(&mut run, &mut settings, "maridia tube", &["tubeBroken"]);
+    push_segment(&mut run, &mut settings, "botwoon", &["botwoon"]);
+    push_segment(&mut run, &mut settings, "botwoon tank", &["botwoonETank"]);
+    push_segment(&mut run, &mut settings, "draygon", &["draygon"]);
+    push_segment(&mut run, &mut settings, "spacejump", &["spaceJump"]);
+    push_segment(&mut run, &mut settings, "plasma", &["plasma"]);
+    push_segment(&mut run, &mut settings, "ice", &["ice"]);
+    push_segment(&mut run, &mut settings, "ridley", &["ridley"]);
+    push_segment(&mut run, &mut settings, "g4 room", &["goldenFour"]);
+    push_segment(&mut run, &mut settings, "the baby!", &["babyMetroidRoom"]);
+    push_segment(&mut run, &mut settings, "mb1", &["mb1"]);
+    push_segment(&mut run, &mut settings, "mb2", &["mb2"]);
+    push_segment(&mut run, &mut settings, "mb3", &["mb3"]);
+    push_segment(&mut run, &mut settings, ".done", &["rtaFinish"]);
     (settings, run)
 }
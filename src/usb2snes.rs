@@ -22,22 +22,25 @@
 
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::net::TcpStream;
+use std::fmt;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
 use strum_macros::Display;
 use tungstenite::protocol::WebSocket;
 use tungstenite::stream::MaybeTlsStream;
-use tungstenite::Message;
+use tungstenite::{HandshakeError, Message};
 
 use std::borrow::Cow;
 use std::rc::Rc;
 
-#[derive(Display, Debug)]
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum Command {
     AppVersion,
     Name,
     DeviceList,
     Attach,
+    Close,
     Info,
     Boot,
     Reset,
@@ -48,10 +51,13 @@ pub enum Command {
     GetFile,
     Rename,
     Remove,
+    MakeDir,
 
     GetAddress,
+    PutAddress,
+    PutIPS,
 }
-#[derive(Display, Debug)]
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
 pub enum Space {
     None,
@@ -59,12 +65,195 @@ pub enum Space {
     CMD,
 }
 
-#[derive(Debug)]
+/// Something a device reports it can't do, among its [`Infos`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Flag {
+    /// No List, GetFile, PutFile, Rename, Remove or MakeDir
+    NoFileCmd,
+    /// No Boot, Menu or Reset
+    NoControlCmd,
+    NoRomRead,
+    NoRomWrite,
+    Other(Rc<str>),
+}
+
+impl Flag {
+    pub fn parse(flag: &str) -> Flag {
+        match flag {
+            "NO_FILE_CMD" => Flag::NoFileCmd,
+            "NO_CONTROL_CMD" => Flag::NoControlCmd,
+            "NO_ROM_READ" => Flag::NoRomRead,
+            "NO_ROM_WRITE" => Flag::NoRomWrite,
+            other => Flag::Other(other.into()),
+        }
+    }
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Flag::NoFileCmd => write!(f, "NO_FILE_CMD"),
+            Flag::NoControlCmd => write!(f, "NO_CONTROL_CMD"),
+            Flag::NoRomRead => write!(f, "NO_ROM_READ"),
+            Flag::NoRomWrite => write!(f, "NO_ROM_WRITE"),
+            Flag::Other(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Infos {
     pub version: Rc<str>,
     pub dev_type: Rc<str>,
     pub game: Rc<str>,
-    pub flags: Vec<Rc<str>>,
+    pub flags: Vec<Flag>,
+}
+
+impl Infos {
+    /// Read the reply to an Info request: the firmware version, the kind
+    /// of device, the running game, and then any flags.
+    pub fn parse(results: &[Rc<str>]) -> Result<Infos, USB2SnesError> {
+        match results {
+            [version, dev_type, game, flags @ ..] => Ok(Infos {
+                version: version.clone(),
+                dev_type: dev_type.clone(),
+                game: game.clone(),
+                flags: flags.iter().map(|flag| Flag::parse(flag)).collect(),
+            }),
+            _ => Err(USB2SnesError::Malformed(format!(
+                "Info needs at least 3 results, got {}",
+                results.len()
+            ))),
+        }
+    }
+
+    pub fn has(&self, flag: &Flag) -> bool {
+        self.flags.contains(flag)
+    }
+}
+
+/// Everything that can go wrong talking to usb2snes
+#[derive(Debug)]
+pub enum USB2SnesError {
+    /// None of the URLs tried would connect, with why for each one
+    Connect(Vec<(String, USB2SnesError)>),
+    /// The host name didn't resolve to anything
+    NoAddresses(String),
+    Io(std::io::Error),
+    WebSocket(Box<tungstenite::Error>),
+    Json(serde_json::Error),
+    /// Nothing arrived within [`TIMEOUT`]
+    Timeout,
+    Closed,
+    /// A reply that doesn't fit the request it answers
+    Malformed(String),
+    /// `cause` cut a binary reply short
+    Partial {
+        received: usize,
+        expected: usize,
+        cause: Box<USB2SnesError>,
+    },
+    Oversized {
+        received: usize,
+        expected: usize,
+    },
+    /// The attached device flagged the command as one it can't do
+    Unsupported {
+        command: Command,
+        flag: Flag,
+    },
+}
+
+impl fmt::Display for USB2SnesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            USB2SnesError::Connect(attempts) => {
+                write!(f, "Couldn't connect to ")?;
+                for (i, (url, e)) in attempts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    write!(f, "{} ({})", url, e)?;
+                }
+                Ok(())
+            }
+            USB2SnesError::NoAddresses(host) => write!(f, "{} has no addresses", host),
+            USB2SnesError::Io(e) => write!(f, "{}", e),
+            USB2SnesError::WebSocket(e) => write!(f, "{}", e),
+            USB2SnesError::Json(e) => write!(f, "Bad reply from usb2snes: {}", e),
+            USB2SnesError::Timeout => write!(
+                f,
+                "usb2snes didn't answer within {} ms",
+                TIMEOUT.as_millis()
+            ),
+            USB2SnesError::Closed => write!(f, "usb2snes closed the connection"),
+            USB2SnesError::Malformed(message) => {
+                write!(f, "Unexpected reply from usb2snes: {}", message)
+            }
+            USB2SnesError::Partial {
+                received,
+                expected,
+                cause,
+            } => write!(f, "{} after {} of {} bytes", cause, received, expected),
+            USB2SnesError::Oversized { received, expected } => write!(
+                f,
+                "usb2snes sent {} bytes when {} were asked for",
+                received, expected
+            ),
+            USB2SnesError::Unsupported { command, flag } => {
+                write!(f, "The device can't do {} ({})", command, flag)
+            }
+        }
+    }
+}
+
+impl Error for USB2SnesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            USB2SnesError::Io(e) => Some(e),
+            USB2SnesError::WebSocket(e) => Some(e.as_ref()),
+            USB2SnesError::Json(e) => Some(e),
+            USB2SnesError::Partial { cause, .. } => Some(cause.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for USB2SnesError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => USB2SnesError::Timeout,
+            _ => USB2SnesError::Io(e),
+        }
+    }
+}
+
+impl From<tungstenite::Error> for USB2SnesError {
+    fn from(e: tungstenite::Error) -> Self {
+        match e {
+            tungstenite::Error::Io(e) => e.into(),
+            tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+                USB2SnesError::Closed
+            }
+            e => USB2SnesError::WebSocket(Box::new(e)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for USB2SnesError {
+    fn from(e: serde_json::Error) -> Self {
+        USB2SnesError::Json(e)
+    }
+}
+
+impl<S: tungstenite::handshake::HandshakeRole> From<HandshakeError<S>> for USB2SnesError {
+    fn from(e: HandshakeError<S>) -> Self {
+        match e {
+            HandshakeError::Failure(e) => e.into(),
+            // The stream blocks, so this is only the read timeout running out
+            HandshakeError::Interrupted(_) => USB2SnesError::Timeout,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -73,7 +262,7 @@ struct USB2SnesQuery<'a> {
     Opcode: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     Space: Option<String>,
-    Flags: Vec<String>,
+    Flags: &'a [Cow<'a, str>],
     Operands: &'a [Cow<'a, str>],
 }
 
@@ -83,38 +272,170 @@ struct USB2SnesResult {
     Results: Rc<[Rc<str>]>,
 }
 
-#[derive(Eq, PartialEq)]
+impl USB2SnesResult {
+    /// The one result a command answers with
+    fn single(&self, command: Command) -> Result<Rc<str>, USB2SnesError> {
+        match &*self.Results {
+            [result] => Ok(result.clone()),
+            results => Err(USB2SnesError::Malformed(format!(
+                "{} needs 1 result, got {}",
+                command,
+                results.len()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
 pub enum USB2SnesFileType {
     File = 0,
     Dir = 1,
 }
 
+#[derive(Debug)]
 pub struct USB2SnesFileInfo {
     pub name: Rc<str>,
     pub file_type: USB2SnesFileType,
 }
 
+impl USB2SnesFileInfo {
+    /// Read the reply to a List request, which is a type and a name for
+    /// each entry
+    pub fn parse_list(results: &[Rc<str>]) -> Result<Vec<USB2SnesFileInfo>, USB2SnesError> {
+        let entries = results.chunks_exact(2);
+        if !entries.remainder().is_empty() {
+            return Err(USB2SnesError::Malformed(format!(
+                "List needs a type and a name for each entry, got {} results",
+                results.len()
+            )));
+        }
+        entries
+            .map(|entry| {
+                let file_type = match &*entry[0] {
+                    "1" => USB2SnesFileType::File,
+                    "0" => USB2SnesFileType::Dir,
+                    other => {
+                        return Err(USB2SnesError::Malformed(format!(
+                            "{:?} isn't a file type",
+                            other
+                        )))
+                    }
+                };
+                Ok(USB2SnesFileInfo {
+                    name: entry[1].clone(),
+                    file_type,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The most ranges a device will read in one request
+pub const MAX_OPERANDS: usize = 8;
+/// The longest range a device will read as part of a request for several
+pub const MAX_OPERAND_SIZE: usize = 0xFF;
+/// How much binary data goes in each frame of a write
+const CHUNK_SIZE: usize = 1024;
+/// Where ROM ends in the SNES space, as usb2snes lays it out. SRAM, WRAM
+/// and the rest come after.
+const ROM_END: u32 = 0xE00000;
+
+/// The port QUsb2Snes and SNI listen on
+pub const DEFAULT_PORT: u16 = 23074;
+/// The port the original usb2snes listened on, which QUsb2Snes and SNI
+/// still serve for older clients
+pub const LEGACY_PORT: u16 = 8080;
+
+/// How long to wait for usb2snes to accept a connection, or to answer a
+/// request, before giving up on it. Reads normally take a few frames, so
+/// a stalled server or a bumped cable is noticed well within a second.
+pub const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How far along the connection to usb2snes is, for showing to the user
+#[derive(Clone, Copy, Debug, Default, Display, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    /// Attached to a device, but not reading memory yet
+    Attached,
+    Polling,
+}
+
+#[derive(
+    clap::ValueEnum, Clone, Copy, Debug, Default, Display, Serialize, Deserialize, PartialEq, Eq,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum Scheme {
+    #[default]
+    Ws,
+    Wss,
+}
+
 pub struct SyncClient {
     client: WebSocket<MaybeTlsStream<TcpStream>>,
     devel: bool,
+    /// What the attached device said about itself, once asked
+    infos: Option<Infos>,
 }
 
 impl SyncClient {
-    pub fn connect() -> Result<SyncClient, Box<dyn Error>> {
-        Ok(SyncClient {
-            client: tungstenite::client::connect("ws://localhost:23074")?.0,
-            devel: false,
-        })
+    pub fn connect() -> Result<SyncClient, USB2SnesError> {
+        SyncClient::connect_to(Scheme::Ws, "localhost", None)
+    }
+
+    pub fn connect_with_devel() -> Result<SyncClient, USB2SnesError> {
+        let mut client = SyncClient::connect()?;
+        client.devel = true;
+        Ok(client)
     }
 
-    pub fn connect_with_devel() -> Result<SyncClient, Box<dyn Error>> {
-        Ok(SyncClient {
-            client: tungstenite::client::connect("ws://localhost:23074")?.0,
-            devel: true,
-        })
+    /// Connect to the server on `host`. Without a `port`, the current
+    /// port is tried first and then the legacy one.
+    pub fn connect_to(
+        scheme: Scheme,
+        host: &str,
+        port: Option<u16>,
+    ) -> Result<SyncClient, USB2SnesError> {
+        let ports = match port {
+            Some(port) => vec![port],
+            None => vec![DEFAULT_PORT, LEGACY_PORT],
+        };
+        let mut errors = vec![];
+        for port in ports {
+            let url = format!("{}://{}:{}", scheme, host, port);
+            match SyncClient::connect_url(&url, host, port) {
+                Ok(client) => return Ok(client),
+                Err(e) => errors.push((url, e)),
+            }
+        }
+        Err(USB2SnesError::Connect(errors))
+    }
+
+    fn connect_url(url: &str, host: &str, port: u16) -> Result<SyncClient, USB2SnesError> {
+        let mut error = USB2SnesError::NoAddresses(host.to_owned());
+        for address in (host, port).to_socket_addrs()? {
+            let stream = match TcpStream::connect_timeout(&address, TIMEOUT) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error = e.into();
+                    continue;
+                }
+            };
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            stream.set_nodelay(true)?;
+            let (client, _) = tungstenite::client_tls(url, stream)?;
+            return Ok(SyncClient {
+                client,
+                devel: false,
+                infos: None,
+            });
+        }
+        Err(error)
     }
 
-    fn send_command(&mut self, command: Command, args: &[Cow<str>]) -> Result<(), Box<dyn Error>> {
+    fn send_command(&mut self, command: Command, args: &[Cow<str>]) -> Result<(), USB2SnesError> {
         self.send_command_with_space(command, None, args)
     }
 
@@ -123,7 +444,7 @@ impl SyncClient {
         command: Command,
         space: Option<Space>,
         args: &[Cow<str>],
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), USB2SnesError> {
         if self.devel {
             println!("Send command : {:?}", command);
         }
@@ -131,7 +452,8 @@ impl SyncClient {
         let query = USB2SnesQuery {
             Opcode: command.to_string(),
             Space: nspace,
-            Flags: vec![],
+            // None of the commands sent here take any
+            Flags: &[],
             Operands: args,
         };
         let json = serde_json::to_string(&query)?;
@@ -143,14 +465,74 @@ impl SyncClient {
         Ok(self.client.send(message)?)
     }
 
-    fn get_reply(&mut self) -> Result<USB2SnesResult, Box<dyn Error>> {
-        let reply = self.client.read()?;
-        let mut textreply: String = String::from("");
-        match reply {
-            Message::Text(value) => {
-                textreply = value;
+    /// Send the data that follows a write command
+    fn send_binary(&mut self, data: &[u8]) -> Result<(), USB2SnesError> {
+        for chunk in data.chunks(CHUNK_SIZE) {
+            self.client.send(Message::binary(chunk))?;
+        }
+        Ok(())
+    }
+
+    /// Fail without sending anything when the attached device has said
+    /// it can't do `command`
+    fn require(&self, command: Command, flag: Flag) -> Result<(), USB2SnesError> {
+        match &self.infos {
+            Some(infos) if infos.has(&flag) => Err(USB2SnesError::Unsupported { command, flag }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The next message that isn't a ping or a pong. Running out of time
+    /// leaves the connection in an unknown state, so it has to be dropped.
+    fn read_message(&mut self) -> Result<Message, USB2SnesError> {
+        loop {
+            match self.client.read()? {
+                Message::Ping(_) | Message::Pong(_) => {}
+                Message::Close(_) => return Err(USB2SnesError::Closed),
+                message => return Ok(message),
             }
-            _ => Err("Error getting a reply")?,
+        }
+    }
+
+    /// Read `size` bytes of binary reply, which may come in several frames
+    fn read_binary(&mut self, size: usize) -> Result<Vec<u8>, USB2SnesError> {
+        let mut data: Vec<u8> = Vec::with_capacity(size);
+        while data.len() < size {
+            let message = self.read_message().map_err(|e| {
+                if data.is_empty() {
+                    e
+                } else {
+                    USB2SnesError::Partial {
+                        received: data.len(),
+                        expected: size,
+                        cause: Box::new(e),
+                    }
+                }
+            })?;
+            match message {
+                Message::Binary(bytes) => data.extend(&bytes),
+                Message::Text(text) => {
+                    return Err(USB2SnesError::Malformed(format!(
+                        "Expected {} bytes, got text: {}",
+                        size, text
+                    )))
+                }
+                _ => return Err(USB2SnesError::Malformed("Expected binary data".to_owned())),
+            }
+        }
+        if data.len() > size {
+            return Err(USB2SnesError::Oversized {
+                received: data.len(),
+                expected: size,
+            });
+        }
+        Ok(data)
+    }
+
+    fn get_reply(&mut self) -> Result<USB2SnesResult, USB2SnesError> {
+        let textreply = match self.read_message()? {
+            Message::Text(value) => value,
+            _ => return Err(USB2SnesError::Malformed("Expected a text reply".to_owned())),
         };
         if self.devel {
             println!("Reply:");
@@ -159,142 +541,185 @@ impl SyncClient {
         Ok(serde_json::from_str(&textreply)?)
     }
 
-    pub fn set_name(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+    pub fn set_name(&mut self, name: &str) -> Result<(), USB2SnesError> {
         self.send_command(Command::Name, &[Cow::Borrowed(name)])
     }
 
-    pub fn app_version(&mut self) -> Result<String, Box<dyn Error>> {
+    pub fn app_version(&mut self) -> Result<String, USB2SnesError> {
         self.send_command(Command::AppVersion, &[])?;
         let usbreply = self.get_reply()?;
-        Ok(usbreply.Results[0].to_string())
+        Ok(usbreply.single(Command::AppVersion)?.to_string())
     }
 
-    pub fn list_device(&mut self) -> Result<Rc<[Rc<str>]>, Box<dyn Error>> {
+    pub fn list_device(&mut self) -> Result<Rc<[Rc<str>]>, USB2SnesError> {
         self.send_command(Command::DeviceList, &[])?;
         let usbreply = self.get_reply()?;
         Ok(usbreply.Results)
     }
 
-    pub fn attach(&mut self, device: &str) -> Result<(), Box<dyn Error>> {
+    pub fn attach(&mut self, device: &str) -> Result<(), USB2SnesError> {
+        self.infos = None;
         self.send_command(Command::Attach, &[Cow::Borrowed(device)])
     }
 
-    pub fn info(&mut self) -> Result<Infos, Box<dyn Error>> {
+    /// Tell the server this client is done, so it lets go of the device
+    pub fn close(&mut self) -> Result<(), USB2SnesError> {
+        self.send_command(Command::Close, &[])
+    }
+
+    /// Ask the attached device about itself. Afterwards, commands it flags
+    /// as unsupported fail with [`USB2SnesError::Unsupported`].
+    pub fn info(&mut self) -> Result<Infos, USB2SnesError> {
         self.send_command(Command::Info, &[])?;
         let usbreply = self.get_reply()?;
-        let info = usbreply.Results;
-        Ok(Infos {
-            version: info[0].clone(),
-            dev_type: info[1].clone(),
-            game: info[2].clone(),
-            flags: info[3..].to_owned(),
-        })
+        let infos = Infos::parse(&usbreply.Results)?;
+        self.infos = Some(infos.clone());
+        Ok(infos)
     }
 
-    pub fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn reset(&mut self) -> Result<(), USB2SnesError> {
+        self.require(Command::Reset, Flag::NoControlCmd)?;
         self.send_command(Command::Reset, &[])
     }
 
-    pub fn menu(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn menu(&mut self) -> Result<(), USB2SnesError> {
+        self.require(Command::Menu, Flag::NoControlCmd)?;
         self.send_command(Command::Menu, &[])
     }
 
-    pub fn boot(&mut self, toboot: &str) -> Result<(), Box<dyn Error>> {
+    pub fn boot(&mut self, toboot: &str) -> Result<(), USB2SnesError> {
+        self.require(Command::Boot, Flag::NoControlCmd)?;
         self.send_command(Command::Boot, &[Cow::Borrowed(toboot)])
     }
 
-    pub fn ls(&mut self, path: &str) -> Result<Vec<USB2SnesFileInfo>, Box<dyn Error>> {
+    pub fn ls(&mut self, path: &str) -> Result<Vec<USB2SnesFileInfo>, USB2SnesError> {
+        self.require(Command::List, Flag::NoFileCmd)?;
         self.send_command(Command::List, &[Cow::Borrowed(path)])?;
         let usbreply = self.get_reply()?;
-        let vec_info = usbreply.Results;
-        let mut toret: Vec<USB2SnesFileInfo> = vec![];
-        let mut i = 0;
-        while i < vec_info.len() {
-            let info: USB2SnesFileInfo = USB2SnesFileInfo {
-                file_type: if &*vec_info[i] == "1" {
-                    USB2SnesFileType::File
-                } else {
-                    USB2SnesFileType::Dir
-                },
-                name: vec_info[i + 1].clone(),
-            };
-            toret.push(info);
-            i += 2;
-        }
-        Ok(toret)
+        USB2SnesFileInfo::parse_list(&usbreply.Results)
     }
 
-    pub fn send_file(&mut self, path: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    pub fn send_file(&mut self, path: &str, data: &[u8]) -> Result<(), USB2SnesError> {
+        self.require(Command::PutFile, Flag::NoFileCmd)?;
         self.send_command(
             Command::PutFile,
             &[Cow::Borrowed(path), Cow::Owned(format!("{:x}", data.len()))],
         )?;
-        let mut start = 0;
-        let mut stop = 1024;
-        while start < data.len() {
-            self.client.send(Message::binary(&data[start..stop]))?;
-            start += 1024;
-            stop += 1024;
-            if stop > data.len() {
-                stop = data.len();
-            }
-        }
-        Ok(())
+        self.send_binary(data)
     }
 
-    pub fn get_file(&mut self, path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    pub fn get_file(&mut self, path: &str) -> Result<Vec<u8>, USB2SnesError> {
+        self.require(Command::GetFile, Flag::NoFileCmd)?;
         self.send_command(Command::GetFile, &[Cow::Borrowed(path)])?;
-        let string_hex = self.get_reply()?.Results[0].to_string();
-        let size = usize::from_str_radix(&string_hex, 16)?;
-        let mut data: Vec<u8> = Vec::with_capacity(size);
-        loop {
-            let reply = self.client.read()?;
-            match reply {
-                Message::Binary(msgdata) => {
-                    data.extend(&msgdata);
-                }
-                _ => Err("Error getting a reply")?,
-            }
-            if data.len() == size {
-                break;
-            }
-        }
-        Ok(data)
+        let string_hex = self.get_reply()?.single(Command::GetFile)?;
+        let size = usize::from_str_radix(&string_hex, 16)
+            .map_err(|_| USB2SnesError::Malformed(format!("{:?} isn't a file size", string_hex)))?;
+        self.read_binary(size)
     }
 
-    pub fn remove_path(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+    pub fn rename(&mut self, path: &str, new_name: &str) -> Result<(), USB2SnesError> {
+        self.require(Command::Rename, Flag::NoFileCmd)?;
+        self.send_command(
+            Command::Rename,
+            &[Cow::Borrowed(path), Cow::Borrowed(new_name)],
+        )
+    }
+
+    pub fn remove_path(&mut self, path: &str) -> Result<(), USB2SnesError> {
+        self.require(Command::Remove, Flag::NoFileCmd)?;
         self.send_command(Command::Remove, &[Cow::Borrowed(path)])
     }
 
-    pub fn get_address(&mut self, address: u32, size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    pub fn make_dir(&mut self, path: &str) -> Result<(), USB2SnesError> {
+        self.require(Command::MakeDir, Flag::NoFileCmd)?;
+        self.send_command(Command::MakeDir, &[Cow::Borrowed(path)])
+    }
+
+    pub fn get_address(&mut self, address: u32, size: usize) -> Result<Vec<u8>, USB2SnesError> {
+        self.get_address_in(Space::SNES, address, size)
+    }
+
+    pub fn get_address_in(
+        &mut self,
+        space: Space,
+        address: u32,
+        size: usize,
+    ) -> Result<Vec<u8>, USB2SnesError> {
         self.send_command_with_space(
             Command::GetAddress,
-            Some(Space::SNES),
+            Some(space),
             &[
                 Cow::Owned(format!("{:x}", address)),
                 Cow::Owned(format!("{:x}", size)),
             ],
         )?;
-        let mut data: Vec<u8> = Vec::with_capacity(size);
-        loop {
-            let reply = self.client.read()?;
-            match reply {
-                Message::Binary(msgdata) => {
-                    data.extend(&msgdata);
-                }
-                _ => Err("Error getting a reply")?,
-            }
-            if data.len() == size {
-                break;
-            }
+        self.read_binary(size)
+    }
+
+    /// Write `data` to the SNES starting at `address`. Nothing comes back,
+    /// so a failed write only shows up in what's read afterwards.
+    pub fn put_address(&mut self, address: u32, data: &[u8]) -> Result<(), USB2SnesError> {
+        self.put_address_in(Space::SNES, address, data)
+    }
+
+    pub fn put_address_in(
+        &mut self,
+        space: Space,
+        address: u32,
+        data: &[u8],
+    ) -> Result<(), USB2SnesError> {
+        if space == Space::SNES && address < ROM_END {
+            self.require(Command::PutAddress, Flag::NoRomWrite)?;
         }
-        Ok(data)
+        self.send_command_with_space(
+            Command::PutAddress,
+            Some(space),
+            &[
+                Cow::Owned(format!("{:x}", address)),
+                Cow::Owned(format!("{:x}", data.len())),
+            ],
+        )?;
+        self.send_binary(data)
     }
 
-    pub fn get_addresses(
+    /// Apply an IPS patch to the SNES memory. `name` is what the server
+    /// calls the patch in its logs.
+    pub fn put_ips(&mut self, name: &str, patch: &[u8]) -> Result<(), USB2SnesError> {
+        self.send_command_with_space(
+            Command::PutIPS,
+            Some(Space::SNES),
+            &[
+                Cow::Borrowed(name),
+                Cow::Owned(format!("{:x}", patch.len())),
+            ],
+        )?;
+        self.send_binary(patch)
+    }
+    /// Read several ranges, split into as many requests as the device
+    /// limits call for. Ranges longer than [`MAX_OPERAND_SIZE`] get a
+    /// request to themselves.
+    pub fn get_addresses(&mut self, pairs: &[(u32, usize)]) -> Result<Vec<Vec<u8>>, USB2SnesError> {
+        let mut ret: Vec<Vec<u8>> = Vec::with_capacity(pairs.len());
+        let mut rest = pairs;
+        while !rest.is_empty() {
+            let count = if rest[0].1 > MAX_OPERAND_SIZE {
+                1
+            } else {
+                rest.iter()
+                    .take(MAX_OPERANDS)
+                    .take_while(|&&(_, size)| size <= MAX_OPERAND_SIZE)
+                    .count()
+            };
+            ret.extend(self.get_addresses_once(&rest[..count])?);
+            rest = &rest[count..];
+        }
+        Ok(ret)
+    }
+
+    fn get_addresses_once(
         &mut self,
         pairs: &[(u32, usize)],
-    ) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    ) -> Result<Vec<Vec<u8>>, USB2SnesError> {
         let mut args = Vec::with_capacity(pairs.len() * 2);
         let mut total_size = 0;
         for &(address, size) in pairs.iter() {
@@ -303,20 +728,8 @@ impl SyncClient {
             total_size += size;
         }
         self.send_command_with_space(Command::GetAddress, Some(Space::SNES), &args)?;
-        let mut data: Vec<u8> = Vec::with_capacity(total_size);
+        let data = self.read_binary(total_size)?;
         let mut ret: Vec<Vec<u8>> = Vec::with_capacity(pairs.len());
-        loop {
-            let reply = self.client.read()?;
-            match reply {
-                Message::Binary(msgdata) => {
-                    data.extend(&msgdata);
-                }
-                _ => Err("Error getting a reply")?,
-            }
-            if data.len() == total_size {
-                break;
-            }
-        }
         let mut consumed = 0;
         for &(_address, size) in pairs.iter() {
             ret.push(data[consumed..consumed + size].into());
@@ -325,3 +738,114 @@ impl SyncClient {
         Ok(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use tungstenite::protocol::Role;
+
+    fn results(results: &[&str]) -> Vec<Rc<str>> {
+        results.iter().map(|&result| result.into()).collect()
+    }
+
+    /// A client that already knows `infos`, talking to a socket nobody
+    /// answers on. The listener has to outlive the client.
+    fn client_knowing(infos: Option<Infos>) -> (SyncClient, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let client = WebSocket::from_raw_socket(MaybeTlsStream::Plain(stream), Role::Client, None);
+        let client = SyncClient {
+            client,
+            devel: false,
+            infos,
+        };
+        (client, listener)
+    }
+
+    #[test]
+    fn infos_are_three_fields_and_then_flags() {
+        let infos = Infos::parse(&results(&[
+            "1.10.3",
+            "SD2SNES",
+            "/sm.sfc",
+            "NO_FILE_CMD",
+            "FEAT_X",
+        ]))
+        .unwrap();
+        assert_eq!(&*infos.version, "1.10.3");
+        assert_eq!(&*infos.dev_type, "SD2SNES");
+        assert_eq!(&*infos.game, "/sm.sfc");
+        assert!(infos.has(&Flag::NoFileCmd));
+        assert!(!infos.has(&Flag::NoRomWrite));
+        assert_eq!(infos.flags[1], Flag::Other("FEAT_X".into()));
+        assert!(Infos::parse(&results(&["1.10.3", "SD2SNES", "/sm.sfc"]))
+            .unwrap()
+            .flags
+            .is_empty());
+        assert!(matches!(
+            Infos::parse(&results(&["1.10.3", "SD2SNES"])),
+            Err(USB2SnesError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn lists_are_a_type_and_a_name_per_entry() {
+        let files =
+            USB2SnesFileInfo::parse_list(&results(&["0", "sd2snes", "1", "sm.sfc"])).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_type, USB2SnesFileType::Dir);
+        assert_eq!(&*files[0].name, "sd2snes");
+        assert_eq!(files[1].file_type, USB2SnesFileType::File);
+        assert_eq!(&*files[1].name, "sm.sfc");
+        assert!(USB2SnesFileInfo::parse_list(&[]).unwrap().is_empty());
+        assert!(matches!(
+            USB2SnesFileInfo::parse_list(&results(&["0", "sd2snes", "1"])),
+            Err(USB2SnesError::Malformed(_))
+        ));
+        assert!(matches!(
+            USB2SnesFileInfo::parse_list(&results(&["2", "sm.sfc"])),
+            Err(USB2SnesError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn single_needs_exactly_one_result() {
+        let reply = |json: &str| serde_json::from_str::<USB2SnesResult>(json).unwrap();
+        let version = reply(r#"{"Results": ["11.0"]}"#).single(Command::AppVersion);
+        assert_eq!(&*version.unwrap(), "11.0");
+        for json in [r#"{"Results": []}"#, r#"{"Results": ["11.0", "12.0"]}"#] {
+            assert!(matches!(
+                reply(json).single(Command::AppVersion),
+                Err(USB2SnesError::Malformed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn require_rejects_what_the_device_flagged() {
+        let infos = Infos::parse(&results(&[
+            "1.10.3",
+            "SD2SNES",
+            "/sm.sfc",
+            "NO_CONTROL_CMD",
+        ]))
+        .unwrap();
+        let (mut client, _listener) = client_knowing(Some(infos));
+        assert!(matches!(
+            client.require(Command::Reset, Flag::NoControlCmd),
+            Err(USB2SnesError::Unsupported {
+                command: Command::Reset,
+                flag: Flag::NoControlCmd
+            })
+        ));
+        assert!(client.require(Command::List, Flag::NoFileCmd).is_ok());
+        assert!(matches!(
+            client.reset(),
+            Err(USB2SnesError::Unsupported { .. })
+        ));
+        // Before asking, anything goes
+        let (client, _listener) = client_knowing(None);
+        assert!(client.require(Command::Reset, Flag::NoControlCmd).is_ok());
+    }
+}